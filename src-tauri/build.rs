@@ -1,3 +1,21 @@
 fn main() {
+    // Bake the OAuth client from .env into release builds so packaged apps work without one; debug
+    // and test builds read .env at runtime instead. Variables already set in the build environment
+    // take precedence over the file.
+    // Watched even while missing, so a .env created after the first build is picked up
+    println!("cargo:rerun-if-changed=../.env");
+    if std::env::var("PROFILE").as_deref() == Ok("release")
+        && let Ok(path) = dotenv::dotenv()
+    {
+        println!("cargo:rerun-if-changed={}", path.display());
+        for key in ["GOOGLE_CLIENT_ID", "GOOGLE_CLIENT_SECRET"] {
+            if let Ok(value) = std::env::var(key) {
+                println!("cargo:rustc-env={}={}", key, value);
+            }
+        }
+    }
+    println!("cargo:rerun-if-env-changed=GOOGLE_CLIENT_ID");
+    println!("cargo:rerun-if-env-changed=GOOGLE_CLIENT_SECRET");
+
     tauri_build::build()
 }
@@ -0,0 +1,160 @@
+use crate::db::Database;
+use crate::secrets::{self, SecretsState};
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, Utc};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::thread;
+
+const DIR: &str = "backups";
+const PREFIX: &str = "tasks-";
+const SUFFIX: &str = ".db";
+// Milliseconds keep a restore's safety copy from overwriting a backup taken the same second
+const TIME_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
+
+/// A scheduled backup is taken when the newest one is at least this old.
+pub const BACKUP_INTERVAL_HOURS: i64 = 24;
+// How often the scheduler checks, so a machine that sleeps overnight still gets its backup
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// How many backups survive pruning: the newest of each of the last `daily` days and of the
+/// last `weekly` ISO weeks that have a backup.
+#[derive(Debug, Clone, Copy)]
+pub struct Retention {
+    pub daily: usize,
+    pub weekly: usize,
+}
+
+pub const DEFAULT_RETENTION: Retention = Retention { daily: 7, weekly: 4 };
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    /// File name inside the backups directory, used to pick a backup to restore
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub size: u64,
+}
+
+pub fn dir(db: &Database) -> PathBuf {
+    db.dir().join(DIR)
+}
+
+fn created_at(name: &str) -> Option<DateTime<Utc>> {
+    let stamp = name.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)?;
+    NaiveDateTime::parse_from_str(stamp, TIME_FORMAT).ok().map(|t| t.and_utc())
+}
+
+/// Backups in the backups directory, newest first. Files that don't look like ours are ignored.
+pub fn list(db: &Database) -> Result<Vec<BackupInfo>, String> {
+    let dir = dir(db);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(&dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if let Some(created_at) = created_at(&name) {
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            backups.push(BackupInfo { name, created_at, size });
+        }
+    }
+    backups.sort_by_key(|b| std::cmp::Reverse(b.created_at));
+    Ok(backups)
+}
+
+/// Snapshots the database into the backups directory.
+pub fn create(db: &Database, now: DateTime<Utc>) -> Result<BackupInfo, String> {
+    let dir = dir(db);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let name = format!("{}{}{}", PREFIX, now.format(TIME_FORMAT), SUFFIX);
+    let path = dir.join(&name);
+    db.backup_to(&path).map_err(|e| format!("Backup failed: {}", e))?;
+    let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    let created_at = created_at(&name).unwrap_or(now);
+    Ok(BackupInfo { name, created_at, size })
+}
+
+/// Names of the backups `retention` keeps, given backups sorted newest first.
+pub fn retained(backups: &[BackupInfo], retention: Retention) -> HashSet<String> {
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut keep = HashSet::new();
+    for backup in backups {
+        let date = backup.created_at.date_naive();
+        let new_day = days.len() < retention.daily && days.insert(date);
+        let new_week = weeks.len() < retention.weekly && weeks.insert(date.iso_week());
+        if new_day || new_week {
+            keep.insert(backup.name.clone());
+        }
+    }
+    keep
+}
+
+/// Deletes backups outside `retention`, returning how many were removed.
+pub fn prune(db: &Database, retention: Retention) -> Result<usize, String> {
+    let backups = list(db)?;
+    let keep = retained(&backups, retention);
+    let dir = dir(db);
+    let mut removed = 0;
+    for backup in backups.iter().filter(|b| !keep.contains(&b.name)) {
+        std::fs::remove_file(dir.join(&backup.name)).map_err(|e| format!("Failed to remove {}: {}", backup.name, e))?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Takes a backup and prunes old ones when the newest backup is older than the interval.
+pub fn backup_if_due(db: &Database, now: DateTime<Utc>) -> Result<Option<BackupInfo>, String> {
+    let latest = list(db)?.into_iter().next();
+    if latest.is_some_and(|b| now - b.created_at < Duration::hours(BACKUP_INTERVAL_HOURS)) {
+        return Ok(None);
+    }
+    let backup = create(db, now)?;
+    prune(db, DEFAULT_RETENTION)?;
+    Ok(Some(backup))
+}
+
+/// Checks for a due backup now and then every hour for the lifetime of the app.
+pub fn start_scheduler(db: Database) {
+    thread::spawn(move || loop {
+        if let Err(e) = backup_if_due(&db, Utc::now()) {
+            eprintln!("Scheduled backup failed: {}", e);
+        }
+        thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+fn integrity_check(path: &std::path::Path) -> Result<(), String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare("PRAGMA integrity_check").map_err(|e| e.to_string())?;
+    let problems: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<_>>()
+        .map_err(|e| e.to_string())?;
+    if problems != ["ok"] {
+        return Err(problems.join("; "));
+    }
+    Ok(())
+}
+
+/// Replaces the database with backup `name` after it passes `PRAGMA integrity_check`.
+/// The current data is backed up first, so a restore can itself be undone.
+pub fn restore(db: &Database, name: &str, now: DateTime<Utc>) -> Result<BackupInfo, String> {
+    // Only names from the listing, never a path from the caller
+    if !list(db)?.iter().any(|b| b.name == name) {
+        return Err(format!("Backup {} not found", name));
+    }
+    let path = dir(db).join(name);
+    integrity_check(&path).map_err(|e| format!("Backup {} failed the integrity check: {}", name, e))?;
+
+    let safety = create(db, now)?;
+    db.restore_from(&path).map_err(|e| format!("Restore failed: {}", e))?;
+    // The restored file may be sealed with another backend or salt than the key in memory
+    if !matches!(db.secrets_state(), SecretsState::Disabled) {
+        secrets::open(db).map_err(|e| format!("Backup restored, but its secrets could not be opened: {}", e))?;
+    }
+    Ok(safety)
+}
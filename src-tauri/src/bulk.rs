@@ -0,0 +1,64 @@
+use crate::db::{Database, Task, TaskPatch};
+
+// Ids without duplicates, in the given order
+fn selection(ids: &[String]) -> Result<Vec<String>, String> {
+    if ids.is_empty() {
+        return Err("No tasks selected".to_string());
+    }
+    let mut seen = std::collections::HashSet::new();
+    Ok(ids.iter().filter(|id| seen.insert(id.as_str())).cloned().collect())
+}
+
+fn count(ids: &[String]) -> String {
+    match ids.len() {
+        1 => "1 task".to_string(),
+        n => format!("{} tasks", n),
+    }
+}
+
+pub fn update(db: &Database, ids: &[String], patch: &TaskPatch) -> Result<Vec<Task>, String> {
+    let ids = &selection(ids)?;
+    let label = match patch.completed {
+        Some(true) => format!("Complete {}", count(ids)),
+        Some(false) => format!("Reopen {}", count(ids)),
+        None => format!("Edit {}", count(ids)),
+    };
+    db.bulk_update(ids, &label, |task| patch.apply(task)).map_err(|e| e.to_string())
+}
+
+pub fn delete(db: &Database, ids: &[String]) -> Result<Vec<Task>, String> {
+    let ids = &selection(ids)?;
+    db.bulk_delete(ids, &format!("Delete {}", count(ids))).map_err(|e| e.to_string())
+}
+
+/// Lists are categories, so moving sets the category.
+pub fn move_to_list(db: &Database, ids: &[String], category: &str) -> Result<Vec<Task>, String> {
+    let ids = &selection(ids)?;
+    let category = category.trim();
+    if category.is_empty() {
+        return Err("List name is empty".to_string());
+    }
+    let label = format!("Move {} to {}", count(ids), category);
+    db.bulk_update(ids, &label, |task| task.category = category.to_string()).map_err(|e| e.to_string())
+}
+
+/// Adds `add` to every task that doesn't have them yet and removes `remove`. A leading '#' is ignored.
+pub fn tag(db: &Database, ids: &[String], add: &[String], remove: &[String]) -> Result<Vec<Task>, String> {
+    let ids = &selection(ids)?;
+    let clean = |tags: &[String]| -> Vec<String> {
+        tags.iter().map(|tag| tag.trim().trim_start_matches('#').to_string()).collect()
+    };
+    let (add, remove) = (clean(add), clean(remove));
+    if add.is_empty() && remove.is_empty() {
+        return Err("No tags to add or remove".to_string());
+    }
+    db.bulk_update(ids, &format!("Tag {}", count(ids)), |task| {
+        task.tags.retain(|tag| !remove.contains(tag));
+        for tag in &add {
+            if !task.tags.contains(tag) {
+                task.tags.push(tag.clone());
+            }
+        }
+    })
+    .map_err(|e| e.to_string())
+}
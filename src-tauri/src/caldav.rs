@@ -0,0 +1,640 @@
+use crate::db::{new_task_id, Account, AccountTokens, Database, RemoteResource, Task};
+use crate::google::{DisconnectSummary, ImportedTaskAction};
+use crate::ical;
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use url::Url;
+
+const PROVIDER: &str = "caldav";
+
+const DAV_NS: &str = "DAV:";
+const CALDAV_NS: &str = "urn:ietf:params:xml:ns:caldav";
+// Apple's extension namespace, home of the collection ctag
+const CALENDARSERVER_NS: &str = "http://calendarserver.org/ns/";
+
+// Account settings
+const SERVER_URL_SETTING: &str = "server_url";
+const HOME_SETTING: &str = "calendar_home";
+const CTAG_PREFIX: &str = "ctag:";
+const SYNC_TOKEN_PREFIX: &str = "sync_token:";
+
+// Resources fetched per calendar-multiget REPORT
+const MULTIGET_BATCH: usize = 50;
+
+/// Calendar collection on the server that can hold to-dos.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collection {
+    pub href: String,
+    pub display_name: String,
+    pub ctag: Option<String>,
+    pub sync_token: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyncSummary {
+    pub added: usize,
+    pub updated: usize,
+    /// Local tasks removed because their resource was deleted on the server
+    pub deleted: usize,
+    /// Local changes and deletions uploaded to the server
+    pub pushed: usize,
+    /// Local changes dropped because the server copy changed too (the server wins)
+    pub conflicts: usize,
+    /// Resources without a usable VTODO
+    pub skipped: usize,
+}
+
+// One `DAV:response` of a multistatus body, with the properties we ask for.
+// Only properties reported with a 2xx propstat are filled in.
+#[derive(Debug, Default)]
+struct DavResponse {
+    href: String,
+    // Response-level status, used by sync-collection for removed members
+    status: Option<u16>,
+    etag: Option<String>,
+    display_name: Option<String>,
+    ctag: Option<String>,
+    sync_token: Option<String>,
+    calendar_data: Option<String>,
+    is_calendar: bool,
+    // None when the server doesn't restrict the component types
+    components: Option<Vec<String>>,
+    principal: Option<String>,
+    calendar_home: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Multistatus {
+    responses: Vec<DavResponse>,
+    sync_token: Option<String>,
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, ns: &str, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name((ns, name)))
+}
+
+fn text(node: Node) -> String {
+    node.text().unwrap_or_default().trim().to_string()
+}
+
+// "HTTP/1.1 200 OK" -> 200
+fn parse_status(node: Node) -> Option<u16> {
+    text(node).split_whitespace().nth(1)?.parse().ok()
+}
+
+fn parse_multistatus(body: &str) -> Result<Multistatus, String> {
+    let doc = Document::parse(body).map_err(|e| format!("Invalid multistatus response: {}", e))?;
+    let root = doc.root_element();
+    if !root.has_tag_name((DAV_NS, "multistatus")) {
+        return Err("Invalid multistatus response: missing DAV:multistatus".to_string());
+    }
+
+    let mut multistatus = Multistatus {
+        sync_token: child(root, DAV_NS, "sync-token").map(text),
+        ..Default::default()
+    };
+    for node in root.children().filter(|n| n.has_tag_name((DAV_NS, "response"))) {
+        let Some(href) = child(node, DAV_NS, "href").map(text) else {
+            continue;
+        };
+        let mut response = DavResponse {
+            href,
+            status: child(node, DAV_NS, "status").and_then(parse_status),
+            ..Default::default()
+        };
+
+        for propstat in node.children().filter(|n| n.has_tag_name((DAV_NS, "propstat"))) {
+            let ok = child(propstat, DAV_NS, "status")
+                .and_then(parse_status)
+                .is_some_and(|status| (200..300).contains(&status));
+            let Some(prop) = child(propstat, DAV_NS, "prop").filter(|_| ok) else {
+                continue;
+            };
+            for value in prop.children().filter(Node::is_element) {
+                let tag = value.tag_name();
+                match (tag.namespace().unwrap_or_default(), tag.name()) {
+                    (DAV_NS, "getetag") => response.etag = Some(text(value)),
+                    (DAV_NS, "displayname") => response.display_name = Some(text(value)),
+                    (DAV_NS, "sync-token") => response.sync_token = Some(text(value)),
+                    (CALENDARSERVER_NS, "getctag") => response.ctag = Some(text(value)),
+                    (CALDAV_NS, "calendar-data") => response.calendar_data = Some(text(value)),
+                    (DAV_NS, "resourcetype") => {
+                        response.is_calendar = child(value, CALDAV_NS, "calendar").is_some();
+                    }
+                    (CALDAV_NS, "supported-calendar-component-set") => {
+                        response.components = Some(
+                            value
+                                .children()
+                                .filter(|n| n.has_tag_name((CALDAV_NS, "comp")))
+                                .filter_map(|n| n.attribute("name"))
+                                .map(|name| name.to_ascii_uppercase())
+                                .collect(),
+                        );
+                    }
+                    (DAV_NS, "current-user-principal") => {
+                        response.principal = child(value, DAV_NS, "href").map(text);
+                    }
+                    (CALDAV_NS, "calendar-home-set") => {
+                        response.calendar_home = child(value, DAV_NS, "href").map(text);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        multistatus.responses.push(response);
+    }
+    Ok(multistatus)
+}
+
+// Hrefs in responses are usually absolute paths; everything is stored as a full URL
+fn resolve(base: &str, href: &str) -> Result<String, String> {
+    let base = Url::parse(base).map_err(|e| format!("Invalid URL {}: {}", base, e))?;
+    base.join(href)
+        .map(String::from)
+        .map_err(|e| format!("Invalid href {}: {}", href, e))
+}
+
+// Servers expect the path form of hrefs in request bodies
+fn href_path(url: &str) -> String {
+    Url::parse(url).map(|u| u.path().to_string()).unwrap_or_else(|_| url.to_string())
+}
+
+fn same_resource(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
+struct Session {
+    client: Client,
+    username: String,
+    password: String,
+}
+
+impl Session {
+    fn new(username: &str, password: &str) -> Self {
+        Self { client: Client::new(), username: username.to_string(), password: password.to_string() }
+    }
+
+    fn request(&self, method: &str, url: &str) -> RequestBuilder {
+        let method = Method::from_bytes(method.as_bytes()).expect("valid HTTP method");
+        self.client.request(method, url).basic_auth(&self.username, Some(&self.password))
+    }
+
+    async fn send_xml(&self, method: &str, url: &str, depth: &str, body: String) -> Result<Response, String> {
+        self.request(method, url)
+            .header("Depth", depth)
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn multistatus(&self, method: &str, url: &str, depth: &str, body: String) -> Result<Multistatus, String> {
+        let res = self.send_xml(method, url, depth, body).await?;
+        read_multistatus(res).await
+    }
+}
+
+async fn read_multistatus(res: Response) -> Result<Multistatus, String> {
+    let status = res.status();
+    if status == StatusCode::UNAUTHORIZED {
+        return Err("CalDAV server rejected the username or password".to_string());
+    }
+    if status != StatusCode::MULTI_STATUS {
+        return Err(format!("CalDAV error {}: {:?}", status, res.text().await));
+    }
+    parse_multistatus(&res.text().await.map_err(|e| e.to_string())?)
+}
+
+async fn find_principal(session: &Session, server_url: &str) -> Result<String, String> {
+    let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:current-user-principal/></d:prop></d:propfind>"#;
+    let multistatus = session.multistatus("PROPFIND", server_url, "0", body.to_string()).await?;
+    // Servers without current-user-principal (RFC 5397) are addressed through the given URL
+    match multistatus.responses.into_iter().find_map(|r| r.principal) {
+        Some(href) => resolve(server_url, &href),
+        None => Ok(server_url.to_string()),
+    }
+}
+
+async fn find_calendar_home(session: &Session, principal: &str) -> Result<String, String> {
+    let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav"><d:prop><c:calendar-home-set/></d:prop></d:propfind>"#;
+    let multistatus = session.multistatus("PROPFIND", principal, "0", body.to_string()).await?;
+    let href = multistatus
+        .responses
+        .into_iter()
+        .find_map(|r| r.calendar_home)
+        .ok_or("Server did not report a calendar home")?;
+    resolve(principal, &href)
+}
+
+/// Discovers the principal and calendar home behind `server_url` and stores the account.
+/// The password (ideally an app password) is kept with the other account secrets.
+pub async fn connect(db: &Database, server_url: &str, username: &str, password: &str) -> Result<Account, String> {
+    let server_url = server_url.trim();
+    let parsed = Url::parse(server_url).map_err(|e| format!("Invalid server URL: {}", e))?;
+    let session = Session::new(username, password);
+
+    let principal = find_principal(&session, server_url).await?;
+    let home = find_calendar_home(&session, &principal).await?;
+
+    // The principal URL identifies the user on that server
+    let account = Account {
+        provider: PROVIDER.to_string(),
+        account_id: principal,
+        email: username.to_string(),
+        name: parsed.host_str().unwrap_or(server_url).to_string(),
+        picture: None,
+    };
+    db.upsert_account(&account).map_err(|e| e.to_string())?;
+    let tokens = AccountTokens { access_token: password.to_string(), refresh_token: None, expires_at: None };
+    db.set_account_tokens(PROVIDER, &account.account_id, &tokens).map_err(|e| e.to_string())?;
+    db.set_account_setting(PROVIDER, &account.account_id, SERVER_URL_SETTING, server_url)
+        .map_err(|e| e.to_string())?;
+    db.set_account_setting(PROVIDER, &account.account_id, HOME_SETTING, &home)
+        .map_err(|e| e.to_string())?;
+    Ok(account)
+}
+
+pub fn get_accounts(db: &Database) -> Result<Vec<Account>, String> {
+    db.get_accounts(PROVIDER).map_err(|e| e.to_string())
+}
+
+fn open_session(db: &Database, account_id: &str) -> Result<Session, String> {
+    let account = get_accounts(db)?
+        .into_iter()
+        .find(|a| a.account_id == account_id)
+        .ok_or("CalDAV account not found")?;
+    let tokens = db.get_account_tokens(PROVIDER, account_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("No CalDAV password found")?;
+    Ok(Session::new(&account.email, &tokens.access_token))
+}
+
+fn setting(db: &Database, account_id: &str, key: &str) -> Result<Option<String>, String> {
+    db.get_account_setting(PROVIDER, account_id, key).map_err(|e| e.to_string())
+}
+
+async fn list_collections(db: &Database, session: &Session, account_id: &str) -> Result<Vec<Collection>, String> {
+    let home = setting(db, account_id, HOME_SETTING)?.ok_or("CalDAV account has no calendar home")?;
+    let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav" xmlns:cs="http://calendarserver.org/ns/">
+<d:prop><d:resourcetype/><d:displayname/><c:supported-calendar-component-set/><cs:getctag/><d:sync-token/></d:prop>
+</d:propfind>"#;
+    let multistatus = session.multistatus("PROPFIND", &home, "1", body.to_string()).await?;
+
+    let mut collections = Vec::new();
+    for response in multistatus.responses {
+        let supports_todos = response.components.as_ref().is_none_or(|c| c.iter().any(|c| c == "VTODO"));
+        if !response.is_calendar || !supports_todos {
+            continue;
+        }
+        let href = resolve(&home, &response.href)?;
+        let display_name = response
+            .display_name
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| href.trim_end_matches('/').rsplit('/').next().unwrap_or_default().to_string());
+        collections.push(Collection { href, display_name, ctag: response.ctag, sync_token: response.sync_token });
+    }
+    Ok(collections)
+}
+
+/// Calendar collections of the account that support VTODO.
+pub async fn fetch_collections(db: &Database, account_id: &str) -> Result<Vec<Collection>, String> {
+    let session = open_session(db, account_id)?;
+    list_collections(db, &session, account_id).await
+}
+
+// The task under the UID the server knows it by. That differs from the task id when the id was
+// already taken by another local task on pull.
+fn remote_copy(task: &Task, uid: &str) -> Task {
+    Task { id: uid.to_string(), ..task.clone() }
+}
+
+// The UID of the last synced copy, None for tasks that were never uploaded
+fn remote_uid(resource: &RemoteResource) -> Option<String> {
+    let parsed = ical::parse_tasks(&resource.synced).ok()?;
+    parsed.tasks.into_iter().next().map(|task| task.id)
+}
+
+// The task as it would be uploaded, with a fixed DTSTAMP so it can be compared between syncs
+fn snapshot(task: &Task, uid: &str) -> String {
+    ical::export_tasks(&[remote_copy(task, uid)], DateTime::<Utc>::UNIX_EPOCH)
+}
+
+fn etag_header(res: &Response) -> String {
+    res.headers()
+        .get("ETag")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+// Uploads local changes and deletions of the collection's tasks. A 412 means the server copy
+// changed since the last sync; the local change is dropped and the server copy pulled instead.
+async fn push_collection(db: &Database, session: &Session, account_id: &str, collection: &Collection, summary: &mut SyncSummary) -> Result<(), String> {
+    for mut resource in db.get_remote_resources(PROVIDER, account_id, &collection.href).map_err(|e| e.to_string())? {
+        let Some(task) = db.get_task_by_id(&resource.task_id).map_err(|e| e.to_string())? else {
+            if resource.synced.is_empty() {
+                // Never uploaded, nothing to delete on the server
+                db.delete_remote_resource(PROVIDER, account_id, &resource.href).map_err(|e| e.to_string())?;
+                continue;
+            }
+            let mut req = session.request("DELETE", &resource.href);
+            if !resource.etag.is_empty() {
+                req = req.header("If-Match", &resource.etag);
+            }
+            let res = req.send().await.map_err(|e| e.to_string())?;
+            match res.status() {
+                StatusCode::PRECONDITION_FAILED => summary.conflicts += 1,
+                StatusCode::NOT_FOUND | StatusCode::GONE => {}
+                status if status.is_success() => summary.pushed += 1,
+                status => return Err(format!("CalDAV error {}: {:?}", status, res.text().await)),
+            }
+            // On conflict the server copy comes back as a new task on pull
+            db.delete_remote_resource(PROVIDER, account_id, &resource.href).map_err(|e| e.to_string())?;
+            continue;
+        };
+
+        let uid = remote_uid(&resource).unwrap_or_else(|| task.id.clone());
+        let current = snapshot(&task, &uid);
+        if current == resource.synced {
+            continue;
+        }
+        let mut req = session
+            .request("PUT", &resource.href)
+            .header("Content-Type", "text/calendar; charset=utf-8")
+            .body(ical::export_tasks(&[remote_copy(&task, &uid)], Utc::now()));
+        req = if resource.synced.is_empty() {
+            req.header("If-None-Match", "*")
+        } else if !resource.etag.is_empty() {
+            req.header("If-Match", &resource.etag)
+        } else {
+            req
+        };
+        let res = req.send().await.map_err(|e| e.to_string())?;
+        match res.status() {
+            StatusCode::PRECONDITION_FAILED => {
+                summary.conflicts += 1;
+                // Forget the etag so the next pull downloads the server copy
+                resource.etag = String::new();
+                resource.synced = current;
+            }
+            status if status.is_success() => {
+                summary.pushed += 1;
+                // Without an ETag the next pull downloads our own upload once
+                resource.etag = etag_header(&res);
+                resource.synced = current;
+            }
+            status => return Err(format!("CalDAV error {}: {:?}", status, res.text().await)),
+        }
+        db.set_remote_resource(&resource).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// Changed members (href, etag) and removed hrefs since the stored sync token (RFC 6578).
+// None when the server doesn't accept the token, e.g. because it expired.
+async fn changes_since(session: &Session, collection: &Collection, token: &str) -> Result<Option<(Vec<(String, String)>, Vec<String>, Option<String>)>, String> {
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<d:sync-collection xmlns:d="DAV:"><d:sync-token>{}</d:sync-token><d:sync-level>1</d:sync-level><d:prop><d:getetag/></d:prop></d:sync-collection>"#,
+        xml_escape(token)
+    );
+    let res = session.send_xml("REPORT", &collection.href, "0", body).await?;
+    if matches!(res.status(), StatusCode::FORBIDDEN | StatusCode::CONFLICT | StatusCode::BAD_REQUEST) {
+        return Ok(None);
+    }
+    let multistatus = read_multistatus(res).await?;
+
+    let mut changed = Vec::new();
+    let mut removed = Vec::new();
+    for response in multistatus.responses {
+        let href = resolve(&collection.href, &response.href)?;
+        if same_resource(&href, &collection.href) {
+            continue;
+        }
+        match (response.status, response.etag) {
+            (Some(404), _) => removed.push(href),
+            (_, Some(etag)) => changed.push((href, etag)),
+            _ => {}
+        }
+    }
+    Ok(Some((changed, removed, multistatus.sync_token)))
+}
+
+// Every VTODO resource of the collection with its etag
+async fn list_members(session: &Session, collection: &Collection) -> Result<Vec<(String, String)>, String> {
+    let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav"><d:prop><d:getetag/></d:prop>
+<c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VTODO"/></c:comp-filter></c:filter></c:calendar-query>"#;
+    let multistatus = session.multistatus("REPORT", &collection.href, "1", body.to_string()).await?;
+    let mut members = Vec::new();
+    for response in multistatus.responses {
+        let href = resolve(&collection.href, &response.href)?;
+        if let Some(etag) = response.etag
+            && !same_resource(&href, &collection.href)
+        {
+            members.push((href, etag));
+        }
+    }
+    Ok(members)
+}
+
+// Downloads (href, etag, calendar data) for the given resources
+async fn fetch_resources(session: &Session, collection: &Collection, hrefs: &[String]) -> Result<Vec<(String, String, String)>, String> {
+    let mut resources = Vec::new();
+    for batch in hrefs.chunks(MULTIGET_BATCH) {
+        let hrefs: String = batch
+            .iter()
+            .map(|href| format!("<d:href>{}</d:href>", xml_escape(&href_path(href))))
+            .collect();
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-multiget xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav"><d:prop><d:getetag/><c:calendar-data/></d:prop>{}</c:calendar-multiget>"#,
+            hrefs
+        );
+        let multistatus = session.multistatus("REPORT", &collection.href, "1", body).await?;
+        for response in multistatus.responses {
+            if let Some(data) = response.calendar_data {
+                let href = resolve(&collection.href, &response.href)?;
+                resources.push((href, response.etag.unwrap_or_default(), data));
+            }
+        }
+    }
+    Ok(resources)
+}
+
+// Stores a downloaded resource. The server owns everything in the VTODO; category and
+// order of an existing task stay local, like Google imports.
+fn apply_resource(db: &Database, account_id: &str, collection: &Collection, known: &HashMap<String, RemoteResource>, (href, etag, data): (String, String, String), summary: &mut SyncSummary) -> Result<(), String> {
+    let parsed = ical::parse_tasks_with_category(&data, &collection.display_name)?;
+    let Some(mut task) = parsed.tasks.into_iter().next() else {
+        summary.skipped += 1;
+        return Ok(());
+    };
+    if let Err(e) = task.validate() {
+        eprintln!("Skipping CalDAV resource {}: {}", href, e);
+        summary.skipped += 1;
+        return Ok(());
+    }
+
+    // Only the task this resource was synced to is updated; a local task that happens to use the
+    // same id (e.g. imported from elsewhere) is left alone and the server copy gets a new id
+    let uid = task.id.clone();
+    let linked = match known.get(&href) {
+        Some(resource) => db.get_task_by_id(&resource.task_id).map_err(|e| e.to_string())?,
+        None => None,
+    };
+    match linked {
+        Some(existing) => {
+            task.id = existing.id;
+            task.category = existing.category;
+            task.position = existing.position;
+            db.update_task(task.clone()).map_err(|e| e.to_string())?;
+            summary.updated += 1;
+        }
+        None => {
+            if db.get_task_by_id(&task.id).map_err(|e| e.to_string())?.is_some() {
+                task.id = new_task_id();
+            }
+            db.add_task(task.clone()).map_err(|e| e.to_string())?;
+            summary.added += 1;
+        }
+    }
+
+    // Compare against the task as stored, with the timestamps the database gave it
+    let task = db.get_task_by_id(&task.id).map_err(|e| e.to_string())?.ok_or("Synced task disappeared")?;
+    db.link_imported_task(&task.id, PROVIDER, account_id, &collection.href).map_err(|e| e.to_string())?;
+    db.set_remote_resource(&RemoteResource {
+        provider: PROVIDER.to_string(),
+        account_id: account_id.to_string(),
+        href,
+        collection: collection.href.clone(),
+        task_id: task.id.clone(),
+        etag,
+        synced: snapshot(&task, &uid),
+    })
+    .map_err(|e| e.to_string())
+}
+
+async fn pull_collection(db: &Database, session: &Session, account_id: &str, collection: &Collection, summary: &mut SyncSummary) -> Result<(), String> {
+    let ctag_key = format!("{}{}", CTAG_PREFIX, collection.href);
+    let token_key = format!("{}{}", SYNC_TOKEN_PREFIX, collection.href);
+    let known: HashMap<String, RemoteResource> = db
+        .get_remote_resources(PROVIDER, account_id, &collection.href)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|r| (r.href.clone(), r))
+        .collect();
+
+    // An unchanged ctag means nothing changed on the server since the last sync
+    let stale = known.values().any(|r| r.etag.is_empty() && !r.synced.is_empty());
+    if !stale && collection.ctag.is_some() && setting(db, account_id, &ctag_key)? == collection.ctag {
+        return Ok(());
+    }
+
+    let incremental = match setting(db, account_id, &token_key)? {
+        Some(token) if collection.sync_token.is_some() => changes_since(session, collection, &token).await?,
+        _ => None,
+    };
+    let (members, removed, sync_token) = match incremental {
+        Some(changes) => changes,
+        None => {
+            let members = list_members(session, collection).await?;
+            let removed = known
+                .keys()
+                .filter(|href| !members.iter().any(|(member, _)| member == *href))
+                .cloned()
+                .collect();
+            (members, removed, collection.sync_token.clone())
+        }
+    };
+
+    let mut outdated: Vec<String> = members
+        .into_iter()
+        .filter(|(href, etag)| known.get(href).is_none_or(|r| r.etag.is_empty() || &r.etag != etag))
+        .map(|(href, _)| href)
+        .collect();
+    // Uploads the server didn't return an ETag for are checked once
+    for resource in known.values() {
+        if resource.etag.is_empty() && !resource.synced.is_empty() && !outdated.contains(&resource.href) && !removed.contains(&resource.href) {
+            outdated.push(resource.href.clone());
+        }
+    }
+
+    for resource in fetch_resources(session, collection, &outdated).await? {
+        apply_resource(db, account_id, collection, &known, resource, summary)?;
+    }
+
+    for href in removed {
+        // Tasks that were never uploaded have no server copy to lose
+        let Some(resource) = known.get(&href).filter(|r| !r.synced.is_empty()) else {
+            continue;
+        };
+        db.delete_task(&resource.task_id).map_err(|e| e.to_string())?;
+        db.delete_remote_resource(PROVIDER, account_id, &href).map_err(|e| e.to_string())?;
+        summary.deleted += 1;
+    }
+
+    if let Some(ctag) = &collection.ctag {
+        db.set_account_setting(PROVIDER, account_id, &ctag_key, ctag).map_err(|e| e.to_string())?;
+    }
+    if let Some(token) = sync_token {
+        db.set_account_setting(PROVIDER, account_id, &token_key, &token).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Two-way sync of every to-do collection of the account. Local changes are uploaded first
+/// (with If-Match, so concurrent server edits win), then server changes are downloaded using
+/// the collection's ctag and sync token, falling back to comparing etags of all members.
+pub async fn sync(db: &Database, account_id: &str) -> Result<SyncSummary, String> {
+    let session = open_session(db, account_id)?;
+    let mut summary = SyncSummary::default();
+    for collection in list_collections(db, &session, account_id).await? {
+        push_collection(db, &session, account_id, &collection, &mut summary).await?;
+        pull_collection(db, &session, account_id, &collection, &mut summary).await?;
+    }
+    Ok(summary)
+}
+
+/// Marks a local task to be created in the collection on the next sync.
+pub fn add_task(db: &Database, account_id: &str, collection_href: &str, task_id: &str) -> Result<(), String> {
+    db.get_task_by_id(task_id).map_err(|e| e.to_string())?.ok_or("Task not found")?;
+    let mut url = Url::parse(collection_href).map_err(|e| format!("Invalid collection URL: {}", e))?;
+    url.path_segments_mut()
+        .map_err(|_| "Invalid collection URL")?
+        .pop_if_empty()
+        .push(&format!("{}.ics", task_id));
+
+    db.link_imported_task(task_id, PROVIDER, account_id, collection_href).map_err(|e| e.to_string())?;
+    db.set_remote_resource(&RemoteResource {
+        provider: PROVIDER.to_string(),
+        account_id: account_id.to_string(),
+        href: url.into(),
+        collection: collection_href.to_string(),
+        task_id: task_id.to_string(),
+        etag: String::new(),
+        synced: String::new(),
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Removes the account and its sync state. Nothing is deleted on the server.
+pub fn disconnect(db: &Database, account_id: &str, imported: ImportedTaskAction) -> Result<DisconnectSummary, String> {
+    let (tasks_detached, tasks_deleted) = db.delete_account(PROVIDER, account_id, imported).map_err(|e| e.to_string())?;
+    Ok(DisconnectSummary { token_revoked: false, tasks_detached, tasks_deleted })
+}
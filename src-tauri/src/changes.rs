@@ -0,0 +1,86 @@
+use crate::db::Database;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::thread;
+
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// What a write did to tasks, passed to the listener set with `Database::set_change_listener`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Updated,
+    Deleted,
+    Reordered,
+    /// Written by another process or replaced wholesale, reload everything
+    External,
+}
+
+/// Sent to the frontend as the "tasks-changed" event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaskChange {
+    pub kind: ChangeKind,
+    /// Tasks affected, empty for `External`
+    pub ids: Vec<String>,
+}
+
+/// Notices tasks written by other processes (another app instance, a script using sqlite3).
+/// `PRAGMA data_version` on a long-lived connection changes whenever any other connection commits,
+/// including this app's own, so it only says when to look. Triggers count every task and dependency
+/// row change in `task_writes.total` and the app's own in `task_writes.local`; a change is external
+/// when the difference between the two moved, even if the app wrote in the same poll.
+pub struct ExternalChangeWatcher {
+    db: Database,
+    conn: Connection,
+    version: i64,
+    external_writes: i64,
+}
+
+fn data_version(conn: &Connection) -> Result<i64, String> {
+    conn.query_row("PRAGMA data_version", [], |row| row.get(0)).map_err(|e| e.to_string())
+}
+
+fn external_writes(conn: &Connection) -> Result<i64, String> {
+    conn.query_row("SELECT total - local FROM task_writes", [], |row| row.get(0)).map_err(|e| e.to_string())
+}
+
+impl ExternalChangeWatcher {
+    pub fn new(db: Database) -> Result<Self, String> {
+        let conn = Connection::open(db.path()).map_err(|e| e.to_string())?;
+        Ok(Self { version: data_version(&conn)?, external_writes: external_writes(&conn)?, db, conn })
+    }
+
+    /// Reports an `External` change to the listener if tasks were changed by someone else since the last check.
+    pub fn check(&mut self) -> Result<bool, String> {
+        let version = data_version(&self.conn)?;
+        if version == self.version {
+            return Ok(false);
+        }
+        self.version = version;
+
+        let external_writes = external_writes(&self.conn)?;
+        let external = external_writes != self.external_writes;
+        if external {
+            self.db.notify(ChangeKind::External, Vec::new());
+        }
+        self.external_writes = external_writes;
+        Ok(external)
+    }
+}
+
+pub fn start_watcher(db: Database) {
+    let mut watcher = match ExternalChangeWatcher::new(db) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("Failed to watch for external changes: {}", e);
+            return;
+        }
+    };
+    thread::spawn(move || loop {
+        thread::sleep(POLL_INTERVAL);
+        if let Err(e) = watcher.check() {
+            eprintln!("Failed to check for external changes: {}", e);
+        }
+    });
+}
@@ -0,0 +1,181 @@
+use crate::db::{Database, Task, SECRET_SETTINGS};
+use crate::{feed, secrets};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Marks JSON files written by `export`, so other JSON isn't mistaken for a backup.
+const APP: &str = "tasker";
+
+/// Version of the JSON document. Bump it when a field changes meaning and teach `import`
+/// to read the older layout.
+pub const DATA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataFormat {
+    /// Tasks only, one row per task with the `Task` fields as columns
+    Csv,
+    /// Versioned `DataExport` document
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Add new tasks and update those with the same id
+    Merge,
+    /// Make the task list match the file, deleting tasks it doesn't contain
+    Replace,
+}
+
+/// Whole-database JSON export. Categories travel with their tasks; accounts, sync state and
+/// calendar events are tied to this machine and left out.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DataExport {
+    pub app: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub tasks: Vec<Task>,
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DataImportSummary {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    pub settings: usize,
+}
+
+// Sealed secrets, the feed token and the secrets key setup stay on this machine
+fn is_secret_setting(key: &str) -> bool {
+    SECRET_SETTINGS.contains(&key) || secrets::KEY_SETTINGS.contains(&key) || key == feed::TOKEN_SETTING
+}
+
+// `Task` fields in file order. A new field goes at the end so older files still line up.
+const CSV_COLUMNS: &[&str] = &[
+    "id",
+    "title",
+    "description",
+    "completed",
+    "priority",
+    "category",
+    "due_date",
+    "due_time",
+    "timezone",
+    "start_date",
+    "recurrence",
+    "parent_id",
+    "tags",
+    "position",
+    "revision",
+    "created_at",
+    "updated_at",
+    "completed_at",
+    "blocked",
+];
+
+// csv can't write lists, so tags become one space-separated column
+const CSV_LIST_COLUMNS: &[&str] = &["tags"];
+// Columns read back as JSON values rather than text
+const CSV_TYPED_COLUMNS: &[&str] = &["completed", "position", "revision", "blocked"];
+
+fn export_csv(tasks: &[Task]) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(CSV_COLUMNS).map_err(|e| e.to_string())?;
+    for task in tasks {
+        let serde_json::Value::Object(fields) = serde_json::to_value(task).map_err(|e| e.to_string())? else {
+            return Err("Task is not a JSON object".to_string());
+        };
+        let record = CSV_COLUMNS.iter().map(|column| match fields.get(*column) {
+            None | Some(serde_json::Value::Null) => String::new(),
+            Some(serde_json::Value::String(text)) => text.clone(),
+            Some(serde_json::Value::Array(items)) => items.iter().filter_map(|i| i.as_str()).collect::<Vec<_>>().join(" "),
+            Some(other) => other.to_string(),
+        });
+        writer.write_record(record).map_err(|e| e.to_string())?;
+    }
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+fn parse_csv(input: &str) -> Result<Vec<Task>, String> {
+    let mut reader = csv::Reader::from_reader(input.as_bytes());
+    let header = reader.headers().map_err(|e| e.to_string())?.clone();
+    let mut tasks = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        // Line 1 is the header
+        let row = i + 2;
+        let record = record.map_err(|e| format!("Row {}: {}", row, e))?;
+        let mut fields = serde_json::Map::new();
+        for (column, cell) in header.iter().zip(record.iter()) {
+            let value = if CSV_LIST_COLUMNS.contains(&column) {
+                cell.split_whitespace().map(|item| serde_json::Value::String(item.to_string())).collect()
+            } else if cell.is_empty() {
+                serde_json::Value::Null
+            } else if CSV_TYPED_COLUMNS.contains(&column) {
+                serde_json::from_str(cell).map_err(|_| format!("Row {}: invalid {} {:?}", row, column, cell))?
+            } else {
+                serde_json::Value::String(cell.to_string())
+            };
+            fields.insert(column.to_string(), value);
+        }
+        tasks.push(serde_json::from_value(fields.into()).map_err(|e| format!("Row {}: {}", row, e))?);
+    }
+    Ok(tasks)
+}
+
+fn parse_json(input: &str) -> Result<DataExport, String> {
+    let value: serde_json::Value = serde_json::from_str(input).map_err(|e| format!("Invalid JSON: {}", e))?;
+    if value.get("app").and_then(|app| app.as_str()) != Some(APP) {
+        return Err("Not a tasker data export".to_string());
+    }
+    match value.get("version").and_then(|v| v.as_u64()) {
+        Some(version) if (1..=DATA_VERSION as u64).contains(&version) => {}
+        Some(version) => {
+            return Err(format!("Unsupported data version {} (this version of the app reads up to {})", version, DATA_VERSION));
+        }
+        None => return Err("Data export has no version".to_string()),
+    }
+    serde_json::from_value(value).map_err(|e| format!("Invalid data export: {}", e))
+}
+
+pub fn export(db: &Database, format: DataFormat) -> Result<String, String> {
+    let tasks = db.get_tasks().map_err(|e| e.to_string())?;
+    match format {
+        DataFormat::Csv => export_csv(&tasks),
+        DataFormat::Json => {
+            let settings = db
+                .get_settings()
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .filter(|(key, _)| !is_secret_setting(key))
+                .collect();
+            let export = DataExport { app: APP.to_string(), version: DATA_VERSION, exported_at: Utc::now(), tasks, settings };
+            serde_json::to_string_pretty(&export).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Reads a file written by `export`. Nothing is written unless the whole file is valid.
+pub fn import(db: &Database, input: &str, format: DataFormat, mode: ImportMode) -> Result<DataImportSummary, String> {
+    let (tasks, settings): (Vec<Task>, Vec<(String, String)>) = match format {
+        DataFormat::Csv => (parse_csv(input)?, Vec::new()),
+        DataFormat::Json => {
+            let export = parse_json(input)?;
+            let settings = export.settings.into_iter().filter(|(key, _)| !is_secret_setting(key)).collect();
+            (export.tasks, settings)
+        }
+    };
+
+    let mut ids = std::collections::HashSet::new();
+    if let Some(task) = tasks.iter().find(|task| !ids.insert(task.id.as_str())) {
+        return Err(format!("Task {} appears more than once", task.id));
+    }
+
+    let (added, updated, removed) =
+        db.import_data(&tasks, &settings, mode == ImportMode::Replace).map_err(|e| e.to_string())?;
+    Ok(DataImportSummary { added, updated, removed, settings: settings.len() })
+}
@@ -1,20 +1,650 @@
-use rusqlite::{params, Connection, Result};
-use serde::{Deserialize, Serialize};
+use crate::changes::{ChangeKind, TaskChange};
+use crate::google::ImportedTaskAction;
+use crate::query::SqlFilter;
+use crate::secrets::{self, SecretsState};
+use crate::smartlists::{SmartFilter, SmartSort};
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use rusqlite::{params, Connection, OptionalExtension, Result, Row, Transaction, TransactionBehavior};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Category of tasks that don't say where they belong, same as the frontend's quick add.
+pub const DEFAULT_CATEGORY: &str = "In-box";
+
+/// Settings holding secrets, sealed like account tokens: the OAuth client secret, and the tokens of
+/// the single-account Google version until `google::migrate_legacy_tokens` moves them into `accounts`.
+/// Read them with `get_secret_setting` and write them with `set_secret_setting`.
+pub const SECRET_SETTINGS: &[&str] = &["google_access_token", "google_refresh_token", "google_client_secret"];
+
+/// Id for tasks created on the backend, in the format of the frontend's `crypto.randomUUID()`.
+pub fn new_task_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// IANA name of the machine's timezone, "UTC" when it can't be determined.
+pub fn local_timezone() -> String {
+    iana_time_zone::get_timezone()
+        .ok()
+        .filter(|name| Tz::from_str(name).is_ok())
+        .unwrap_or_else(|| "UTC".to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
     pub id: String,
     pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
     pub completed: bool,
     pub priority: String,
     pub category: String,
-    pub due_date: Option<String>,
+    /// All-day due date, or the date of a timed due when `due_time` is set
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub due_date: Option<NaiveDate>,
+    /// Wall-clock due time in `timezone`, serialized as "HH:MM"
+    #[serde(default, deserialize_with = "empty_as_none", serialize_with = "serialize_time")]
+    pub due_time: Option<NaiveTime>,
+    /// IANA timezone name (e.g. "Asia/Seoul"), required with `due_time`
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub timezone: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub start_date: Option<NaiveDate>,
+    /// RFC 5545 recurrence rule without the "RRULE:" prefix, e.g. "FREQ=WEEKLY;BYDAY=MO"
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub recurrence: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub parent_id: Option<String>,
+    /// Free-form labels without the leading '#', e.g. ["work", "waiting"]
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub position: i32,
+    /// Bumped on every write; `patch_task` rejects a patch made against an older revision
+    #[serde(default)]
+    pub revision: i64,
+    /// Set by the backend when the task is added; values sent by the frontend are ignored
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// Set by the backend on every write
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Set by the backend when the task is completed, cleared when it is reopened
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Derived, not stored: a task this one depends on (`task_dependencies`) is still open
+    #[serde(default)]
+    pub blocked: bool,
+}
+
+/// Open, medium-priority task in the default list with nothing else set. The id is empty;
+/// set one with `new_task_id` or from the source the task comes from.
+impl Default for Task {
+    fn default() -> Self {
+        Task {
+            id: String::new(),
+            title: String::new(),
+            description: None,
+            completed: false,
+            priority: "Medium".to_string(),
+            category: DEFAULT_CATEGORY.to_string(),
+            due_date: None,
+            due_time: None,
+            timezone: None,
+            start_date: None,
+            recurrence: None,
+            parent_id: None,
+            tags: Vec::new(),
+            position: 0,
+            revision: 0,
+            created_at: None,
+            updated_at: None,
+            completed_at: None,
+            blocked: false,
+        }
+    }
+}
+
+/// Fields to change on a task, leaving the missing ones alone. On optional fields `null` (or "")
+/// clears the value, so `{"due_date": null}` removes the due date while `{}` keeps it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskPatch {
+    pub title: Option<String>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub description: Option<Option<String>>,
+    pub completed: Option<bool>,
+    pub priority: Option<String>,
+    pub category: Option<String>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub due_date: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub due_time: Option<Option<NaiveTime>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub timezone: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub start_date: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub recurrence: Option<Option<String>>,
+    #[serde(default, deserialize_with = "patch_field")]
+    pub parent_id: Option<Option<String>>,
+    pub tags: Option<Vec<String>>,
+}
+
+impl TaskPatch {
+    pub fn apply(&self, task: &mut Task) {
+        fn set<T: Clone>(field: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *field = value.clone();
+            }
+        }
+        set(&mut task.title, &self.title);
+        set(&mut task.description, &self.description);
+        set(&mut task.completed, &self.completed);
+        set(&mut task.priority, &self.priority);
+        set(&mut task.category, &self.category);
+        set(&mut task.due_date, &self.due_date);
+        set(&mut task.due_time, &self.due_time);
+        set(&mut task.timezone, &self.timezone);
+        set(&mut task.start_date, &self.start_date);
+        set(&mut task.recurrence, &self.recurrence);
+        set(&mut task.parent_id, &self.parent_id);
+        set(&mut task.tags, &self.tags);
+    }
+}
+
+/// Latest entry of the undo history, see `Database::undo`.
+#[derive(Debug, Clone, Serialize)]
+pub struct UndoEntry {
+    pub id: i64,
+    pub label: String,
+    pub created_at: String,
+}
+
+// Tasks (and reminders of deleted tasks) as they were before an undoable write
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UndoSnapshot {
+    tasks: Vec<Task>,
+    // Revision each task was left at by the write; deleted tasks have none
+    #[serde(default)]
+    revisions: HashMap<String, i64>,
+    #[serde(default)]
+    reminders: Vec<Reminder>,
+    #[serde(default)]
+    dependencies: Vec<Dependency>,
+}
+
+/// Undo entries kept; older ones are dropped.
+pub const UNDO_LIMIT: i64 = 50;
+
+/// When a task is due: a whole day, or an instant in the task's timezone.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Due {
+    AllDay(NaiveDate),
+    At(DateTime<Tz>),
+}
+
+impl Due {
+    pub fn date(&self) -> NaiveDate {
+        match self {
+            Due::AllDay(date) => *date,
+            Due::At(at) => at.date_naive(),
+        }
+    }
+}
+
+impl Task {
+    // The timestamps are the backend's to set, whatever the frontend sent back
+    fn without_timestamps(self) -> Task {
+        Task { created_at: None, updated_at: None, completed_at: None, ..self }
+    }
+
+    pub fn due(&self) -> std::result::Result<Option<Due>, String> {
+        let Some(date) = self.due_date else {
+            if self.due_time.is_some() {
+                return Err("A due time needs a due date".to_string());
+            }
+            return Ok(None);
+        };
+        let Some(time) = self.due_time else {
+            return Ok(Some(Due::AllDay(date)));
+        };
+
+        let tz_name = self.timezone.as_deref().ok_or("A due time needs a timezone")?;
+        let tz = Tz::from_str(tz_name).map_err(|_| format!("Unknown timezone: {}", tz_name))?;
+        // Ambiguous times (DST fall back) resolve to the first occurrence
+        let at = tz
+            .from_local_datetime(&date.and_time(time))
+            .earliest()
+            .ok_or_else(|| format!("{} {} does not exist in {}", date, time.format("%H:%M"), tz_name))?;
+        Ok(Some(Due::At(at)))
+    }
+
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if let Some(tz_name) = self.timezone.as_deref()
+            && Tz::from_str(tz_name).is_err()
+        {
+            return Err(format!("Unknown timezone: {}", tz_name));
+        }
+        self.due()?;
+        if let Some(rule) = self.recurrence.as_deref()
+            && !rule.to_ascii_uppercase().split(';').any(|part| part.starts_with("FREQ="))
+        {
+            return Err(format!("Invalid recurrence rule: {}", rule));
+        }
+        if self.parent_id.as_deref() == Some(self.id.as_str()) {
+            return Err("A task can't be its own parent".to_string());
+        }
+        if let (Some(start), Some(due)) = (self.start_date, self.due_date)
+            && start > due
+        {
+            return Err("Start date is after the due date".to_string());
+        }
+        if let Some(tag) = self.tags.iter().find(|tag| tag.is_empty() || tag.contains(char::is_whitespace) || tag.starts_with('#')) {
+            return Err(format!("Invalid tag: {:?}", tag));
+        }
+        Ok(())
+    }
+}
+
+// Date and time inputs send "" once cleared
+fn empty_as_none<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    match Option::<String>::deserialize(deserializer)?.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => value.parse().map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+// A field that is present, even as null, is Some
+fn patch_field<'de, D, T>(deserializer: D) -> std::result::Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    empty_as_none(deserializer).map(Some)
+}
+
+fn serialize_time<S: Serializer>(time: &Option<NaiveTime>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    match time {
+        Some(time) => serializer.serialize_str(&time.format("%H:%M").to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+const TASK_COLUMNS: &str =
+    "id, title, completed, priority, category, due_date, due_time, timezone, start_date, position, description, recurrence, parent_id, tags, revision, created_at, updated_at, completed_at";
+
+// Stored columns of `table` (the tasks table or its alias), then the derived `blocked` flag
+fn task_columns(table: &str) -> String {
+    let stored = TASK_COLUMNS.split(", ").map(|c| format!("{}.{}", table, c)).collect::<Vec<_>>().join(", ");
+    format!("{}, {} AS blocked", stored, blocked_sql(table))
+}
+
+// Task and dependency rows changed by any connection (`total`) and by this app's `WriteTx`s (`local`)
+const TASK_WRITES: &str = "
+    CREATE TABLE IF NOT EXISTS task_writes (total INTEGER NOT NULL, local INTEGER NOT NULL);
+    INSERT INTO task_writes (total, local) SELECT 0, 0 WHERE NOT EXISTS (SELECT 1 FROM task_writes);
+";
+
+// Triggers adding one to `task_writes.total` for every task or dependency row changed, by any connection
+const WRITE_COUNTERS: &str = "
+    CREATE TRIGGER IF NOT EXISTS count_total_tasks_insert AFTER INSERT ON tasks BEGIN UPDATE task_writes SET total = total + 1; END;
+    CREATE TRIGGER IF NOT EXISTS count_total_tasks_update AFTER UPDATE ON tasks BEGIN UPDATE task_writes SET total = total + 1; END;
+    CREATE TRIGGER IF NOT EXISTS count_total_tasks_delete AFTER DELETE ON tasks BEGIN UPDATE task_writes SET total = total + 1; END;
+    CREATE TRIGGER IF NOT EXISTS count_total_task_dependencies_insert AFTER INSERT ON task_dependencies BEGIN UPDATE task_writes SET total = total + 1; END;
+    CREATE TRIGGER IF NOT EXISTS count_total_task_dependencies_update AFTER UPDATE ON task_dependencies BEGIN UPDATE task_writes SET total = total + 1; END;
+    CREATE TRIGGER IF NOT EXISTS count_total_task_dependencies_delete AFTER DELETE ON task_dependencies BEGIN UPDATE task_writes SET total = total + 1; END;
+";
+
+/// Transaction writing tasks or dependencies. It is immediate, so every change of `task_writes.total`
+/// between begin and `commit` is its own; `commit` adds them to `task_writes.local` and
+/// `changes::ExternalChangeWatcher` doesn't take them for another process's.
+struct WriteTx<'a> {
+    tx: Transaction<'a>,
+    total: i64,
+}
+
+impl<'a> WriteTx<'a> {
+    fn begin(conn: &'a mut Connection) -> Result<Self> {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let total = tx.query_row("SELECT total FROM task_writes", [], |row| row.get(0))?;
+        Ok(Self { tx, total })
+    }
+
+    fn commit(self) -> Result<()> {
+        self.tx.execute("UPDATE task_writes SET local = local + (total - ?1)", params![self.total])?;
+        self.tx.commit()
+    }
+}
+
+impl std::ops::Deref for WriteTx<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.tx
+    }
+}
+
+/// SQL that is true when the task `table.id` waits for an open task.
+pub(crate) fn blocked_sql(table: &str) -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM task_dependencies dep JOIN tasks blocker ON blocker.id = dep.blocked_by
+                 WHERE dep.task_id = {}.id AND blocker.completed = 0)",
+        table
+    )
+}
+
+// Dependencies whose task or blocker is gone; run after deleting tasks
+fn delete_orphan_dependencies(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "DELETE FROM task_dependencies WHERE task_id NOT IN (SELECT id FROM tasks) OR blocked_by NOT IN (SELECT id FROM tasks)",
+        [],
+    )?;
+    Ok(())
+}
+
+// Current time in the format the timestamp columns use, e.g. "2026-10-18T09:30:00.123Z"
+const NOW_SQL: &str = "strftime('%Y-%m-%dT%H:%M:%fZ', 'now')";
+
+// Older versions could store "" for a cleared due date
+fn parse_column<T: FromStr>(row: &Row, index: usize) -> Result<Option<T>>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match row.get::<_, Option<String>>(index)?.as_deref() {
+        None | Some("") => Ok(None),
+        Some(value) => value.parse().map(Some).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))
+        }),
+    }
+}
+
+// Tags are stored as a JSON array so queries can match them with json_each()
+fn tags_from_column(row: &Row, index: usize) -> Result<Vec<String>> {
+    match row.get::<_, Option<String>>(index)? {
+        None => Ok(Vec::new()),
+        Some(json) => serde_json::from_str(&json).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))
+        }),
+    }
+}
+
+fn tags_to_column(tags: &[String]) -> String {
+    serde_json::to_string(tags).unwrap_or_else(|_| "[]".to_string())
+}
+
+fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+fn task_from_row(row: &Row) -> Result<Task> {
+    Ok(Task {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        description: row.get(10)?,
+        completed: row.get(2)?,
+        priority: row.get(3)?,
+        category: row.get(4)?,
+        due_date: parse_column(row, 5)?,
+        due_time: parse_column(row, 6)?,
+        timezone: row.get(7)?,
+        start_date: parse_column(row, 8)?,
+        recurrence: row.get(11)?,
+        parent_id: row.get(12)?,
+        tags: tags_from_column(row, 13)?,
+        position: row.get(9).unwrap_or(0),
+        revision: row.get(14)?,
+        created_at: parse_column(row, 15)?,
+        updated_at: parse_column(row, 16)?,
+        completed_at: parse_column(row, 17)?,
+        blocked: row.get(18)?,
+    })
+}
+
+const REMINDER_COLUMNS: &str = "id, task_id, remind_at, offset_minutes, snoozed_until, fired_for";
+
+fn reminder_from_row(row: &Row) -> Result<Reminder> {
+    Ok(Reminder {
+        id: row.get(0)?,
+        task_id: row.get(1)?,
+        remind_at: row.get(2)?,
+        offset_minutes: row.get(3)?,
+        snoozed_until: row.get(4)?,
+        fired_for: row.get(5)?,
+    })
+}
+
+fn dependency_from_row(row: &Row) -> Result<Dependency> {
+    Ok(Dependency { task_id: row.get(0)?, blocked_by: row.get(1)? })
+}
+
+const SMART_LIST_COLUMNS: &str = "id, name, filter, sort, position";
+
+fn smart_list_from_row(row: &Row) -> Result<SmartList> {
+    let conversion = |index: usize, e: serde_json::Error| rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e));
+    Ok(SmartList {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        filter: serde_json::from_str(&row.get::<_, String>(2)?).map_err(|e| conversion(2, e))?,
+        sort: serde_json::from_value(serde_json::Value::String(row.get(3)?)).map_err(|e| conversion(3, e))?,
+        position: row.get(4)?,
+    })
+}
+
+// Tasks in the order of `ids`, without duplicates; an unknown id is an error
+fn tasks_by_ids(conn: &Connection, ids: &[String]) -> Result<Vec<Task>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM tasks WHERE id = ?1", task_columns("tasks")))?;
+    let mut seen = std::collections::HashSet::new();
+    let mut tasks = Vec::new();
+    for id in ids.iter().filter(|id| seen.insert(id.as_str())) {
+        let task = stmt
+            .query_map(params![id], task_from_row)?
+            .next()
+            .transpose()?
+            .ok_or_else(|| invalid_task(format!("Task {} not found", id)))?;
+        tasks.push(task);
+    }
+    Ok(tasks)
+}
+
+fn push_undo(conn: &Connection, label: &str, snapshot: &UndoSnapshot) -> Result<()> {
+    let snapshot = serde_json::to_string(snapshot).map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
+    conn.execute(
+        "INSERT INTO undo_entries (label, snapshot, created_at) VALUES (?1, ?2, ?3)",
+        params![label, snapshot, chrono::Utc::now().to_rfc3339()],
+    )?;
+    conn.execute(
+        "DELETE FROM undo_entries WHERE id <= (SELECT MAX(id) FROM undo_entries) - ?1",
+        params![UNDO_LIMIT],
+    )?;
+    Ok(())
+}
+
+fn invalid_task(message: String) -> rusqlite::Error {
+    rusqlite::Error::ToSqlConversionFailure(message.into())
+}
+
+// Timestamps on `task` are kept (imports, undo); missing ones are set to now
+fn insert_task(conn: &Connection, task: &Task, position: i32) -> Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO tasks (id, title, completed, priority, category, due_date, due_time, timezone, start_date, position, description, recurrence, parent_id, tags,
+                                created_at, updated_at, completed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14,
+                     COALESCE(?15, {now}), COALESCE(?16, {now}), CASE WHEN ?3 THEN COALESCE(?17, {now}) END)",
+            now = NOW_SQL
+        ),
+        params![
+            task.id, 
+            task.title, 
+            task.completed, 
+            task.priority, 
+            task.category, 
+            task.due_date.map(|d| d.to_string()),
+            task.due_time.map(|t| t.format("%H:%M").to_string()),
+            task.timezone,
+            task.start_date.map(|d| d.to_string()),
+            position,
+            task.description,
+            task.recurrence,
+            task.parent_id,
+            tags_to_column(&task.tags),
+            task.created_at.map(format_timestamp),
+            task.updated_at.map(format_timestamp),
+            task.completed_at.map(format_timestamp)
+        ],
+    )?;
+    Ok(())
+}
+
+// `created_at` is never changed. A task that stays completed keeps its `completed_at`, one completed
+// now takes `task.completed_at` (undo) or now.
+fn update_task_row(conn: &Connection, task: &Task) -> Result<()> {
+    conn.execute(
+        &format!(
+            "UPDATE tasks SET title = ?2, completed = ?3, priority = ?4, category = ?5, due_date = ?6, due_time = ?7, timezone = ?8, start_date = ?9, position = ?10,
+             description = ?11, recurrence = ?12, parent_id = ?13, tags = ?14, revision = revision + 1,
+             updated_at = {now}, completed_at = CASE WHEN ?3 THEN COALESCE(completed_at, ?15, {now}) END
+             WHERE id = ?1",
+            now = NOW_SQL
+        ),
+        params![
+            task.id, 
+            task.title, 
+            task.completed, 
+            task.priority, 
+            task.category, 
+            task.due_date.map(|d| d.to_string()),
+            task.due_time.map(|t| t.format("%H:%M").to_string()),
+            task.timezone,
+            task.start_date.map(|d| d.to_string()),
+            task.position,
+            task.description,
+            task.recurrence,
+            task.parent_id,
+            tags_to_column(&task.tags),
+            task.completed_at.map(format_timestamp)
+        ],
+    )?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub provider: String,
+    pub account_id: String,
+    pub email: String,
+    pub name: String,
+    pub picture: Option<String>,
+}
+
+// Kept separate from `Account` so tokens are never serialized to the frontend
+#[derive(Debug, Clone)]
+pub struct AccountTokens {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<i64>,
+}
+
+/// Calendar event mirrored from a remote calendar. `start`/`end` keep the remote value
+/// (`YYYY-MM-DD` for all-day events, RFC 3339 otherwise); the `_ts` fields are unix seconds for range queries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub id: String,
+    pub provider: String,
+    pub account_id: String,
+    pub calendar_id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub start: String,
+    pub end: String,
+    pub start_ts: i64,
+    pub end_ts: i64,
+    pub all_day: bool,
+    pub color: Option<String>,
+    pub html_link: Option<String>,
+}
+
+/// Remote calendar event created for a task, so later task changes can update or remove it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskEventLink {
+    pub task_id: String,
+    pub provider: String,
+    pub account_id: String,
+    pub calendar_id: String,
+    pub event_id: String,
+    // Local "HH:MM" start of the time block on the due date, all-day event when None
+    pub start_time: Option<String>,
+    pub duration_minutes: Option<i64>,
+}
+
+/// Last known server copy of a task synced as a whole resource (a CalDAV `.ics` file).
+/// `synced` is the task as it was last uploaded or downloaded, so local edits can be detected;
+/// an empty `synced` marks a task that still has to be created on the server.
+#[derive(Debug, Clone)]
+pub struct RemoteResource {
+    pub provider: String,
+    pub account_id: String,
+    pub href: String,
+    pub collection: String,
+    pub task_id: String,
+    pub etag: String,
+    pub synced: String,
+}
+
+/// Reminder for a task, at a fixed instant (`remind_at`) or `offset_minutes` before the task is due.
+/// Times are unix seconds. `fired_for` is the reminder time that was last delivered, so a reminder fires
+/// once per time it is set to and fires again after a snooze or when the task's due date moves.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reminder {
+    pub id: String,
+    pub task_id: String,
+    pub remind_at: Option<i64>,
+    pub offset_minutes: Option<i64>,
+    pub snoozed_until: Option<i64>,
+    pub fired_for: Option<i64>,
+}
+
+/// `task_id` can't start until `blocked_by` is completed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dependency {
+    pub task_id: String,
+    pub blocked_by: String,
+}
+
+/// Saved filter shown in the sidebar, see `smartlists`. The filter is stored as JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SmartList {
+    pub id: String,
+    pub name: String,
+    pub filter: SmartFilter,
+    #[serde(default)]
+    pub sort: SmartSort,
+    #[serde(default)]
     pub position: i32,
 }
 
+type ChangeListener = Arc<dyn Fn(&TaskChange) + Send + Sync>;
+
 #[derive(Clone)]
 pub struct Database {
     path: String,
+    // Shared between clones so unlocking once applies to every handle
+    secrets: Arc<RwLock<SecretsState>>,
+    // Shared so writes through any clone reach the listener
+    listener: Arc<RwLock<Option<ChangeListener>>>,
+}
+
+fn secret_error(message: String) -> rusqlite::Error {
+    rusqlite::Error::ToSqlConversionFailure(message.into())
 }
 
 impl Database {
@@ -25,7 +655,146 @@ impl Database {
             std::fs::create_dir_all(&app_dir).unwrap();
         }
         let path = app_dir.join("tasks.db").to_str().unwrap().to_string();
-        Self { path }
+        Self::from_path(path)
+    }
+
+    pub fn from_path(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            secrets: Arc::new(RwLock::new(SecretsState::Disabled)),
+            listener: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Calls `listener` after every write to tasks, on the writing thread.
+    pub fn set_change_listener(&self, listener: impl Fn(&TaskChange) + Send + Sync + 'static) {
+        *self.listener.write().unwrap() = Some(Arc::new(listener));
+    }
+
+    pub fn notify(&self, kind: ChangeKind, ids: Vec<String>) {
+        let listener = self.listener.read().unwrap().clone();
+        if let Some(listener) = listener {
+            listener(&TaskChange { kind, ids });
+        }
+    }
+
+    pub fn set_secrets_state(&self, state: SecretsState) {
+        *self.secrets.write().unwrap() = state;
+    }
+
+    pub fn secrets_state(&self) -> SecretsState {
+        self.secrets.read().unwrap().clone()
+    }
+
+    fn seal(&self, value: &str) -> Result<String> {
+        match &*self.secrets.read().unwrap() {
+            SecretsState::Disabled => Ok(value.to_string()),
+            SecretsState::Locked => Err(secret_error("Secrets are locked".to_string())),
+            SecretsState::Unlocked(store) => store.encrypt(value).map_err(secret_error),
+        }
+    }
+
+    fn unseal(&self, value: String) -> Result<String> {
+        // Plaintext is left over from before encryption and gets sealed on the next unlock
+        if !secrets::is_encrypted(&value) {
+            return Ok(value);
+        }
+        match &*self.secrets.read().unwrap() {
+            SecretsState::Unlocked(store) => store.decrypt(&value).map_err(secret_error),
+            _ => Err(secret_error("Secrets are locked".to_string())),
+        }
+    }
+
+    /// A setting from `SECRET_SETTINGS`, decrypted.
+    pub fn get_secret_setting(&self, key: &str) -> Result<Option<String>> {
+        self.get_setting(key)?.map(|value| self.unseal(value)).transpose()
+    }
+
+    /// Stores a setting from `SECRET_SETTINGS`, encrypted while secrets are open.
+    pub fn set_secret_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.set_setting(key, &self.seal(value)?)
+    }
+
+    /// Rewrites every stored secret (account tokens and `SECRET_SETTINGS`) through `reseal`
+    /// and applies `settings` (`None` deletes the key) in a single transaction.
+    pub fn reseal_secrets<F>(&self, reseal: F, settings: &[(&str, Option<String>)]) -> Result<usize>
+    where
+        F: Fn(&str) -> std::result::Result<String, String>,
+    {
+        let mut conn = Connection::open(&self.path)?;
+        let tx = conn.transaction()?;
+
+        let rows: Vec<(String, String, Option<String>, Option<String>)> = {
+            let mut stmt = tx.prepare("SELECT provider, account_id, access_token, refresh_token FROM accounts")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+            rows.collect::<Result<_>>()?
+        };
+
+        for (provider, account_id, access_token, refresh_token) in &rows {
+            let access_token = access_token.as_deref().map(&reseal).transpose().map_err(secret_error)?;
+            let refresh_token = refresh_token.as_deref().map(&reseal).transpose().map_err(secret_error)?;
+            tx.execute(
+                "UPDATE accounts SET access_token = ?3, refresh_token = ?4 WHERE provider = ?1 AND account_id = ?2",
+                params![provider, account_id, access_token, refresh_token],
+            )?;
+        }
+
+        for key in SECRET_SETTINGS {
+            let value: Option<String> =
+                tx.query_row("SELECT value FROM settings WHERE key = ?1", params![key], |row| row.get(0)).optional()?;
+            if let Some(value) = value {
+                let value = reseal(&value).map_err(secret_error)?;
+                tx.execute("UPDATE settings SET value = ?2 WHERE key = ?1", params![key, value])?;
+            }
+        }
+
+        for (key, value) in settings {
+            match value {
+                Some(value) => tx.execute(
+                    "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+                    params![key, value],
+                )?,
+                None => tx.execute("DELETE FROM settings WHERE key = ?1", params![key])?,
+            };
+        }
+
+        tx.commit()?;
+        Ok(rows.len())
+    }
+
+    // Directory holding the database file, also used for app-level config files
+    pub fn dir(&self) -> std::path::PathBuf {
+        std::path::Path::new(&self.path)
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_default()
+    }
+
+    /// Copies the live database to `dest` with SQLite's online backup API.
+    pub fn backup_to(&self, dest: &std::path::Path) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        conn.backup(rusqlite::MAIN_DB, dest, None)
+    }
+
+    /// Overwrites the live database with the contents of `src` and brings an older copy up to the
+    /// current schema. Reported as one `External` change.
+    pub fn restore_from(&self, src: &std::path::Path) -> Result<()> {
+        let mut conn = Connection::open(&self.path)?;
+        let external_writes: i64 = conn.query_row("SELECT total - local FROM task_writes", [], |row| row.get(0))?;
+        // Carry the counters over before the copy goes live, so `changes::ExternalChangeWatcher`
+        // doesn't take the swap for another process's writes and report it a second time
+        let mut copy = Connection::open_in_memory()?;
+        copy.restore(rusqlite::MAIN_DB, src, None::<fn(rusqlite::backup::Progress)>)?;
+        copy.execute_batch(TASK_WRITES)?;
+        copy.execute("UPDATE task_writes SET local = total - ?1", params![external_writes])?;
+        rusqlite::backup::Backup::new(&copy, &mut conn)?.run_to_completion(i32::MAX, std::time::Duration::from_millis(250), None)?;
+        self.init()?;
+        self.notify(ChangeKind::External, Vec::new());
+        Ok(())
     }
 
     pub fn init(&self) -> Result<()> {
@@ -45,6 +814,29 @@ impl Database {
         // Attempt to add position column if it doesn't exist
         // We ignore the error if the column already exists
         let _ = conn.execute("ALTER TABLE tasks ADD COLUMN position INTEGER DEFAULT 0", []);
+        // Due time ("HH:MM") is wall-clock time in the IANA `timezone`
+        let _ = conn.execute("ALTER TABLE tasks ADD COLUMN due_time TEXT", []);
+        let _ = conn.execute("ALTER TABLE tasks ADD COLUMN timezone TEXT", []);
+        let _ = conn.execute("ALTER TABLE tasks ADD COLUMN start_date TEXT", []);
+        let _ = conn.execute("ALTER TABLE tasks ADD COLUMN description TEXT", []);
+        let _ = conn.execute("ALTER TABLE tasks ADD COLUMN recurrence TEXT", []);
+        let _ = conn.execute("ALTER TABLE tasks ADD COLUMN parent_id TEXT", []);
+        let _ = conn.execute("ALTER TABLE tasks ADD COLUMN tags TEXT", []);
+        let _ = conn.execute("ALTER TABLE tasks ADD COLUMN revision INTEGER NOT NULL DEFAULT 0", []);
+        let _ = conn.execute("ALTER TABLE tasks ADD COLUMN created_at TEXT", []);
+        let _ = conn.execute("ALTER TABLE tasks ADD COLUMN updated_at TEXT", []);
+        let _ = conn.execute("ALTER TABLE tasks ADD COLUMN completed_at TEXT", []);
+        // Rows from before timestamps existed: their history is unknown, so they count as created
+        // (and, if done, completed) when the columns were added
+        conn.execute(
+            &format!(
+                "UPDATE tasks SET created_at = {now}, updated_at = COALESCE(updated_at, {now}),
+                 completed_at = CASE WHEN completed THEN COALESCE(completed_at, {now}) END
+                 WHERE created_at IS NULL",
+                now = NOW_SQL
+            ),
+            [],
+        )?;
 
         conn.execute(
             "CREATE TABLE IF NOT EXISTS settings (
@@ -53,6 +845,149 @@ impl Database {
             )",
             [],
         )?;
+
+        // Tracks which local tasks came from a remote provider, so disconnecting can clean them up
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS imported_tasks (
+                task_id TEXT PRIMARY KEY,
+                provider TEXT NOT NULL,
+                account_id TEXT NOT NULL DEFAULT '',
+                list_id TEXT NOT NULL
+            )",
+            [],
+        )?;
+        let _ = conn.execute("ALTER TABLE imported_tasks ADD COLUMN account_id TEXT NOT NULL DEFAULT ''", []);
+
+        // One row per connected remote account, e.g. ("google", <GoogleUser.id>)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS accounts (
+                provider TEXT NOT NULL,
+                account_id TEXT NOT NULL,
+                email TEXT NOT NULL,
+                name TEXT NOT NULL,
+                picture TEXT,
+                access_token TEXT,
+                refresh_token TEXT,
+                token_expires_at INTEGER,
+                PRIMARY KEY (provider, account_id)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS events (
+                id TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                account_id TEXT NOT NULL,
+                calendar_id TEXT NOT NULL,
+                title TEXT NOT NULL,
+                description TEXT,
+                location TEXT,
+                start TEXT NOT NULL,
+                end TEXT NOT NULL,
+                start_ts INTEGER NOT NULL,
+                end_ts INTEGER NOT NULL,
+                all_day BOOLEAN NOT NULL,
+                color TEXT,
+                html_link TEXT,
+                PRIMARY KEY (provider, account_id, calendar_id, id)
+            )",
+            [],
+        )?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_events_range ON events (start_ts, end_ts)", [])?;
+
+        // Calendar events pushed for tasks, at most one per task. Deleting a task leaves its row
+        // so `google::sync_task_events` can still find and remove the event
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS task_events (
+                task_id TEXT PRIMARY KEY,
+                provider TEXT NOT NULL,
+                account_id TEXT NOT NULL,
+                calendar_id TEXT NOT NULL,
+                event_id TEXT NOT NULL,
+                start_time TEXT,
+                duration_minutes INTEGER
+            )",
+            [],
+        )?;
+
+        // Per-account key-value store for sync cursors and similar state
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS account_settings (
+                provider TEXT NOT NULL,
+                account_id TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (provider, account_id, key)
+            )",
+            [],
+        )?;
+
+        // Tasks kept in sync with a resource on a remote server, see `RemoteResource`
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS remote_resources (
+                provider TEXT NOT NULL,
+                account_id TEXT NOT NULL,
+                href TEXT NOT NULL,
+                collection TEXT NOT NULL,
+                task_id TEXT NOT NULL,
+                etag TEXT NOT NULL,
+                synced TEXT NOT NULL,
+                PRIMARY KEY (provider, account_id, href)
+            )",
+            [],
+        )?;
+
+        // Task reminders, see `Reminder`
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS reminders (
+                id TEXT PRIMARY KEY,
+                task_id TEXT NOT NULL,
+                remind_at INTEGER,
+                offset_minutes INTEGER,
+                snoozed_until INTEGER,
+                fired_for INTEGER
+            )",
+            [],
+        )?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_reminders_task ON reminders (task_id)", [])?;
+
+        // "task_id can't start until blocked_by is done", see `dependencies`
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS task_dependencies (
+                task_id TEXT NOT NULL,
+                blocked_by TEXT NOT NULL,
+                PRIMARY KEY (task_id, blocked_by)
+            )",
+            [],
+        )?;
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_task_dependencies_blocked_by ON task_dependencies (blocked_by)", [])?;
+
+        // Saved filters, see `SmartList`
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS smart_lists (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                filter TEXT NOT NULL,
+                sort TEXT NOT NULL,
+                position INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+
+        // Snapshots taken before bulk edits, newest undone first
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS undo_entries (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                label TEXT NOT NULL,
+                snapshot TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        conn.execute_batch(TASK_WRITES)?;
+        conn.execute_batch(WRITE_COUNTERS)?;
         Ok(())
     }
 
@@ -77,20 +1012,23 @@ impl Database {
         Ok(())
     }
 
+    pub fn delete_setting(&self, key: &str) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        conn.execute("DELETE FROM settings WHERE key = ?1", params![key])?;
+        Ok(())
+    }
+
+    pub fn get_settings(&self) -> Result<Vec<(String, String)>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
     pub fn get_tasks(&self) -> Result<Vec<Task>> {
         let conn = Connection::open(&self.path)?;
-        let mut stmt = conn.prepare("SELECT id, title, completed, priority, category, due_date, position FROM tasks ORDER BY position ASC")?;
-        let task_iter = stmt.query_map([], |row| {
-            Ok(Task {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                completed: row.get(2)?,
-                priority: row.get(3)?,
-                category: row.get(4)?,
-                due_date: row.get(5)?,
-                position: row.get(6).unwrap_or(0),
-            })
-        })?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM tasks ORDER BY position ASC", task_columns("tasks")))?;
+        let task_iter = stmt.query_map([], task_from_row)?;
 
         let mut tasks = Vec::new();
         for task in task_iter {
@@ -99,20 +1037,28 @@ impl Database {
         Ok(tasks)
     }
 
+    /// Tasks matching a filter from `query::compile`, in list order.
+    pub fn find_tasks(&self, filter: &SqlFilter) -> Result<Vec<Task>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM tasks WHERE ({}) ORDER BY position ASC", task_columns("tasks"), filter.sql))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(&filter.params), task_from_row)?;
+        rows.collect()
+    }
+
+    pub fn count_tasks(&self, filter: &SqlFilter) -> Result<usize> {
+        let conn = Connection::open(&self.path)?;
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM tasks WHERE ({})", filter.sql),
+            rusqlite::params_from_iter(&filter.params),
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|count| count as usize)
+    }
+
     pub fn get_task_by_id(&self, id: &str) -> Result<Option<Task>> {
         let conn = Connection::open(&self.path)?;
-        let mut stmt = conn.prepare("SELECT id, title, completed, priority, category, due_date, position FROM tasks WHERE id = ?1")?;
-        let mut task_iter = stmt.query_map(params![id], |row| {
-            Ok(Task {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                completed: row.get(2)?,
-                priority: row.get(3)?,
-                category: row.get(4)?,
-                due_date: row.get(5)?,
-                position: row.get(6).unwrap_or(0),
-            })
-        })?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM tasks WHERE id = ?1", task_columns("tasks")))?;
+        let mut task_iter = stmt.query_map(params![id], task_from_row)?;
 
         if let Some(task) = task_iter.next() {
             return Ok(Some(task?));
@@ -121,56 +1067,826 @@ impl Database {
     }
 
     pub fn add_task(&self, task: Task) -> Result<()> {
-        let conn = Connection::open(&self.path)?;
+        let task = task.without_timestamps();
+        task.validate().map_err(invalid_task)?;
+        let mut conn = Connection::open(&self.path)?;
+        let tx = WriteTx::begin(&mut conn)?;
         
         // Get max position to append to the end
-        let max_pos: i32 = conn.query_row(
+        let max_pos: i32 = tx.query_row(
             "SELECT COALESCE(MAX(position), -1) FROM tasks",
             [],
             |row| row.get(0),
         ).unwrap_or(-1);
 
+        insert_task(&tx, &task, max_pos + 1)?;
+        tx.commit()?;
+        self.notify(ChangeKind::Added, vec![task.id]);
+        Ok(())
+    }
+
+    pub fn update_task(&self, task: Task) -> Result<()> {
+        let task = task.without_timestamps();
+        task.validate().map_err(invalid_task)?;
+        let mut conn = Connection::open(&self.path)?;
+        let tx = WriteTx::begin(&mut conn)?;
+        update_task_row(&tx, &task)?;
+        tx.commit()?;
+        self.notify(ChangeKind::Updated, vec![task.id]);
+        Ok(())
+    }
+
+    /// Applies `patch` to one task and returns it as saved. With `revision`, a task written since
+    /// that revision is left alone and the error starts with "Conflict:".
+    pub fn patch_task(&self, id: &str, patch: &TaskPatch, revision: Option<i64>) -> Result<Task> {
+        let mut conn = Connection::open(&self.path)?;
+        // Immediate, so no other writer slips in between the revision check and the update
+        let tx = WriteTx::begin(&mut conn)?;
+        let mut task = tasks_by_ids(&tx, &[id.to_string()])?.remove(0);
+        if let Some(expected) = revision
+            && expected != task.revision
+        {
+            return Err(invalid_task(format!(
+                "Conflict: task {} was changed elsewhere (revision {}, expected {})",
+                id, task.revision, expected
+            )));
+        }
+        patch.apply(&mut task);
+        task.validate().map_err(invalid_task)?;
+        update_task_row(&tx, &task)?;
+        let task = tasks_by_ids(&tx, &[task.id])?.remove(0);
+        tx.commit()?;
+
+        self.notify(ChangeKind::Updated, vec![task.id.clone()]);
+        Ok(task)
+    }
+
+    pub fn delete_task(&self, id: &str) -> Result<()> {
+        let mut conn = Connection::open(&self.path)?;
+        let tx = WriteTx::begin(&mut conn)?;
+        tx.execute("DELETE FROM tasks WHERE id = ?1", params![id])?;
+        tx.execute("DELETE FROM imported_tasks WHERE task_id = ?1", params![id])?;
+        tx.execute("DELETE FROM reminders WHERE task_id = ?1", params![id])?;
+        tx.execute("DELETE FROM task_dependencies WHERE task_id = ?1 OR blocked_by = ?1", params![id])?;
+        tx.commit()?;
+        self.notify(ChangeKind::Deleted, vec![id.to_string()]);
+        Ok(())
+    }
+
+    pub fn add_reminder(&self, reminder: &Reminder) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
         conn.execute(
-            "INSERT INTO tasks (id, title, completed, priority, category, due_date, position) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            "INSERT INTO reminders (id, task_id, remind_at, offset_minutes, snoozed_until, fired_for)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
             params![
-                task.id, 
-                task.title, 
-                task.completed, 
-                task.priority, 
-                task.category, 
-                task.due_date,
-                max_pos + 1
+                reminder.id,
+                reminder.task_id,
+                reminder.remind_at,
+                reminder.offset_minutes,
+                reminder.snoozed_until,
+                reminder.fired_for
             ],
         )?;
         Ok(())
     }
 
-    pub fn update_task(&self, task: Task) -> Result<()> {
+    pub fn get_reminder(&self, id: &str) -> Result<Option<Reminder>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM reminders WHERE id = ?1", REMINDER_COLUMNS))?;
+        let mut rows = stmt.query_map(params![id], reminder_from_row)?;
+        rows.next().transpose()
+    }
+
+    pub fn get_reminders(&self, task_id: &str) -> Result<Vec<Reminder>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM reminders WHERE task_id = ?1 ORDER BY remind_at IS NULL, remind_at, offset_minutes DESC",
+            REMINDER_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![task_id], reminder_from_row)?;
+        rows.collect()
+    }
+
+    /// Reminders of tasks that are not completed, with their task.
+    pub fn get_open_reminders(&self) -> Result<Vec<(Reminder, Task)>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}, r.id, r.task_id, r.remind_at, r.offset_minutes, r.snoozed_until, r.fired_for
+             FROM reminders r JOIN tasks t ON t.id = r.task_id WHERE t.completed = 0",
+            task_columns("t")
+        ))?;
+        // Stored columns and `blocked`
+        let offset = TASK_COLUMNS.split(", ").count() + 1;
+        let rows = stmt.query_map([], |row| {
+            let task = task_from_row(row)?;
+            let reminder = Reminder {
+                id: row.get(offset)?,
+                task_id: row.get(offset + 1)?,
+                remind_at: row.get(offset + 2)?,
+                offset_minutes: row.get(offset + 3)?,
+                snoozed_until: row.get(offset + 4)?,
+                fired_for: row.get(offset + 5)?,
+            };
+            Ok((reminder, task))
+        })?;
+        rows.collect()
+    }
+
+    pub fn set_reminder_state(&self, id: &str, snoozed_until: Option<i64>, fired_for: Option<i64>) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        conn.execute(
+            "UPDATE reminders SET snoozed_until = ?2, fired_for = ?3 WHERE id = ?1",
+            params![id, snoozed_until, fired_for],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_reminder(&self, id: &str) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        conn.execute("DELETE FROM reminders WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn get_smart_lists(&self) -> Result<Vec<SmartList>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM smart_lists ORDER BY position, name", SMART_LIST_COLUMNS))?;
+        let rows = stmt.query_map([], smart_list_from_row)?;
+        rows.collect()
+    }
+
+    pub fn get_smart_list(&self, id: &str) -> Result<Option<SmartList>> {
         let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM smart_lists WHERE id = ?1", SMART_LIST_COLUMNS))?;
+        let mut rows = stmt.query_map(params![id], smart_list_from_row)?;
+        rows.next().transpose()
+    }
+
+    /// Adds the list after the existing ones, ignoring `list.position`.
+    pub fn add_smart_list(&self, list: &SmartList) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        let filter = serde_json::to_string(&list.filter).map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
         conn.execute(
-            "UPDATE tasks SET title = ?2, completed = ?3, priority = ?4, category = ?5, due_date = ?6, position = ?7 WHERE id = ?1",
+            "INSERT INTO smart_lists (id, name, filter, sort, position)
+             VALUES (?1, ?2, ?3, ?4, (SELECT COALESCE(MAX(position), -1) + 1 FROM smart_lists))",
+            params![list.id, list.name, filter, list.sort.as_str()],
+        )?;
+        Ok(())
+    }
+
+    /// Returns false when there is no list with that id.
+    pub fn update_smart_list(&self, list: &SmartList) -> Result<bool> {
+        let conn = Connection::open(&self.path)?;
+        let filter = serde_json::to_string(&list.filter).map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
+        let updated = conn.execute(
+            "UPDATE smart_lists SET name = ?2, filter = ?3, sort = ?4, position = ?5 WHERE id = ?1",
+            params![list.id, list.name, filter, list.sort.as_str(), list.position],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn delete_smart_list(&self, id: &str) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        conn.execute("DELETE FROM smart_lists WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn get_dependencies(&self) -> Result<Vec<Dependency>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare("SELECT task_id, blocked_by FROM task_dependencies ORDER BY task_id, blocked_by")?;
+        let rows = stmt.query_map([], dependency_from_row)?;
+        rows.collect()
+    }
+
+    /// Tasks `task_id` waits for, open ones first.
+    pub fn get_blockers(&self, task_id: &str) -> Result<Vec<Task>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM tasks WHERE id IN (SELECT blocked_by FROM task_dependencies WHERE task_id = ?1) ORDER BY completed, position",
+            task_columns("tasks")
+        ))?;
+        let rows = stmt.query_map(params![task_id], task_from_row)?;
+        rows.collect()
+    }
+
+    /// Tasks waiting for `task_id`.
+    pub fn get_dependents(&self, task_id: &str) -> Result<Vec<Task>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM tasks WHERE id IN (SELECT task_id FROM task_dependencies WHERE blocked_by = ?1) ORDER BY completed, position",
+            task_columns("tasks")
+        ))?;
+        let rows = stmt.query_map(params![task_id], task_from_row)?;
+        rows.collect()
+    }
+
+    /// Links whose blocking task is still open.
+    pub fn get_open_dependencies(&self) -> Result<Vec<Dependency>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare(
+            "SELECT dep.task_id, dep.blocked_by FROM task_dependencies dep
+             JOIN tasks blocker ON blocker.id = dep.blocked_by
+             WHERE blocker.completed = 0 ORDER BY dep.task_id, dep.blocked_by",
+        )?;
+        let rows = stmt.query_map([], dependency_from_row)?;
+        rows.collect()
+    }
+
+    /// Makes `task_id` wait for `blocked_by`. Both tasks must exist, and a link that would close
+    /// a loop is rejected with an error starting with "Circular dependency:".
+    pub fn add_dependency(&self, task_id: &str, blocked_by: &str) -> Result<()> {
+        let mut conn = Connection::open(&self.path)?;
+        // Immediate, so two links can't close a loop between them
+        let tx = WriteTx::begin(&mut conn)?;
+        let tasks = tasks_by_ids(&tx, &[task_id.to_string(), blocked_by.to_string()])?;
+        let edges: Vec<Dependency> = {
+            let mut stmt = tx.prepare("SELECT task_id, blocked_by FROM task_dependencies")?;
+            let rows = stmt.query_map([], dependency_from_row)?;
+            rows.collect::<Result<_>>()?
+        };
+        if let Some(path) = crate::dependencies::path(&edges, blocked_by, task_id) {
+            let mut stmt = tx.prepare("SELECT title FROM tasks WHERE id = ?1")?;
+            let mut titles = vec![tasks[0].title.clone()];
+            for id in &path[..path.len() - 1] {
+                titles.push(stmt.query_row(params![id], |row| row.get(0))?);
+            }
+            titles.push(tasks[0].title.clone());
+            return Err(invalid_task(format!("Circular dependency: {}", titles.join(" → "))));
+        }
+        tx.execute(
+            "INSERT OR IGNORE INTO task_dependencies (task_id, blocked_by) VALUES (?1, ?2)",
+            params![task_id, blocked_by],
+        )?;
+        tx.commit()?;
+        self.notify(ChangeKind::Updated, vec![task_id.to_string()]);
+        Ok(())
+    }
+
+    pub fn remove_dependency(&self, task_id: &str, blocked_by: &str) -> Result<()> {
+        let mut conn = Connection::open(&self.path)?;
+        let tx = WriteTx::begin(&mut conn)?;
+        let removed = tx.execute(
+            "DELETE FROM task_dependencies WHERE task_id = ?1 AND blocked_by = ?2",
+            params![task_id, blocked_by],
+        )?;
+        tx.commit()?;
+        if removed > 0 {
+            self.notify(ChangeKind::Updated, vec![task_id.to_string()]);
+        }
+        Ok(())
+    }
+
+    pub fn set_task_event(&self, link: &TaskEventLink) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO task_events (task_id, provider, account_id, calendar_id, event_id, start_time, duration_minutes)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
             params![
-                task.id, 
-                task.title, 
-                task.completed, 
-                task.priority, 
-                task.category, 
-                task.due_date,
-                task.position
+                link.task_id,
+                link.provider,
+                link.account_id,
+                link.calendar_id,
+                link.event_id,
+                link.start_time,
+                link.duration_minutes
             ],
         )?;
         Ok(())
     }
 
-    pub fn delete_task(&self, id: &str) -> Result<()> {
+    /// Ids of tasks with a calendar event, including deleted tasks whose event is still there.
+    pub fn get_task_event_ids(&self) -> Result<Vec<String>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare("SELECT task_id FROM task_events ORDER BY task_id")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    pub fn get_task_event(&self, task_id: &str) -> Result<Option<TaskEventLink>> {
         let conn = Connection::open(&self.path)?;
-        conn.execute("DELETE FROM tasks WHERE id = ?1", params![id])?;
+        let mut stmt = conn.prepare(
+            "SELECT task_id, provider, account_id, calendar_id, event_id, start_time, duration_minutes FROM task_events WHERE task_id = ?1",
+        )?;
+        let mut rows = stmt.query(params![task_id])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(TaskEventLink {
+                task_id: row.get(0)?,
+                provider: row.get(1)?,
+                account_id: row.get(2)?,
+                calendar_id: row.get(3)?,
+                event_id: row.get(4)?,
+                start_time: row.get(5)?,
+                duration_minutes: row.get(6)?,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn delete_task_event(&self, task_id: &str) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        conn.execute("DELETE FROM task_events WHERE task_id = ?1", params![task_id])?;
         Ok(())
     }
 
-    pub fn update_task_order(&self, ordered_ids: Vec<String>) -> Result<()> {
+    pub fn link_imported_task(&self, task_id: &str, provider: &str, account_id: &str, list_id: &str) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO imported_tasks (task_id, provider, account_id, list_id) VALUES (?1, ?2, ?3, ?4)",
+            params![task_id, provider, account_id, list_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_imported_task_ids(&self, provider: &str, account_id: &str) -> Result<Vec<String>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare("SELECT task_id FROM imported_tasks WHERE provider = ?1 AND account_id = ?2")?;
+        let ids = stmt.query_map(params![provider, account_id], |row| row.get(0))?;
+        ids.collect()
+    }
+
+    // Forget the remote origin of imported tasks but keep them as local tasks
+    // Imports made before accounts existed were stored with an empty account id
+    pub fn assign_unowned_imports(&self, provider: &str, account_id: &str) -> Result<usize> {
+        let conn = Connection::open(&self.path)?;
+        conn.execute(
+            "UPDATE imported_tasks SET account_id = ?2 WHERE provider = ?1 AND account_id = ''",
+            params![provider, account_id],
+        )
+    }
+
+    pub fn upsert_account(&self, account: &Account) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        conn.execute(
+            "INSERT INTO accounts (provider, account_id, email, name, picture) VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(provider, account_id) DO UPDATE SET email = ?3, name = ?4, picture = ?5",
+            params![account.provider, account.account_id, account.email, account.name, account.picture],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_accounts(&self, provider: &str) -> Result<Vec<Account>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare(
+            "SELECT provider, account_id, email, name, picture FROM accounts WHERE provider = ?1 ORDER BY email ASC",
+        )?;
+        let accounts = stmt.query_map(params![provider], |row| {
+            Ok(Account {
+                provider: row.get(0)?,
+                account_id: row.get(1)?,
+                email: row.get(2)?,
+                name: row.get(3)?,
+                picture: row.get(4)?,
+            })
+        })?;
+        accounts.collect()
+    }
+
+    pub fn get_account_tokens(&self, provider: &str, account_id: &str) -> Result<Option<AccountTokens>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare(
+            "SELECT access_token, refresh_token, token_expires_at FROM accounts WHERE provider = ?1 AND account_id = ?2",
+        )?;
+        let mut rows = stmt.query(params![provider, account_id])?;
+
+        if let Some(row) = rows.next()? {
+            let access_token: Option<String> = row.get(0)?;
+            let refresh_token: Option<String> = row.get(1)?;
+            let Some(access_token) = access_token else {
+                return Ok(None);
+            };
+            Ok(Some(AccountTokens {
+                access_token: self.unseal(access_token)?,
+                refresh_token: refresh_token.map(|t| self.unseal(t)).transpose()?,
+                expires_at: row.get(2)?,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn set_account_tokens(&self, provider: &str, account_id: &str, tokens: &AccountTokens) -> Result<()> {
+        let access_token = self.seal(&tokens.access_token)?;
+        let refresh_token = tokens.refresh_token.as_deref().map(|t| self.seal(t)).transpose()?;
+        let conn = Connection::open(&self.path)?;
+        conn.execute(
+            "UPDATE accounts SET access_token = ?3, refresh_token = ?4, token_expires_at = ?5 WHERE provider = ?1 AND account_id = ?2",
+            params![provider, account_id, access_token, refresh_token, tokens.expires_at],
+        )?;
+        Ok(())
+    }
+
+    /// Removes the account with its settings, events and sync state, and applies `imported` to the
+    /// tasks imported from it, in one transaction. Returns how many tasks were detached and deleted.
+    pub fn delete_account(&self, provider: &str, account_id: &str, imported: ImportedTaskAction) -> Result<(usize, usize)> {
+        let mut conn = Connection::open(&self.path)?;
+        let tx = WriteTx::begin(&mut conn)?;
+        let (mut detached, mut deleted, mut deleted_ids) = (0, 0, Vec::new());
+        match imported {
+            ImportedTaskAction::Keep => {}
+            ImportedTaskAction::Detach => {
+                detached = tx.execute(
+                    "DELETE FROM imported_tasks WHERE provider = ?1 AND account_id = ?2",
+                    params![provider, account_id],
+                )?;
+            }
+            ImportedTaskAction::Delete => {
+                deleted_ids = {
+                    let mut stmt = tx.prepare(
+                        "SELECT id FROM tasks WHERE id IN (SELECT task_id FROM imported_tasks WHERE provider = ?1 AND account_id = ?2)",
+                    )?;
+                    let rows = stmt.query_map(params![provider, account_id], |row| row.get(0))?;
+                    rows.collect::<Result<Vec<String>>>()?
+                };
+                deleted = tx.execute(
+                    "DELETE FROM tasks WHERE id IN (SELECT task_id FROM imported_tasks WHERE provider = ?1 AND account_id = ?2)",
+                    params![provider, account_id],
+                )?;
+                tx.execute(
+                    "DELETE FROM reminders WHERE task_id IN (SELECT task_id FROM imported_tasks WHERE provider = ?1 AND account_id = ?2)",
+                    params![provider, account_id],
+                )?;
+                tx.execute(
+                    "DELETE FROM imported_tasks WHERE provider = ?1 AND account_id = ?2",
+                    params![provider, account_id],
+                )?;
+                delete_orphan_dependencies(&tx)?;
+            }
+        }
+        tx.execute(
+            "DELETE FROM account_settings WHERE provider = ?1 AND account_id = ?2",
+            params![provider, account_id],
+        )?;
+        tx.execute(
+            "DELETE FROM events WHERE provider = ?1 AND account_id = ?2",
+            params![provider, account_id],
+        )?;
+        tx.execute(
+            "DELETE FROM task_events WHERE provider = ?1 AND account_id = ?2",
+            params![provider, account_id],
+        )?;
+        tx.execute(
+            "DELETE FROM remote_resources WHERE provider = ?1 AND account_id = ?2",
+            params![provider, account_id],
+        )?;
+        tx.execute(
+            "DELETE FROM accounts WHERE provider = ?1 AND account_id = ?2",
+            params![provider, account_id],
+        )?;
+        tx.commit()?;
+        if !deleted_ids.is_empty() {
+            self.notify(ChangeKind::Deleted, deleted_ids);
+        }
+        Ok((detached, deleted))
+    }
+
+    pub fn get_remote_resources(&self, provider: &str, account_id: &str, collection: &str) -> Result<Vec<RemoteResource>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare(
+            "SELECT provider, account_id, href, collection, task_id, etag, synced FROM remote_resources
+             WHERE provider = ?1 AND account_id = ?2 AND collection = ?3 ORDER BY href",
+        )?;
+        let rows = stmt.query_map(params![provider, account_id, collection], |row| {
+            Ok(RemoteResource {
+                provider: row.get(0)?,
+                account_id: row.get(1)?,
+                href: row.get(2)?,
+                collection: row.get(3)?,
+                task_id: row.get(4)?,
+                etag: row.get(5)?,
+                synced: row.get(6)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn set_remote_resource(&self, resource: &RemoteResource) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO remote_resources (provider, account_id, href, collection, task_id, etag, synced)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                resource.provider,
+                resource.account_id,
+                resource.href,
+                resource.collection,
+                resource.task_id,
+                resource.etag,
+                resource.synced,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_remote_resource(&self, provider: &str, account_id: &str, href: &str) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        conn.execute(
+            "DELETE FROM remote_resources WHERE provider = ?1 AND account_id = ?2 AND href = ?3",
+            params![provider, account_id, href],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_account_setting(&self, provider: &str, account_id: &str, key: &str) -> Result<Option<String>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare(
+            "SELECT value FROM account_settings WHERE provider = ?1 AND account_id = ?2 AND key = ?3",
+        )?;
+        let mut rows = stmt.query(params![provider, account_id, key])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(row.get(0)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn set_account_setting(&self, provider: &str, account_id: &str, key: &str, value: &str) -> Result<()> {
+        let conn = Connection::open(&self.path)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO account_settings (provider, account_id, key, value) VALUES (?1, ?2, ?3, ?4)",
+            params![provider, account_id, key, value],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_account_settings_with_prefix(&self, provider: &str, account_id: &str, prefix: &str) -> Result<Vec<(String, String)>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare(
+            "SELECT key, value FROM account_settings WHERE provider = ?1 AND account_id = ?2 AND substr(key, 1, length(?3)) = ?3",
+        )?;
+        let rows = stmt.query_map(params![provider, account_id, prefix], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Replaces the events of one calendar that overlap `[range_start, range_end)`.
+    pub fn replace_events(
+        &self,
+        provider: &str,
+        account_id: &str,
+        calendar_id: &str,
+        range_start: i64,
+        range_end: i64,
+        events: &[Event],
+    ) -> Result<()> {
         let mut conn = Connection::open(&self.path)?;
         let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM events WHERE provider = ?1 AND account_id = ?2 AND calendar_id = ?3 AND start_ts < ?5 AND end_ts > ?4",
+            params![provider, account_id, calendar_id, range_start, range_end],
+        )?;
+        for event in events {
+            tx.execute(
+                "INSERT OR REPLACE INTO events (id, provider, account_id, calendar_id, title, description, location, start, end, start_ts, end_ts, all_day, color, html_link)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                params![
+                    event.id,
+                    event.provider,
+                    event.account_id,
+                    event.calendar_id,
+                    event.title,
+                    event.description,
+                    event.location,
+                    event.start,
+                    event.end,
+                    event.start_ts,
+                    event.end_ts,
+                    event.all_day,
+                    event.color,
+                    event.html_link
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_events(&self, range_start: i64, range_end: i64) -> Result<Vec<Event>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare(
+            "SELECT id, provider, account_id, calendar_id, title, description, location, start, end, start_ts, end_ts, all_day, color, html_link
+             FROM events WHERE start_ts < ?2 AND end_ts > ?1 ORDER BY start_ts ASC",
+        )?;
+        let events = stmt.query_map(params![range_start, range_end], |row| {
+            Ok(Event {
+                id: row.get(0)?,
+                provider: row.get(1)?,
+                account_id: row.get(2)?,
+                calendar_id: row.get(3)?,
+                title: row.get(4)?,
+                description: row.get(5)?,
+                location: row.get(6)?,
+                start: row.get(7)?,
+                end: row.get(8)?,
+                start_ts: row.get(9)?,
+                end_ts: row.get(10)?,
+                all_day: row.get(11)?,
+                color: row.get(12)?,
+                html_link: row.get(13)?,
+            })
+        })?;
+        events.collect()
+    }
+
+    /// Writes `tasks` and `settings` in one transaction, returning (added, updated, removed).
+    /// Tasks with a known id are updated in place and new ones are appended. With `replace`,
+    /// tasks missing from `tasks` are deleted and the given positions are kept.
+    pub fn import_data(&self, tasks: &[Task], settings: &[(String, String)], replace: bool) -> Result<(usize, usize, usize)> {
+        for task in tasks {
+            task.validate().map_err(|e| invalid_task(format!("{}: {}", task.id, e)))?;
+        }
+        let mut conn = Connection::open(&self.path)?;
+        let tx = WriteTx::begin(&mut conn)?;
+
+        let existing: std::collections::HashMap<String, i32> = {
+            let mut stmt = tx.prepare("SELECT id, COALESCE(position, 0) FROM tasks")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<_>>()?
+        };
+
+        let (mut added_ids, mut updated_ids, mut removed_ids) = (Vec::new(), Vec::new(), Vec::new());
+        if replace {
+            let keep: std::collections::HashSet<&str> = tasks.iter().map(|t| t.id.as_str()).collect();
+            for id in existing.keys().filter(|id| !keep.contains(id.as_str())) {
+                tx.execute("DELETE FROM tasks WHERE id = ?1", params![id])?;
+                tx.execute("DELETE FROM imported_tasks WHERE task_id = ?1", params![id])?;
+                tx.execute("DELETE FROM reminders WHERE task_id = ?1", params![id])?;
+                removed_ids.push(id.clone());
+            }
+            delete_orphan_dependencies(&tx)?;
+        }
+
+        let mut next_pos = existing.values().max().map_or(0, |p| p + 1);
+        for task in tasks {
+            match existing.get(&task.id) {
+                Some(&position) => {
+                    let position = if replace { task.position } else { position };
+                    update_task_row(&tx, &Task { position, ..task.clone() })?;
+                    updated_ids.push(task.id.clone());
+                }
+                None => {
+                    let position = if replace { task.position } else { next_pos };
+                    next_pos += 1;
+                    insert_task(&tx, task, position)?;
+                    added_ids.push(task.id.clone());
+                }
+            }
+        }
+
+        for (key, value) in settings {
+            tx.execute("INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)", params![key, value])?;
+        }
+
+        tx.commit()?;
+        let counts = (added_ids.len(), updated_ids.len(), removed_ids.len());
+        for (kind, ids) in [(ChangeKind::Deleted, removed_ids), (ChangeKind::Updated, updated_ids), (ChangeKind::Added, added_ids)] {
+            if !ids.is_empty() {
+                self.notify(kind, ids);
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Applies `change` to every task in `ids` and writes them in one transaction with one undo
+    /// entry. Nothing is written if a task is missing or a changed task fails validation.
+    pub fn bulk_update<F>(&self, ids: &[String], label: &str, change: F) -> Result<Vec<Task>>
+    where
+        F: Fn(&mut Task),
+    {
+        let mut conn = Connection::open(&self.path)?;
+        let tx = WriteTx::begin(&mut conn)?;
+        let before = tasks_by_ids(&tx, ids)?;
+        let mut changed = before.clone();
+        for task in &mut changed {
+            change(task);
+            task.validate().map_err(|e| invalid_task(format!("{}: {}", task.id, e)))?;
+            update_task_row(&tx, task)?;
+        }
+        let after = tasks_by_ids(&tx, ids)?;
+        let revisions = after.iter().map(|t| (t.id.clone(), t.revision)).collect();
+        push_undo(&tx, label, &UndoSnapshot { tasks: before, revisions, ..Default::default() })?;
+        tx.commit()?;
+
+        self.notify(ChangeKind::Updated, after.iter().map(|t| t.id.clone()).collect());
+        Ok(after)
+    }
+
+    /// Deletes the tasks in `ids` with their reminders and links in one transaction with one undo entry.
+    pub fn bulk_delete(&self, ids: &[String], label: &str) -> Result<Vec<Task>> {
+        let mut conn = Connection::open(&self.path)?;
+        let tx = WriteTx::begin(&mut conn)?;
+        let tasks = tasks_by_ids(&tx, ids)?;
+        let mut reminders = Vec::new();
+        let mut dependencies = Vec::new();
+        for task in &tasks {
+            let mut stmt = tx.prepare("SELECT task_id, blocked_by FROM task_dependencies WHERE task_id = ?1 OR blocked_by = ?1")?;
+            for dependency in stmt.query_map(params![task.id], dependency_from_row)? {
+                dependencies.push(dependency?);
+            }
+            let mut stmt = tx.prepare(&format!("SELECT {} FROM reminders WHERE task_id = ?1", REMINDER_COLUMNS))?;
+            for reminder in stmt.query_map(params![task.id], reminder_from_row)? {
+                reminders.push(reminder?);
+            }
+            tx.execute("DELETE FROM tasks WHERE id = ?1", params![task.id])?;
+            tx.execute("DELETE FROM imported_tasks WHERE task_id = ?1", params![task.id])?;
+            tx.execute("DELETE FROM reminders WHERE task_id = ?1", params![task.id])?;
+        }
+        delete_orphan_dependencies(&tx)?;
+        // Links between two deleted tasks were found from both ends
+        dependencies.sort_by(|a: &Dependency, b| (&a.task_id, &a.blocked_by).cmp(&(&b.task_id, &b.blocked_by)));
+        dependencies.dedup();
+        push_undo(&tx, label, &UndoSnapshot { tasks: tasks.clone(), revisions: HashMap::new(), reminders, dependencies })?;
+        tx.commit()?;
+
+        self.notify(ChangeKind::Deleted, tasks.iter().map(|t| t.id.clone()).collect());
+        Ok(tasks)
+    }
+
+    pub fn last_undo(&self) -> Result<Option<UndoEntry>> {
+        let conn = Connection::open(&self.path)?;
+        let mut stmt = conn.prepare("SELECT id, label, created_at FROM undo_entries ORDER BY id DESC LIMIT 1")?;
+        let mut rows = stmt.query_map([], |row| Ok(UndoEntry { id: row.get(0)?, label: row.get(1)?, created_at: row.get(2)? }))?;
+        rows.next().transpose()
+    }
+
+    /// Puts back the tasks saved by the newest undo entry and removes the entry. Deleted tasks return
+    /// at their old position with their reminders and dependencies; calendar events and sync links
+    /// are not restored. Tasks written since the entry was made (edited, or deleted ones created
+    /// again) keep the newer version.
+    pub fn undo(&self) -> Result<Option<UndoEntry>> {
+        let mut conn = Connection::open(&self.path)?;
+        let tx = WriteTx::begin(&mut conn)?;
+        let entry: Option<(UndoEntry, String)> = {
+            let mut stmt = tx.prepare("SELECT id, label, created_at, snapshot FROM undo_entries ORDER BY id DESC LIMIT 1")?;
+            let mut rows = stmt.query_map([], |row| {
+                Ok((UndoEntry { id: row.get(0)?, label: row.get(1)?, created_at: row.get(2)? }, row.get(3)?))
+            })?;
+            rows.next().transpose()?
+        };
+        let Some((entry, snapshot)) = entry else {
+            return Ok(None);
+        };
+        let snapshot: UndoSnapshot = serde_json::from_str(&snapshot)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e)))?;
+
+        let (mut added, mut updated, mut skipped) = (Vec::new(), Vec::new(), Vec::new());
+        for task in &snapshot.tasks {
+            let revision: Option<i64> = tx
+                .query_row("SELECT revision FROM tasks WHERE id = ?1", params![task.id], |row| row.get(0))
+                .optional()?;
+            match revision {
+                None => {
+                    insert_task(&tx, task, task.position)?;
+                    added.push(task.id.clone());
+                }
+                Some(revision) if snapshot.revisions.get(&task.id) == Some(&revision) => {
+                    update_task_row(&tx, task)?;
+                    updated.push(task.id.clone());
+                }
+                Some(_) => {
+                    skipped.push(task.id.as_str());
+                    continue;
+                }
+            }
+            // Back to the revision it had before the write, which older entries recorded
+            tx.execute("UPDATE tasks SET revision = ?2 WHERE id = ?1", params![task.id, task.revision])?;
+        }
+        let restored = |id: &str| !skipped.contains(&id);
+        for reminder in snapshot.reminders.iter().filter(|r| restored(&r.task_id)) {
+            tx.execute(
+                "INSERT OR REPLACE INTO reminders (id, task_id, remind_at, offset_minutes, snoozed_until, fired_for)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    reminder.id,
+                    reminder.task_id,
+                    reminder.remind_at,
+                    reminder.offset_minutes,
+                    reminder.snoozed_until,
+                    reminder.fired_for
+                ],
+            )?;
+        }
+        for dependency in snapshot.dependencies.iter().filter(|d| restored(&d.task_id) && restored(&d.blocked_by)) {
+            tx.execute(
+                "INSERT OR IGNORE INTO task_dependencies (task_id, blocked_by)
+                 SELECT ?1, ?2 WHERE EXISTS (SELECT 1 FROM tasks WHERE id = ?1) AND EXISTS (SELECT 1 FROM tasks WHERE id = ?2)",
+                params![dependency.task_id, dependency.blocked_by],
+            )?;
+        }
+        tx.execute("DELETE FROM undo_entries WHERE id = ?1", params![entry.id])?;
+        tx.commit()?;
+
+        for (kind, ids) in [(ChangeKind::Added, added), (ChangeKind::Updated, updated)] {
+            if !ids.is_empty() {
+                self.notify(kind, ids);
+            }
+        }
+        Ok(Some(entry))
+    }
+
+    pub fn update_task_order(&self, ordered_ids: Vec<String>) -> Result<()> {
+        let mut conn = Connection::open(&self.path)?;
+        let tx = WriteTx::begin(&mut conn)?;
         
         for (index, id) in ordered_ids.iter().enumerate() {
             tx.execute(
@@ -180,6 +1896,7 @@ impl Database {
         }
         
         tx.commit()?;
+        self.notify(ChangeKind::Reordered, ordered_ids);
         Ok(())
     }
 }
@@ -0,0 +1,107 @@
+use crate::db::{Database, Dependency, Task};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Task that can start now because the last open task it waited for was completed, as sent
+/// to the notifier and the frontend's "task-unblocked" event.
+#[derive(Debug, Clone, Serialize)]
+pub struct Unblocked {
+    pub task: Task,
+    pub blockers: Vec<Task>,
+}
+
+/// Chain of blockers leading from `from` to `to`, both included, or None if
+/// `from` doesn't wait for `to`, directly or not. Linking `to` to `from` would close that loop.
+pub fn path(edges: &[Dependency], from: &str, to: &str) -> Option<Vec<String>> {
+    if from == to {
+        return Some(vec![to.to_string()]);
+    }
+    let mut blockers: HashMap<&str, Vec<&str>> = HashMap::new();
+    for edge in edges {
+        blockers.entry(edge.task_id.as_str()).or_default().push(edge.blocked_by.as_str());
+    }
+    // Breadth first, so the error names the shortest loop
+    let mut came_from: HashMap<&str, &str> = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+    while let Some(id) = queue.pop_front() {
+        for &next in blockers.get(id).into_iter().flatten() {
+            if next == from || came_from.contains_key(next) {
+                continue;
+            }
+            came_from.insert(next, id);
+            if next == to {
+                let mut path = vec![to.to_string()];
+                let mut step = to;
+                while let Some(&prev) = came_from.get(step) {
+                    path.push(prev.to_string());
+                    step = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            queue.push_back(next);
+        }
+    }
+    None
+}
+
+/// Makes `task_id` wait until `blocked_by` is completed.
+pub fn link(db: &Database, task_id: &str, blocked_by: &str) -> Result<(), String> {
+    if task_id == blocked_by {
+        return Err("A task can't depend on itself".to_string());
+    }
+    db.add_dependency(task_id, blocked_by).map_err(|e| e.to_string())
+}
+
+pub fn unlink(db: &Database, task_id: &str, blocked_by: &str) -> Result<(), String> {
+    db.remove_dependency(task_id, blocked_by).map_err(|e| e.to_string())
+}
+
+/// Notification text, e.g. "Ready to start · Deploy is done".
+pub fn message(unblocked: &Unblocked) -> String {
+    match unblocked.blockers.as_slice() {
+        [blocker] => format!("Ready to start · {} is done", blocker.title),
+        blockers => format!("Ready to start · {} tasks it waited for are done", blockers.len()),
+    }
+}
+
+/// Remembers which tasks are blocked and by what, to tell after a write which of them became ready.
+pub struct UnblockTracker {
+    // Blocked task id to the ids of its open blockers
+    blocked: HashMap<String, HashSet<String>>,
+}
+
+fn open_blockers(db: &Database) -> Result<HashMap<String, HashSet<String>>, String> {
+    let mut blocked: HashMap<String, HashSet<String>> = HashMap::new();
+    for edge in db.get_open_dependencies().map_err(|e| e.to_string())? {
+        blocked.entry(edge.task_id).or_default().insert(edge.blocked_by);
+    }
+    Ok(blocked)
+}
+
+impl UnblockTracker {
+    pub fn new(db: &Database) -> Result<Self, String> {
+        Ok(Self { blocked: open_blockers(db)? })
+    }
+
+    /// Open tasks that were blocked at the last check and aren't anymore because one of their
+    /// blockers was completed. Tasks freed only by removing a link or deleting a blocker don't count.
+    pub fn check(&mut self, db: &Database) -> Result<Vec<Unblocked>, String> {
+        let blocked = open_blockers(db)?;
+        let mut unblocked = Vec::new();
+        let mut freed: Vec<_> = self.blocked.iter().filter(|(id, _)| !blocked.contains_key(*id)).collect();
+        freed.sort_by_key(|(id, _)| *id);
+        for (id, was_waiting_for) in freed {
+            let Some(task) = db.get_task_by_id(id).map_err(|e| e.to_string())? else {
+                continue;
+            };
+            let blockers = db.get_blockers(id).map_err(|e| e.to_string())?;
+            let completed = blockers.iter().any(|b| b.completed && was_waiting_for.contains(&b.id));
+            if !task.completed && completed {
+                unblocked.push(Unblocked { task, blockers });
+            }
+        }
+        self.blocked = blocked;
+        Ok(unblocked)
+    }
+}
@@ -0,0 +1,179 @@
+use crate::db::{Database, Task};
+use crate::ical;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::thread;
+
+const ENABLED_SETTING: &str = "ics_feed_enabled";
+const PORT_SETTING: &str = "ics_feed_port";
+/// Random path segment, so other local users and web pages can't guess the feed URL.
+pub const TOKEN_SETTING: &str = "ics_feed_token";
+
+/// Next to the OAuth callback port.
+pub const DEFAULT_PORT: u16 = 14124;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeedStatus {
+    pub enabled: bool,
+    pub running: bool,
+    pub port: u16,
+    /// Subscription URL without filters, None while the feed is disabled
+    pub url: Option<String>,
+}
+
+/// Filters taken from the feed URL query, e.g. `?category=Work&category=Home&tag=urgent&completed=true&format=todo`.
+/// `list` is accepted as an alias of `category`. Repeated categories or tags match any of them.
+#[derive(Debug, Default, Clone)]
+pub struct FeedQuery {
+    pub categories: Vec<String>,
+    pub tags: Vec<String>,
+    pub include_completed: bool,
+    /// Serve VTODOs instead of deadline events
+    pub todos: bool,
+}
+
+impl FeedQuery {
+    pub fn parse(query: &str) -> Self {
+        let mut parsed = Self::default();
+        for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "category" | "list" if !value.trim().is_empty() => parsed.categories.push(value.trim().to_string()),
+                "tag" if !value.trim().is_empty() => parsed.tags.push(value.trim().to_string()),
+                "completed" => parsed.include_completed = matches!(value.as_ref(), "1" | "true" | "yes"),
+                "format" => parsed.todos = value.eq_ignore_ascii_case("todo"),
+                _ => {}
+            }
+        }
+        parsed
+    }
+
+    fn matches(&self, task: &Task) -> bool {
+        task.due_date.is_some()
+            && (self.include_completed || !task.completed)
+            && (self.categories.is_empty() || self.categories.iter().any(|c| c.eq_ignore_ascii_case(&task.category)))
+            && (self.tags.is_empty() || self.tags.iter().any(|tag| task.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))))
+    }
+}
+
+/// Calendar served to subscribers: tasks with a due date that pass the query filters.
+pub fn render(tasks: &[Task], query: &FeedQuery) -> String {
+    let tasks: Vec<Task> = tasks.iter().filter(|task| query.matches(task)).cloned().collect();
+    if query.todos {
+        ical::export_tasks(&tasks, Utc::now())
+    } else {
+        ical::export_deadline_events(&tasks, Utc::now())
+    }
+}
+
+fn new_token() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn token(db: &Database) -> Result<String, String> {
+    if let Some(token) = db.get_setting(TOKEN_SETTING).map_err(|e| e.to_string())? {
+        return Ok(token);
+    }
+    let token = new_token();
+    db.set_setting(TOKEN_SETTING, &token).map_err(|e| e.to_string())?;
+    Ok(token)
+}
+
+/// Whether the feed should run and on which port.
+pub fn settings(db: &Database) -> Result<(bool, u16), String> {
+    let enabled = db.get_setting(ENABLED_SETTING).map_err(|e| e.to_string())?.as_deref() == Some("true");
+    let port = db
+        .get_setting(PORT_SETTING)
+        .map_err(|e| e.to_string())?
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(DEFAULT_PORT);
+    Ok((enabled, port))
+}
+
+pub fn save_settings(db: &Database, enabled: bool, port: u16) -> Result<(), String> {
+    if port == 0 {
+        return Err("Port must be between 1 and 65535".to_string());
+    }
+    db.set_setting(ENABLED_SETTING, if enabled { "true" } else { "false" }).map_err(|e| e.to_string())?;
+    db.set_setting(PORT_SETTING, &port.to_string()).map_err(|e| e.to_string())
+}
+
+/// Replaces the URL token; existing subscriptions stop working.
+pub fn reset_token(db: &Database) -> Result<(), String> {
+    db.set_setting(TOKEN_SETTING, &new_token()).map_err(|e| e.to_string())
+}
+
+pub fn status(db: &Database, server: Option<&FeedServer>) -> Result<FeedStatus, String> {
+    let (enabled, port) = settings(db)?;
+    let port = server.map(FeedServer::port).unwrap_or(port);
+    let url = if enabled { Some(format!("http://127.0.0.1:{}/{}.ics", port, token(db)?)) } else { None };
+    Ok(FeedStatus { enabled, running: server.is_some(), port, url })
+}
+
+fn respond(request: tiny_http::Request, status: u16, body: String, content_type: &str) {
+    let header = tiny_http::Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()).unwrap();
+    let _ = request.respond(tiny_http::Response::from_string(body).with_status_code(status).with_header(header));
+}
+
+fn handle(db: &Database, request: tiny_http::Request) {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((url.as_str(), ""));
+    // Read on every request so a reset token takes effect immediately
+    let authorized = match token(db) {
+        Ok(token) => path == format!("/{}.ics", token),
+        Err(e) => {
+            eprintln!("Failed to read feed token: {}", e);
+            false
+        }
+    };
+    if !authorized || !matches!(request.method(), tiny_http::Method::Get | tiny_http::Method::Head) {
+        respond(request, 404, "Not found".to_string(), "text/plain");
+        return;
+    }
+
+    match db.get_tasks() {
+        Ok(tasks) => respond(request, 200, render(&tasks, &FeedQuery::parse(query)), "text/calendar; charset=utf-8"),
+        Err(e) => {
+            eprintln!("Failed to serve feed: {}", e);
+            respond(request, 500, "Failed to load tasks".to_string(), "text/plain");
+        }
+    }
+}
+
+/// Local HTTP server for the feed, bound to 127.0.0.1 only. Stops when dropped.
+pub struct FeedServer {
+    server: Arc<tiny_http::Server>,
+    port: u16,
+}
+
+impl FeedServer {
+    /// Starts serving on `port` (0 picks a free port).
+    pub fn start(db: Database, port: u16) -> Result<Self, String> {
+        let server = tiny_http::Server::http(("127.0.0.1", port))
+            .map_err(|e| format!("Failed to start feed server on port {}: {}", port, e))?;
+        let port = server.server_addr().to_ip().map(|addr| addr.port()).unwrap_or(port);
+        let server = Arc::new(server);
+
+        let thread_server = Arc::clone(&server);
+        thread::spawn(move || {
+            for request in thread_server.incoming_requests() {
+                handle(&db, request);
+            }
+        });
+        Ok(Self { server, port })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl Drop for FeedServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
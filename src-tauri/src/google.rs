@@ -1,25 +1,92 @@
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use crate::db::Database;
+use crate::db::{Account, AccountTokens, Database, Due, Event, Task, TaskEventLink};
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::str::FromStr;
+use serde_json::{json, Value};
 use std::env;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
 const REDIRECT_URI: &str = "http://localhost:14123";
-const AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
-const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const PROVIDER: &str = "google";
+const CREDENTIALS_FILE: &str = "google_oauth.json";
 const SCOPE: &str = "https://www.googleapis.com/auth/tasks https://www.googleapis.com/auth/calendar https://www.googleapis.com/auth/userinfo.email https://www.googleapis.com/auth/userinfo.profile";
 
+// Refresh the access token this many seconds before Google says it expires
+const TOKEN_EXPIRY_MARGIN_SECS: i64 = 60;
+
+/// Base URLs for every Google endpoint we talk to.
+/// Production code uses `Default`; tests point these at a local stand-in server.
+#[derive(Debug, Clone)]
+pub struct GoogleEndpoints {
+    pub auth_url: String,
+    pub token_url: String,
+    pub userinfo_url: String,
+    pub revoke_url: String,
+    pub tasks_api_url: String,
+    pub calendar_api_url: String,
+}
+
+impl Default for GoogleEndpoints {
+    fn default() -> Self {
+        Self {
+            auth_url: "https://accounts.google.com/o/oauth2/v2/auth".to_string(),
+            token_url: "https://oauth2.googleapis.com/token".to_string(),
+            userinfo_url: "https://www.googleapis.com/oauth2/v2/userinfo".to_string(),
+            revoke_url: "https://oauth2.googleapis.com/revoke".to_string(),
+            tasks_api_url: "https://tasks.googleapis.com/tasks/v1".to_string(),
+            calendar_api_url: "https://www.googleapis.com/calendar/v3".to_string(),
+        }
+    }
+}
+
+impl GoogleEndpoints {
+    /// Serve every endpoint from a single host, keeping Google's paths.
+    pub fn with_base_url(base_url: &str) -> Self {
+        let base = base_url.trim_end_matches('/');
+        Self {
+            auth_url: format!("{}/o/oauth2/v2/auth", base),
+            token_url: format!("{}/token", base),
+            userinfo_url: format!("{}/oauth2/v2/userinfo", base),
+            revoke_url: format!("{}/revoke", base),
+            tasks_api_url: format!("{}/tasks/v1", base),
+            calendar_api_url: format!("{}/calendar/v3", base),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenResponse {
     access_token: String,
     expires_in: i64,
     refresh_token: Option<String>,
+    #[serde(default)]
     scope: String,
     token_type: String,
 }
 
+/// What to do with tasks imported from Google when the account is disconnected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportedTaskAction {
+    /// Leave the tasks and their Google link untouched
+    Keep,
+    /// Keep the tasks but turn them into plain local tasks
+    Detach,
+    /// Remove the tasks from the local database
+    Delete,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DisconnectSummary {
+    pub token_revoked: bool,
+    pub tasks_detached: usize,
+    pub tasks_deleted: usize,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GoogleUser {
     pub id: String,
@@ -38,6 +105,8 @@ pub struct TaskList {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TaskListResponse {
     pub items: Option<Vec<TaskList>>,
+    #[serde(rename = "nextPageToken")]
+    pub next_page_token: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -56,21 +125,153 @@ pub struct GoogleTask {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TaskResponse {
     pub items: Option<Vec<GoogleTask>>,
+    #[serde(rename = "nextPageToken")]
+    pub next_page_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Calendar {
+    pub id: String,
+    pub summary: String,
+    pub primary: Option<bool>,
+    // Whether the calendar is shown in the Google Calendar UI
+    pub selected: Option<bool>,
+    #[serde(rename = "backgroundColor")]
+    pub background_color: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CalendarListResponse {
+    pub items: Option<Vec<Calendar>>,
+    #[serde(rename = "nextPageToken")]
+    pub next_page_token: Option<String>,
+}
+
+/// Either `date` (all-day events) or `dateTime` is set.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventTime {
+    pub date: Option<String>,
+    #[serde(rename = "dateTime")]
+    pub date_time: Option<String>,
+    #[serde(rename = "timeZone")]
+    pub time_zone: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleEvent {
+    pub id: String,
+    pub status: Option<String>,
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub start: EventTime,
+    pub end: EventTime,
+    #[serde(rename = "htmlLink")]
+    pub html_link: Option<String>,
+    #[serde(rename = "recurringEventId")]
+    pub recurring_event_id: Option<String>,
+    #[serde(rename = "extendedProperties")]
+    pub extended_properties: Option<ExtendedProperties>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExtendedProperties {
+    pub private: Option<HashMap<String, String>>,
+}
+
+// Private event property marking events created for a local task
+const TASK_ID_PROPERTY: &str = "taskerTaskId";
+
+impl GoogleEvent {
+    pub fn task_id(&self) -> Option<&str> {
+        self.extended_properties.as_ref()?.private.as_ref()?.get(TASK_ID_PROPERTY).map(String::as_str)
+    }
+}
+
+/// Optional time block for a task event. Without one the event is all-day on the due date.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeBlock {
+    /// Local start time, "HH:MM"
+    pub start_time: String,
+    pub duration_minutes: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventsResponse {
+    pub items: Option<Vec<GoogleEvent>>,
+    #[serde(rename = "nextPageToken")]
+    pub next_page_token: Option<String>,
+}
+
+/// OAuth client registered in the Google Cloud console.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoogleCredentials {
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+/// Resolves the OAuth client, first match wins:
+/// 1. `google_client_id` / `google_client_secret` (sealed) in the settings table
+/// 2. `google_oauth.json` in the app data directory
+/// 3. `GOOGLE_CLIENT_ID` / `GOOGLE_CLIENT_SECRET` environment variables (`.env` in development)
+/// 4. The same variables captured at build time
+pub fn load_credentials(db: &Database) -> Result<GoogleCredentials, String> {
+    let from_settings = (
+        db.get_setting("google_client_id").map_err(|e| e.to_string())?,
+        db.get_secret_setting("google_client_secret").map_err(|e| e.to_string())?,
+    );
+    if let Some(credentials) = complete_pair("settings", from_settings)? {
+        return Ok(credentials);
+    }
+
+    let config_path = db.dir().join(CREDENTIALS_FILE);
+    if config_path.exists() {
+        let content = std::fs::read_to_string(&config_path)
+            .map_err(|e| format!("Failed to read {}: {}", config_path.display(), e))?;
+        let credentials: GoogleCredentials = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid {}: {}", config_path.display(), e))?;
+        return Ok(credentials);
+    }
+
+    let from_env = (env::var("GOOGLE_CLIENT_ID").ok(), env::var("GOOGLE_CLIENT_SECRET").ok());
+    if let Some(credentials) = complete_pair("environment", from_env)? {
+        return Ok(credentials);
+    }
+
+    let built_in = (
+        option_env!("GOOGLE_CLIENT_ID").map(str::to_string),
+        option_env!("GOOGLE_CLIENT_SECRET").map(str::to_string),
+    );
+    if let Some(credentials) = complete_pair("build", built_in)? {
+        return Ok(credentials);
+    }
+
+    Err("Google OAuth client is not configured. Set a client ID and secret in Settings.".to_string())
 }
 
-fn get_client_id() -> String {
-    env::var("GOOGLE_CLIENT_ID").expect("GOOGLE_CLIENT_ID must be set in .env")
+// A source with only one of the two values is a configuration mistake, not a fallthrough
+fn complete_pair(source: &str, pair: (Option<String>, Option<String>)) -> Result<Option<GoogleCredentials>, String> {
+    match pair {
+        (Some(client_id), Some(client_secret)) if !client_id.is_empty() && !client_secret.is_empty() => {
+            Ok(Some(GoogleCredentials { client_id, client_secret }))
+        }
+        (None, None) => Ok(None),
+        (Some(id), Some(secret)) if id.is_empty() && secret.is_empty() => Ok(None),
+        _ => Err(format!("Incomplete Google OAuth client in {}: both client ID and secret are required", source)),
+    }
 }
 
-fn get_client_secret() -> String {
-    env::var("GOOGLE_CLIENT_SECRET").expect("GOOGLE_CLIENT_SECRET must be set in .env")
+pub fn save_credentials(db: &Database, credentials: &GoogleCredentials) -> Result<(), String> {
+    db.set_setting("google_client_id", credentials.client_id.trim()).map_err(|e| e.to_string())?;
+    db.set_secret_setting("google_client_secret", credentials.client_secret.trim()).map_err(|e| e.to_string())
 }
 
-pub fn get_auth_url() -> String {
-    format!(
-        "{}?client_id={}&redirect_uri={}&response_type=code&scope={}&access_type=offline&prompt=consent",
-        AUTH_URL, get_client_id(), REDIRECT_URI, SCOPE
-    )
+pub fn get_auth_url(db: &Database, endpoints: &GoogleEndpoints) -> Result<String, String> {
+    let credentials = load_credentials(db)?;
+    Ok(format!(
+        "{}?client_id={}&redirect_uri={}&response_type=code&scope={}&access_type=offline&prompt=select_account%20consent",
+        endpoints.auth_url, credentials.client_id, REDIRECT_URI, SCOPE
+    ))
 }
 
 pub fn start_oauth_server() -> Result<String, String> {
@@ -89,13 +290,13 @@ pub fn start_oauth_server() -> Result<String, String> {
             if let Some(query_start) = url.find('?') {
                 let query = &url[query_start + 1..];
                 for param in query.split('&') {
-                    if let Some((key, value)) = param.split_once('=') {
-                        if key == "code" {
-                            let mut code_guard = auth_code_clone.lock().unwrap();
-                            *code_guard = Some(value.to_string());
-                            found_code = true;
-                            break;
-                        }
+                    if let Some((key, value)) = param.split_once('=')
+                        && key == "code"
+                    {
+                        let mut code_guard = auth_code_clone.lock().unwrap();
+                        *code_guard = Some(value.to_string());
+                        found_code = true;
+                        break;
                     }
                 }
             }
@@ -171,15 +372,28 @@ pub fn start_oauth_server() -> Result<String, String> {
     Err("Timeout waiting for authorization code".to_string())
 }
 
-pub async fn get_user_profile(db: &Database) -> Result<GoogleUser, String> {
-    let token = get_access_token(db).await?;
-    let client = Client::new();
-    
-    let res = client.get("https://www.googleapis.com/oauth2/v2/userinfo")
-        .header("Authorization", format!("Bearer {}", token))
+async fn send_user_profile(endpoints: &GoogleEndpoints, token: &str) -> Result<Response, String> {
+    Client::new().get(&endpoints.userinfo_url)
+        .bearer_auth(token)
         .send()
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| e.to_string())
+}
+
+async fn fetch_user_profile(endpoints: &GoogleEndpoints, token: &str) -> Result<GoogleUser, String> {
+    let res = send_user_profile(endpoints, token).await?;
+    if !res.status().is_success() {
+        return Err(format!("API Error: {:?}", res.text().await));
+    }
+
+    let user: GoogleUser = res.json().await.map_err(|e| e.to_string())?;
+    Ok(user)
+}
+
+pub async fn get_user_profile(db: &Database, endpoints: &GoogleEndpoints, account_id: &str) -> Result<GoogleUser, String> {
+    let res = send_authorized(db, endpoints, account_id, |client, token| {
+        client.get(&endpoints.userinfo_url).bearer_auth(token)
+    }).await?;
 
     if !res.status().is_success() {
         return Err(format!("API Error: {:?}", res.text().await));
@@ -189,16 +403,33 @@ pub async fn get_user_profile(db: &Database) -> Result<GoogleUser, String> {
     Ok(user)
 }
 
-pub async fn exchange_code(code: &str, db: &Database) -> Result<GoogleUser, String> {
+pub fn get_accounts(db: &Database) -> Result<Vec<Account>, String> {
+    db.get_accounts(PROVIDER).map_err(|e| e.to_string())
+}
+
+fn save_account(db: &Database, user: &GoogleUser, tokens: &AccountTokens) -> Result<(), String> {
+    let account = Account {
+        provider: PROVIDER.to_string(),
+        account_id: user.id.clone(),
+        email: user.email.clone(),
+        name: user.name.clone(),
+        picture: user.picture.clone(),
+    };
+    db.upsert_account(&account).map_err(|e| e.to_string())?;
+    db.set_account_tokens(PROVIDER, &user.id, tokens).map_err(|e| e.to_string())
+}
+
+pub async fn exchange_code(code: &str, db: &Database, endpoints: &GoogleEndpoints) -> Result<GoogleUser, String> {
+    let credentials = load_credentials(db)?;
     let client = Client::new();
     let mut params = HashMap::new();
     params.insert("code", code.to_string());
-    params.insert("client_id", get_client_id());
-    params.insert("client_secret", get_client_secret());
+    params.insert("client_id", credentials.client_id);
+    params.insert("client_secret", credentials.client_secret);
     params.insert("redirect_uri", REDIRECT_URI.to_string());
     params.insert("grant_type", "authorization_code".to_string());
 
-    let res = client.post(TOKEN_URL)
+    let res = client.post(&endpoints.token_url)
         .form(&params)
         .send()
         .await
@@ -210,57 +441,745 @@ pub async fn exchange_code(code: &str, db: &Database) -> Result<GoogleUser, Stri
 
     let token_res: TokenResponse = res.json().await.map_err(|e| e.to_string())?;
 
-    // Save tokens
-    db.set_setting("google_access_token", &token_res.access_token).map_err(|e| e.to_string())?;
-    if let Some(refresh_token) = token_res.refresh_token {
-        db.set_setting("google_refresh_token", &refresh_token).map_err(|e| e.to_string())?;
+    // The profile id is the account key, so it has to be fetched before anything is stored
+    let user = fetch_user_profile(endpoints, &token_res.access_token).await?;
+    let previous = db.get_account_tokens(PROVIDER, &user.id).map_err(|e| e.to_string())?;
+    let tokens = merge_tokens(previous, &token_res);
+    save_account(db, &user, &tokens)?;
+    Ok(user)
+}
+
+async fn send_refresh(db: &Database, endpoints: &GoogleEndpoints, refresh_token: &str) -> Result<Response, String> {
+    let credentials = load_credentials(db)?;
+    let client = Client::new();
+    let mut params = HashMap::new();
+    params.insert("refresh_token", refresh_token.to_string());
+    params.insert("client_id", credentials.client_id);
+    params.insert("client_secret", credentials.client_secret);
+    params.insert("grant_type", "refresh_token".to_string());
+
+    client.post(&endpoints.token_url)
+        .form(&params)
+        .send()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+async fn request_refresh(db: &Database, endpoints: &GoogleEndpoints, refresh_token: &str) -> Result<TokenResponse, String> {
+    let res = send_refresh(db, endpoints, refresh_token).await?;
+    if !res.status().is_success() {
+        return Err(format!("Failed to refresh token: {:?}", res.text().await));
+    }
+
+    res.json().await.map_err(|e| e.to_string())
+}
+
+pub async fn refresh_access_token(db: &Database, endpoints: &GoogleEndpoints, account_id: &str) -> Result<String, String> {
+    let previous = db.get_account_tokens(PROVIDER, account_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("No access token found")?;
+    let refresh_token = previous.refresh_token.clone().ok_or("No refresh token found")?;
+
+    let token_res = request_refresh(db, endpoints, &refresh_token).await?;
+    let tokens = merge_tokens(Some(previous), &token_res);
+    db.set_account_tokens(PROVIDER, account_id, &tokens).map_err(|e| e.to_string())?;
+    Ok(tokens.access_token)
+}
+
+fn merge_tokens(previous: Option<AccountTokens>, token_res: &TokenResponse) -> AccountTokens {
+    AccountTokens {
+        access_token: token_res.access_token.clone(),
+        // Google only returns a refresh token on the first consent, keep the old one otherwise
+        refresh_token: token_res.refresh_token.clone().or(previous.and_then(|t| t.refresh_token)),
+        expires_at: Some(chrono::Utc::now().timestamp() + token_res.expires_in),
     }
-    
-    // Fetch and return user profile
-    get_user_profile(db).await
 }
 
-async fn get_access_token(db: &Database) -> Result<String, String> {
-    if let Some(token) = db.get_setting("google_access_token").map_err(|e| e.to_string())? {
-        Ok(token)
-    } else {
-        Err("No access token found".to_string())
+async fn get_access_token(db: &Database, endpoints: &GoogleEndpoints, account_id: &str) -> Result<String, String> {
+    let tokens = db.get_account_tokens(PROVIDER, account_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("No access token found")?;
+
+    match tokens.expires_at {
+        Some(expires_at) if expires_at - TOKEN_EXPIRY_MARGIN_SECS <= chrono::Utc::now().timestamp() => {
+            refresh_access_token(db, endpoints, account_id).await
+        }
+        _ => Ok(tokens.access_token),
     }
 }
 
-pub async fn fetch_task_lists(db: &Database) -> Result<Vec<TaskList>, String> {
-    let token = get_access_token(db).await?;
+// Sends a request with the account's access token, refreshing and retrying once on 401
+async fn send_authorized<F>(db: &Database, endpoints: &GoogleEndpoints, account_id: &str, build: F) -> Result<Response, String>
+where
+    F: Fn(&Client, &str) -> RequestBuilder,
+{
     let client = Client::new();
-    
-    let res = client.get("https://tasks.googleapis.com/tasks/v1/users/@me/lists")
-        .header("Authorization", format!("Bearer {}", token))
-        .send()
-        .await
+    let token = get_access_token(db, endpoints, account_id).await?;
+    let res = build(&client, &token).send().await.map_err(|e| e.to_string())?;
+
+    if res.status() != StatusCode::UNAUTHORIZED {
+        return Ok(res);
+    }
+    let has_refresh_token = db.get_account_tokens(PROVIDER, account_id)
+        .map_err(|e| e.to_string())?
+        .is_some_and(|t| t.refresh_token.is_some());
+    if !has_refresh_token {
+        return Ok(res);
+    }
+
+    let token = refresh_access_token(db, endpoints, account_id).await?;
+    build(&client, &token).send().await.map_err(|e| e.to_string())
+}
+
+const LEGACY_TOKEN_KEYS: [&str; 3] = ["google_access_token", "google_refresh_token", "google_token_expires_at"];
+
+fn delete_legacy_tokens(db: &Database) -> Result<(), String> {
+    for key in LEGACY_TOKEN_KEYS {
+        db.delete_setting(key).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// A 400 or 401 means Google won't take the legacy tokens again (revoked, or expired without a
+// refresh token), so they are dropped. Other failures keep them for the next start.
+async fn check_legacy_response(db: &Database, res: Response, context: &str) -> Result<Response, String> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res);
+    }
+    let error = format!("{}: {:?}", context, res.text().await);
+    if status == StatusCode::BAD_REQUEST || status == StatusCode::UNAUTHORIZED {
+        delete_legacy_tokens(db)?;
+        return Err(format!("{} (the old Google sign-in was removed, connect the account again)", error));
+    }
+    Err(error)
+}
+
+/// Moves tokens saved by the single-account version (`google_*` settings, sealed by
+/// `secrets::open` meanwhile) into the `accounts` table. Returns the migrated user, if there was one.
+/// Run once at startup; tokens Google rejects are deleted, on network errors they stay for the next start.
+pub async fn migrate_legacy_tokens(db: &Database, endpoints: &GoogleEndpoints) -> Result<Option<GoogleUser>, String> {
+    let access_token = db.get_secret_setting("google_access_token").map_err(|e| e.to_string())?;
+    let refresh_token = db.get_secret_setting("google_refresh_token").map_err(|e| e.to_string())?;
+
+    let tokens = match (access_token, refresh_token) {
+        (_, Some(refresh_token)) => {
+            let res = send_refresh(db, endpoints, &refresh_token).await?;
+            let res = check_legacy_response(db, res, "Failed to refresh token").await?;
+            let token_res: TokenResponse = res.json().await.map_err(|e| e.to_string())?;
+            merge_tokens(Some(AccountTokens { access_token: String::new(), refresh_token: Some(refresh_token), expires_at: None }), &token_res)
+        }
+        (Some(access_token), None) => AccountTokens { access_token, refresh_token: None, expires_at: None },
+        (None, None) => return Ok(None),
+    };
+
+    let res = send_user_profile(endpoints, &tokens.access_token).await?;
+    let res = check_legacy_response(db, res, "API Error").await?;
+    let user: GoogleUser = res.json().await.map_err(|e| e.to_string())?;
+    save_account(db, &user, &tokens)?;
+    db.assign_unowned_imports(PROVIDER, &user.id).map_err(|e| e.to_string())?;
+    delete_legacy_tokens(db)?;
+    Ok(Some(user))
+}
+
+pub async fn fetch_task_lists(db: &Database, endpoints: &GoogleEndpoints, account_id: &str) -> Result<Vec<TaskList>, String> {
+    let url = format!("{}/users/@me/lists", endpoints.tasks_api_url);
+    let mut lists = Vec::new();
+    let mut page_token: Option<String> = None;
+
+    loop {
+        let res = send_authorized(db, endpoints, account_id, |client, token| {
+            let mut req = client.get(&url)
+                .bearer_auth(token)
+                .query(&[("maxResults", "100")]);
+            if let Some(page_token) = &page_token {
+                req = req.query(&[("pageToken", page_token)]);
+            }
+            req
+        }).await?;
+
+        if !res.status().is_success() {
+            return Err(format!("API Error: {:?}", res.text().await));
+        }
+
+        let list_res: TaskListResponse = res.json().await.map_err(|e| e.to_string())?;
+        lists.extend(list_res.items.unwrap_or_default());
+
+        match list_res.next_page_token {
+            Some(next) => page_token = Some(next),
+            None => break,
+        }
+    }
+
+    Ok(lists)
+}
+
+pub async fn fetch_tasks(db: &Database, endpoints: &GoogleEndpoints, account_id: &str, tasklist_id: &str) -> Result<Vec<GoogleTask>, String> {
+    let url = format!("{}/lists/{}/tasks", endpoints.tasks_api_url, tasklist_id);
+    let mut tasks = Vec::new();
+    let mut page_token: Option<String> = None;
+
+    loop {
+        let res = send_authorized(db, endpoints, account_id, |client, token| {
+            let mut req = client.get(&url)
+                .bearer_auth(token)
+                .query(&[("showCompleted", "true"), ("showHidden", "true"), ("maxResults", "100")]);
+            if let Some(page_token) = &page_token {
+                req = req.query(&[("pageToken", page_token)]);
+            }
+            req
+        }).await?;
+
+        if !res.status().is_success() {
+            return Err(format!("API Error: {:?}", res.text().await));
+        }
+
+        let task_res: TaskResponse = res.json().await.map_err(|e| e.to_string())?;
+        tasks.extend(task_res.items.unwrap_or_default());
+
+        match task_res.next_page_token {
+            Some(next) => page_token = Some(next),
+            None => break,
+        }
+    }
+
+    Ok(tasks)
+}
+
+/// Google Tasks only keeps the date of a due, sent as midnight UTC ("2026-01-10T00:00:00.000Z").
+/// The calendar date is read as written, converting to local time would shift it a day east of UTC.
+pub fn parse_google_due(due: &str) -> Option<NaiveDate> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(due) {
+        return Some(dt.date_naive());
+    }
+    NaiveDate::parse_from_str(due.get(..10)?, "%Y-%m-%d").ok()
+}
+
+// Account setting holding the local category a task list imports into
+const LIST_CATEGORY_PREFIX: &str = "list_category:";
+
+/// Local category per Google task list id, as remembered from earlier imports or set by the user.
+pub fn get_list_categories(db: &Database, account_id: &str) -> Result<HashMap<String, String>, String> {
+    let settings = db.get_account_settings_with_prefix(PROVIDER, account_id, LIST_CATEGORY_PREFIX)
         .map_err(|e| e.to_string())?;
+    Ok(settings
+        .into_iter()
+        .map(|(key, category)| (key[LIST_CATEGORY_PREFIX.len()..].to_string(), category))
+        .collect())
+}
 
-    if !res.status().is_success() {
-        return Err(format!("API Error: {:?}", res.text().await));
+pub fn set_list_category(db: &Database, account_id: &str, list_id: &str, category: &str) -> Result<(), String> {
+    let category = category.trim();
+    if category.is_empty() {
+        return Err("Category must not be empty".to_string());
+    }
+    db.set_account_setting(PROVIDER, account_id, &format!("{}{}", LIST_CATEGORY_PREFIX, list_id), category)
+        .map_err(|e| e.to_string())
+}
+
+// The first import of a list goes to a category named after it, later imports keep using
+// that category even if the list is renamed on Google
+fn list_category(db: &Database, account_id: &str, list: &TaskList) -> Result<String, String> {
+    let key = format!("{}{}", LIST_CATEGORY_PREFIX, list.id);
+    if let Some(category) = db.get_account_setting(PROVIDER, account_id, &key).map_err(|e| e.to_string())? {
+        return Ok(category);
+    }
+    set_list_category(db, account_id, &list.id, &list.title)?;
+    Ok(list.title.trim().to_string())
+}
+
+// Google only tracks the due date, so a local due time is kept when the date moves
+fn apply_google_due(task: &mut Task, due_date: Option<NaiveDate>) {
+    match due_date {
+        Some(date) => {
+            task.due_date = Some(date);
+            if task.start_date.is_some_and(|start| start > date) {
+                task.start_date = None;
+            }
+        }
+        None => {
+            task.due_date = None;
+            task.due_time = None;
+            task.timezone = None;
+        }
     }
+}
+
+async fn import_list(db: &Database, endpoints: &GoogleEndpoints, account_id: &str, list: &TaskList) -> Result<usize, String> {
+    let category = list_category(db, account_id, list)?;
+    let mut g_tasks = fetch_tasks(db, endpoints, account_id, &list.id).await?;
+    // Position strings are zero-padded and sort lexicographically. New tasks are appended
+    // to the local order in Google's order, tasks without a position go last.
+    g_tasks.sort_by(|a, b| (a.position.is_none(), &a.position).cmp(&(b.position.is_none(), &b.position)));
+    let mut count = 0;
+
+    for g_task in g_tasks {
+        let completed = g_task.status == "completed";
+        let due_date = g_task.due.as_deref().and_then(parse_google_due);
 
-    let list_res: TaskListResponse = res.json().await.map_err(|e| e.to_string())?;
-    Ok(list_res.items.unwrap_or_default())
+        match db.get_task_by_id(&g_task.id).map_err(|e| e.to_string())? {
+            // Re-import only touches fields Google owns; priority, category and order stay local
+            Some(mut task) => {
+                task.title = g_task.title;
+                task.description = g_task.notes;
+                task.completed = completed;
+                apply_google_due(&mut task, due_date);
+                db.update_task(task).map_err(|e| e.to_string())?;
+            }
+            None => {
+                let task = Task {
+                    id: g_task.id.clone(),
+                    title: g_task.title,
+                    description: g_task.notes,
+                    completed,
+                    category: category.clone(),
+                    due_date,
+                    // Google Tasks has no priority, add_task appends to the end
+                    ..Default::default()
+                };
+                db.add_task(task).map_err(|e| e.to_string())?;
+            }
+        }
+        db.link_imported_task(&g_task.id, PROVIDER, account_id, &list.id).map_err(|e| e.to_string())?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+pub async fn import_tasks(db: &Database, endpoints: &GoogleEndpoints, account_id: &str, tasklist_id: &str) -> Result<usize, String> {
+    let lists = fetch_task_lists(db, endpoints, account_id).await?;
+    let list = lists.iter().find(|l| l.id == tasklist_id).ok_or("Task list not found")?;
+    import_list(db, endpoints, account_id, list).await
+}
+
+/// Imports every task list of the account. Returns the number of tasks per list id.
+pub async fn import_all_tasks(db: &Database, endpoints: &GoogleEndpoints, account_id: &str) -> Result<HashMap<String, usize>, String> {
+    let mut counts = HashMap::new();
+    for list in fetch_task_lists(db, endpoints, account_id).await? {
+        let count = import_list(db, endpoints, account_id, &list).await?;
+        counts.insert(list.id, count);
+    }
+    Ok(counts)
 }
 
-pub async fn fetch_tasks(db: &Database, tasklist_id: &str) -> Result<Vec<GoogleTask>, String> {
-    let token = get_access_token(db).await?;
+// Revoking the refresh token also invalidates every access token issued from it
+async fn revoke_token(endpoints: &GoogleEndpoints, token: &str) -> Result<(), String> {
     let client = Client::new();
-    
-    let res = client.get(&format!("https://tasks.googleapis.com/tasks/v1/lists/{}/tasks", tasklist_id))
-        .header("Authorization", format!("Bearer {}", token))
-        .query(&[("showCompleted", "true"), ("showHidden", "true")])
+    let res = client.post(&endpoints.revoke_url)
+        .form(&[("token", token)])
         .send()
         .await
         .map_err(|e| e.to_string())?;
 
+    if !res.status().is_success() {
+        return Err(format!("Failed to revoke token: {:?}", res.text().await));
+    }
+    Ok(())
+}
+
+/// Revokes the account's Google grant and removes the account and its sync state from the local database.
+/// Local cleanup happens even if revocation fails (offline, token already revoked),
+/// the summary reports whether Google acknowledged the revocation.
+pub async fn disconnect(db: &Database, endpoints: &GoogleEndpoints, account_id: &str, imported: ImportedTaskAction) -> Result<DisconnectSummary, String> {
+    let tokens = db.get_account_tokens(PROVIDER, account_id).map_err(|e| e.to_string())?;
+
+    let token_revoked = match tokens.map(|t| t.refresh_token.unwrap_or(t.access_token)) {
+        Some(token) => revoke_token(endpoints, &token).await.is_ok(),
+        None => false,
+    };
+
+    let (tasks_detached, tasks_deleted) = db.delete_account(PROVIDER, account_id, imported).map_err(|e| e.to_string())?;
+
+    Ok(DisconnectSummary { token_revoked, tasks_detached, tasks_deleted })
+}
+
+pub async fn fetch_calendars(db: &Database, endpoints: &GoogleEndpoints, account_id: &str) -> Result<Vec<Calendar>, String> {
+    let url = format!("{}/users/me/calendarList", endpoints.calendar_api_url);
+    let mut calendars = Vec::new();
+    let mut page_token: Option<String> = None;
+
+    loop {
+        let res = send_authorized(db, endpoints, account_id, |client, token| {
+            let mut req = client.get(&url)
+                .bearer_auth(token)
+                .query(&[("maxResults", "250")]);
+            if let Some(page_token) = &page_token {
+                req = req.query(&[("pageToken", page_token)]);
+            }
+            req
+        }).await?;
+
+        if !res.status().is_success() {
+            return Err(format!("API Error: {:?}", res.text().await));
+        }
+
+        let list_res: CalendarListResponse = res.json().await.map_err(|e| e.to_string())?;
+        calendars.extend(list_res.items.unwrap_or_default());
+
+        match list_res.next_page_token {
+            Some(next) => page_token = Some(next),
+            None => break,
+        }
+    }
+
+    Ok(calendars)
+}
+
+// Calendar ids are often email addresses or contain '#', so they need path encoding
+fn calendar_events_url(endpoints: &GoogleEndpoints, calendar_id: &str) -> Result<String, String> {
+    let mut url = url::Url::parse(&endpoints.calendar_api_url).map_err(|e| e.to_string())?;
+    url.path_segments_mut()
+        .map_err(|_| "Invalid calendar API URL".to_string())?
+        .pop_if_empty()
+        .extend(["calendars", calendar_id, "events"]);
+    Ok(url.to_string())
+}
+
+/// Fetches events overlapping `[time_min, time_max)`, with recurring events expanded into instances.
+pub async fn fetch_events(
+    db: &Database,
+    endpoints: &GoogleEndpoints,
+    account_id: &str,
+    calendar_id: &str,
+    time_min: DateTime<Utc>,
+    time_max: DateTime<Utc>,
+) -> Result<Vec<GoogleEvent>, String> {
+    let url = calendar_events_url(endpoints, calendar_id)?;
+    let time_min = time_min.to_rfc3339();
+    let time_max = time_max.to_rfc3339();
+    let mut events = Vec::new();
+    let mut page_token: Option<String> = None;
+
+    loop {
+        let res = send_authorized(db, endpoints, account_id, |client, token| {
+            let mut req = client.get(&url)
+                .bearer_auth(token)
+                .query(&[
+                    ("timeMin", time_min.as_str()),
+                    ("timeMax", time_max.as_str()),
+                    ("singleEvents", "true"),
+                    ("orderBy", "startTime"),
+                    ("maxResults", "250"),
+                ]);
+            if let Some(page_token) = &page_token {
+                req = req.query(&[("pageToken", page_token)]);
+            }
+            req
+        }).await?;
+
+        if !res.status().is_success() {
+            return Err(format!("API Error: {:?}", res.text().await));
+        }
+
+        let events_res: EventsResponse = res.json().await.map_err(|e| e.to_string())?;
+        events.extend(events_res.items.unwrap_or_default());
+
+        match events_res.next_page_token {
+            Some(next) => page_token = Some(next),
+            None => break,
+        }
+    }
+
+    Ok(events)
+}
+
+/// Time range sent by the frontend. Bounds are `YYYY-MM-DD` (local midnight) or RFC 3339.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeRange {
+    pub start: String,
+    pub end: String,
+}
+
+impl TimeRange {
+    pub fn bounds(&self) -> Result<(DateTime<Utc>, DateTime<Utc>), String> {
+        let start = parse_time_bound(&self.start)?;
+        let end = parse_time_bound(&self.end)?;
+        if end <= start {
+            return Err("Range end must be after its start".to_string());
+        }
+        Ok((start, end))
+    }
+}
+
+/// Parses a range bound given either as `YYYY-MM-DD` (local midnight) or RFC 3339.
+pub fn parse_time_bound(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return local_midnight(date);
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|_| format!("Invalid date or time: {}", value))
+}
+
+fn local_midnight(date: NaiveDate) -> Result<DateTime<Utc>, String> {
+    let midnight = date.and_hms_opt(0, 0, 0).ok_or("Invalid date")?;
+    Local.from_local_datetime(&midnight)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok_or_else(|| format!("Invalid local date: {}", date))
+}
+
+// Returns (original value, unix timestamp, all-day)
+fn event_time(time: &EventTime) -> Option<(String, i64, bool)> {
+    if let Some(date_time) = &time.date_time {
+        let parsed = DateTime::parse_from_rfc3339(date_time).ok()?;
+        return Some((date_time.clone(), parsed.timestamp(), false));
+    }
+    let date = time.date.as_ref()?;
+    let parsed = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    Some((date.clone(), local_midnight(parsed).ok()?.timestamp(), true))
+}
+
+/// Mirrors every selected calendar of the account for `[time_min, time_max)` into the `events` table.
+/// Events that disappeared remotely within the range are removed locally.
+pub async fn sync_events(
+    db: &Database,
+    endpoints: &GoogleEndpoints,
+    account_id: &str,
+    time_min: DateTime<Utc>,
+    time_max: DateTime<Utc>,
+) -> Result<usize, String> {
+    let calendars = fetch_calendars(db, endpoints, account_id).await?;
+    let mut count = 0;
+
+    for calendar in calendars.iter().filter(|c| c.selected.unwrap_or(true)) {
+        let g_events = fetch_events(db, endpoints, account_id, &calendar.id, time_min, time_max).await?;
+
+        let events: Vec<Event> = g_events
+            .into_iter()
+            .filter(|e| e.status.as_deref() != Some("cancelled"))
+            // Events pushed for tasks would show up twice next to the task itself
+            .filter(|e| e.task_id().is_none())
+            .filter_map(|e| {
+                let (start, start_ts, all_day) = event_time(&e.start)?;
+                let (end, end_ts, _) = event_time(&e.end)?;
+                Some(Event {
+                    id: e.id,
+                    provider: PROVIDER.to_string(),
+                    account_id: account_id.to_string(),
+                    calendar_id: calendar.id.clone(),
+                    title: e.summary.unwrap_or_else(|| "(No title)".to_string()),
+                    description: e.description,
+                    location: e.location,
+                    start,
+                    end,
+                    start_ts,
+                    end_ts,
+                    all_day,
+                    color: calendar.background_color.clone(),
+                    html_link: e.html_link,
+                })
+            })
+            .collect();
+
+        count += events.len();
+        db.replace_events(PROVIDER, account_id, &calendar.id, time_min.timestamp(), time_max.timestamp(), &events)
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(count)
+}
+
+fn task_event_url(endpoints: &GoogleEndpoints, calendar_id: &str, event_id: &str) -> Result<String, String> {
+    let mut url = url::Url::parse(&calendar_events_url(endpoints, calendar_id)?).map_err(|e| e.to_string())?;
+    url.path_segments_mut()
+        .map_err(|_| "Invalid calendar API URL".to_string())?
+        .push(event_id);
+    Ok(url.to_string())
+}
+
+// Length of the event for a task with a due time but no time block
+const DUE_EVENT_MINUTES: i64 = 30;
+
+fn timed_event_times(start: DateTime<FixedOffset>, minutes: i64, timezone: Option<&str>) -> (Value, Value) {
+    let end = start + Duration::minutes(minutes);
+    let mut start = json!({ "dateTime": start.to_rfc3339() });
+    let mut end = json!({ "dateTime": end.to_rfc3339() });
+    if let Some(timezone) = timezone {
+        start["timeZone"] = json!(timezone);
+        end["timeZone"] = json!(timezone);
+    }
+    (start, end)
+}
+
+// Event resource for a task, or None when the task should not be on the calendar
+fn task_event_body(task: &Task, link: &TaskEventLink) -> Result<Option<Value>, String> {
+    if task.completed {
+        return Ok(None);
+    }
+    let Some(due) = task.due()? else {
+        return Ok(None);
+    };
+    let timezone = task.timezone.as_deref();
+
+    let (start, end) = match (&link.start_time, link.duration_minutes, &due) {
+        (Some(start_time), Some(duration), _) => {
+            let time = NaiveTime::parse_from_str(start_time, "%H:%M")
+                .map_err(|_| format!("Invalid start time: {}", start_time))?;
+            if duration <= 0 {
+                return Err("Duration must be positive".to_string());
+            }
+            // The block is on the due date in the task's timezone, or the machine's when it has none
+            let local = due.date().and_time(time);
+            let start = match timezone {
+                Some(name) => {
+                    let tz = Tz::from_str(name).map_err(|_| format!("Unknown timezone: {}", name))?;
+                    tz.from_local_datetime(&local).earliest().map(|dt| dt.fixed_offset())
+                }
+                None => Local.from_local_datetime(&local).earliest().map(|dt| dt.fixed_offset()),
+            }
+            .ok_or_else(|| format!("Invalid local time: {}", local))?;
+            timed_event_times(start, duration, timezone)
+        }
+        (_, _, Due::At(at)) => timed_event_times(at.fixed_offset(), DUE_EVENT_MINUTES, timezone),
+        (_, _, Due::AllDay(date)) => {
+            let end = date.succ_opt().ok_or("Invalid due date")?;
+            (json!({ "date": date.to_string() }), json!({ "date": end.to_string() }))
+        }
+    };
+
+    Ok(Some(json!({
+        "summary": task.title,
+        "start": start,
+        "end": end,
+        "extendedProperties": { "private": { TASK_ID_PROPERTY: task.id } },
+    })))
+}
+
+// Updates the linked event, creating it if it does not exist (yet or anymore). Returns the event id.
+async fn upsert_task_event(db: &Database, endpoints: &GoogleEndpoints, link: &TaskEventLink, body: &Value) -> Result<String, String> {
+    if !link.event_id.is_empty() {
+        let url = task_event_url(endpoints, &link.calendar_id, &link.event_id)?;
+        let res = send_authorized(db, endpoints, &link.account_id, |client, token| {
+            client.patch(&url).bearer_auth(token).json(body)
+        }).await?;
+
+        match res.status() {
+            // Deleted on the calendar side, create it again below
+            StatusCode::NOT_FOUND | StatusCode::GONE => {}
+            status if status.is_success() => return Ok(link.event_id.clone()),
+            _ => return Err(format!("API Error: {:?}", res.text().await)),
+        }
+    }
+
+    let url = calendar_events_url(endpoints, &link.calendar_id)?;
+    let res = send_authorized(db, endpoints, &link.account_id, |client, token| {
+        client.post(&url).bearer_auth(token).json(body)
+    }).await?;
+
     if !res.status().is_success() {
         return Err(format!("API Error: {:?}", res.text().await));
     }
+    let event: GoogleEvent = res.json().await.map_err(|e| e.to_string())?;
+    Ok(event.id)
+}
+
+async fn delete_remote_task_event(db: &Database, endpoints: &GoogleEndpoints, link: &TaskEventLink) -> Result<(), String> {
+    let url = task_event_url(endpoints, &link.calendar_id, &link.event_id)?;
+    let res = send_authorized(db, endpoints, &link.account_id, |client, token| {
+        client.delete(&url).bearer_auth(token)
+    }).await?;
 
-    let task_res: TaskResponse = res.json().await.map_err(|e| e.to_string())?;
-    Ok(task_res.items.unwrap_or_default())
+    match res.status() {
+        StatusCode::NOT_FOUND | StatusCode::GONE => Ok(()),
+        status if status.is_success() => Ok(()),
+        _ => Err(format!("API Error: {:?}", res.text().await)),
+    }
+}
+
+/// Creates (or moves) the calendar event for a task. The task must have a due date and be open.
+pub async fn schedule_task(
+    db: &Database,
+    endpoints: &GoogleEndpoints,
+    task_id: &str,
+    account_id: &str,
+    calendar_id: &str,
+    time_block: Option<TimeBlock>,
+) -> Result<TaskEventLink, String> {
+    let task = db.get_task_by_id(task_id).map_err(|e| e.to_string())?.ok_or("Task not found")?;
+
+    let previous = db.get_task_event(task_id).map_err(|e| e.to_string())?;
+    let mut link = TaskEventLink {
+        task_id: task_id.to_string(),
+        provider: PROVIDER.to_string(),
+        account_id: account_id.to_string(),
+        calendar_id: calendar_id.to_string(),
+        event_id: String::new(),
+        start_time: time_block.as_ref().map(|b| b.start_time.clone()),
+        duration_minutes: time_block.as_ref().map(|b| b.duration_minutes),
+    };
+    // Same calendar: update the existing event in place
+    if let Some(previous) = &previous
+        && previous.account_id == link.account_id
+        && previous.calendar_id == link.calendar_id
+    {
+        link.event_id = previous.event_id.clone();
+    }
+
+    let body = task_event_body(&task, &link)?.ok_or("Only open tasks with a due date can be scheduled")?;
+    link.event_id = upsert_task_event(db, endpoints, &link, &body).await?;
+
+    if let Some(previous) = previous
+        && previous.event_id != link.event_id
+    {
+        delete_remote_task_event(db, endpoints, &previous).await?;
+    }
+    db.set_task_event(&link).map_err(|e| e.to_string())?;
+    Ok(link)
+}
+
+/// Removes the task's calendar event, if it has one.
+pub async fn unschedule_task(db: &Database, endpoints: &GoogleEndpoints, task_id: &str) -> Result<(), String> {
+    if let Some(link) = db.get_task_event(task_id).map_err(|e| e.to_string())? {
+        delete_remote_task_event(db, endpoints, &link).await?;
+        db.delete_task_event(task_id).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Brings the calendar events of `task_ids` in line with the stored tasks after a write, or of
+/// every task with an event when `task_ids` is empty. Events of deleted tasks are removed.
+/// Every event is tried; the errors are reported together.
+pub async fn sync_task_events(db: &Database, endpoints: &GoogleEndpoints, task_ids: &[String]) -> Result<(), String> {
+    let linked = db.get_task_event_ids().map_err(|e| e.to_string())?;
+    let mut errors = Vec::new();
+    for task_id in linked.iter().filter(|id| task_ids.is_empty() || task_ids.contains(id)) {
+        let result = match db.get_task_by_id(task_id).map_err(|e| e.to_string()) {
+            Ok(Some(task)) => sync_task_event(db, endpoints, &task).await,
+            Ok(None) => unschedule_task(db, endpoints, task_id).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            errors.push(format!("{}: {}", task_id, e));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+/// Brings the task's calendar event in line with the task after a local change.
+/// The event is removed once the task is completed or loses its due date.
+pub async fn sync_task_event(db: &Database, endpoints: &GoogleEndpoints, task: &Task) -> Result<(), String> {
+    let Some(mut link) = db.get_task_event(&task.id).map_err(|e| e.to_string())? else {
+        return Ok(());
+    };
+
+    match task_event_body(task, &link)? {
+        Some(body) => {
+            let event_id = upsert_task_event(db, endpoints, &link, &body).await?;
+            if event_id != link.event_id {
+                link.event_id = event_id;
+                db.set_task_event(&link).map_err(|e| e.to_string())?;
+            }
+        }
+        None => {
+            delete_remote_task_event(db, endpoints, &link).await?;
+            db.delete_task_event(&task.id).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,448 @@
+use crate::db::{local_timezone, Database, Due, Task, DEFAULT_CATEGORY};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+const PRODID: &str = "-//Tasker//Tasker//EN";
+// RFC 5545 3.1: lines longer than 75 octets are folded
+const MAX_LINE_OCTETS: usize = 75;
+// Length of the event written for a task due at a time, same as Google task events
+const DEADLINE_EVENT_MINUTES: i64 = 30;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IcsImportSummary {
+    pub added: usize,
+    pub updated: usize,
+    /// Components without a UID, cancelled events and overrides of single occurrences
+    pub skipped: usize,
+}
+
+/// Tasks read from an iCalendar file, deduplicated by UID (the last component wins).
+#[derive(Debug, Default)]
+pub struct ParsedCalendar {
+    pub tasks: Vec<Task>,
+    pub skipped: usize,
+}
+
+// One content line, `NAME;PARAM=VALUE:value`, with the name and parameter names uppercased
+#[derive(Debug)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+}
+
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+// Splits a TEXT list (e.g. CATEGORIES) on commas that are not escaped
+fn split_text_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push(c);
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            ',' => items.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    items.push(current);
+    items.iter().map(|item| unescape_text(item.trim())).filter(|item| !item.is_empty()).collect()
+}
+
+fn fold_line(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            // The leading space counts towards the next line
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+fn priority_value(priority: &str) -> Option<u8> {
+    match priority.to_ascii_lowercase().as_str() {
+        "high" => Some(1),
+        "medium" => Some(5),
+        "low" => Some(9),
+        _ => None,
+    }
+}
+
+// RFC 5545 3.8.1.9: 1-4 high, 5 medium, 6-9 low, 0 undefined
+fn priority_name(value: &str) -> &'static str {
+    match value.trim().parse::<u8>() {
+        Ok(1..=4) => "High",
+        Ok(6..=9) => "Low",
+        _ => "Medium",
+    }
+}
+
+// `NAME;VALUE=DATE:...` for all-day values, UTC (`Z`) or `TZID` date-times otherwise
+fn date_time_property(name: &str, due: &Due) -> String {
+    match due {
+        Due::AllDay(date) => format!("{};VALUE=DATE:{}", name, format_date(*date)),
+        Due::At(at) if at.timezone() == Tz::UTC => format!("{}:{}", name, at.format("%Y%m%dT%H%M%SZ")),
+        Due::At(at) => format!("{};TZID={}:{}", name, at.timezone().name(), at.format("%Y%m%dT%H%M%S")),
+    }
+}
+
+fn todo_lines(task: &Task, dtstamp: &str) -> Vec<String> {
+    let mut lines = vec![
+        "BEGIN:VTODO".to_string(),
+        format!("UID:{}", task.id),
+        format!("DTSTAMP:{}", dtstamp),
+        format!("SUMMARY:{}", escape_text(&task.title)),
+    ];
+    if let Some(description) = task.description.as_deref().filter(|d| !d.is_empty()) {
+        lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+    }
+    if let Some(start) = task.start_date {
+        lines.push(format!("DTSTART;VALUE=DATE:{}", format_date(start)));
+    }
+    // Invalid rows can't be written anymore, but don't fail a whole export on an old one
+    if let Ok(Some(due)) = task.due() {
+        lines.push(date_time_property("DUE", &due));
+    }
+    if let Some(priority) = priority_value(&task.priority) {
+        lines.push(format!("PRIORITY:{}", priority));
+    }
+    lines.push(format!("STATUS:{}", if task.completed { "COMPLETED" } else { "NEEDS-ACTION" }));
+    if let Some(completed_at) = task.completed_at {
+        lines.push(format!("COMPLETED:{}", format_dtstamp(completed_at)));
+    }
+    if let Some(created_at) = task.created_at {
+        lines.push(format!("CREATED:{}", format_dtstamp(created_at)));
+    }
+    if let Some(updated_at) = task.updated_at {
+        lines.push(format!("LAST-MODIFIED:{}", format_dtstamp(updated_at)));
+    }
+    // The category comes first, tags follow it
+    let categories: Vec<String> =
+        std::iter::once(&task.category).chain(&task.tags).filter(|c| !c.is_empty()).map(|c| escape_text(c)).collect();
+    if !categories.is_empty() {
+        lines.push(format!("CATEGORIES:{}", categories.join(",")));
+    }
+    if let Some(rule) = &task.recurrence {
+        lines.push(format!("RRULE:{}", rule));
+    }
+    if let Some(parent_id) = &task.parent_id {
+        lines.push(format!("RELATED-TO;RELTYPE=PARENT:{}", parent_id));
+    }
+    lines.push("END:VTODO".to_string());
+    lines
+}
+
+// A deadline shown as an event: the due day, or a block starting at the due time
+fn event_lines(task: &Task, dtstamp: &str) -> Option<Vec<String>> {
+    let due = task.due().ok().flatten()?;
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", task.id),
+        format!("DTSTAMP:{}", dtstamp),
+        format!("SUMMARY:{}", escape_text(&task.title)),
+    ];
+    if let Some(description) = task.description.as_deref().filter(|d| !d.is_empty()) {
+        lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+    }
+    lines.push(date_time_property("DTSTART", &due));
+    match &due {
+        Due::AllDay(date) => lines.push(format!("DTEND;VALUE=DATE:{}", format_date(date.succ_opt()?))),
+        Due::At(_) => lines.push(format!("DURATION:PT{}M", DEADLINE_EVENT_MINUTES)),
+    }
+    // Deadlines don't make the user busy
+    lines.push("TRANSP:TRANSPARENT".to_string());
+    if !task.category.is_empty() {
+        lines.push(format!("CATEGORIES:{}", escape_text(&task.category)));
+    }
+    if let Some(rule) = &task.recurrence {
+        lines.push(format!("RRULE:{}", rule));
+    }
+    lines.push("END:VEVENT".to_string());
+    Some(lines)
+}
+
+fn write_calendar(components: impl Iterator<Item = Vec<String>>) -> String {
+    let mut out = String::new();
+    for line in ["BEGIN:VCALENDAR", "VERSION:2.0", &format!("PRODID:{}", PRODID)] {
+        fold_line(line, &mut out);
+    }
+    for line in components.flatten() {
+        fold_line(&line, &mut out);
+    }
+    fold_line("END:VCALENDAR", &mut out);
+    out
+}
+
+fn format_dtstamp(dtstamp: DateTime<Utc>) -> String {
+    dtstamp.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Serializes tasks into a VCALENDAR with one VTODO per task. Uses CRLF line endings and folding.
+pub fn export_tasks(tasks: &[Task], dtstamp: DateTime<Utc>) -> String {
+    let dtstamp = format_dtstamp(dtstamp);
+    write_calendar(tasks.iter().map(|task| todo_lines(task, &dtstamp)))
+}
+
+/// Serializes tasks with a due date as VEVENTs, for calendar apps that don't show to-dos.
+/// Tasks without a due date are left out.
+pub fn export_deadline_events(tasks: &[Task], dtstamp: DateTime<Utc>) -> String {
+    let dtstamp = format_dtstamp(dtstamp);
+    write_calendar(tasks.iter().filter_map(|task| event_lines(task, &dtstamp)))
+}
+
+// Joins folded lines back together
+fn unfold(input: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in input.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if let Some(continuation) = line.strip_prefix([' ', '\t'])
+            && let Some(last) = lines.last_mut()
+        {
+            last.push_str(continuation);
+        } else if !line.is_empty() {
+            lines.push(line.to_string());
+        }
+    }
+    lines
+}
+
+fn parse_property(line: &str) -> Option<Property> {
+    // The value starts after the first colon that is not inside a quoted parameter value
+    let mut in_quotes = false;
+    let split = line.char_indices().find(|&(_, c)| {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        }
+        c == ':' && !in_quotes
+    })?;
+    let (head, value) = (&line[..split.0], &line[split.0 + 1..]);
+
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    in_quotes = false;
+    for c in head.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => parts.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    parts.push(current);
+
+    let mut parts = parts.into_iter();
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    if name.is_empty() {
+        return None;
+    }
+    let params = parts
+        .filter_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            Some((key.trim().to_ascii_uppercase(), value.to_string()))
+        })
+        .collect();
+    Some(Property { name, params, value: value.to_string() })
+}
+
+// Date or date-time value as (date, optional (time, timezone name))
+fn parse_date_value(prop: &Property) -> Option<(NaiveDate, Option<(chrono::NaiveTime, String)>)> {
+    let value = prop.value.trim();
+    if prop.param("VALUE").is_some_and(|v| v.eq_ignore_ascii_case("DATE")) || value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d").ok().map(|date| (date, None));
+    }
+
+    let (local, utc) = match value.strip_suffix('Z') {
+        Some(local) => (local, true),
+        None => (value, false),
+    };
+    let at = NaiveDateTime::parse_from_str(local, "%Y%m%dT%H%M%S").ok()?;
+    let timezone = if utc {
+        "UTC".to_string()
+    } else {
+        // Some producers prefix TZID with "/", unknown ids (e.g. Windows names) are treated as floating
+        prop.param("TZID")
+            .map(|tzid| tzid.trim_start_matches('/'))
+            .filter(|tzid| Tz::from_str(tzid).is_ok())
+            .map(str::to_string)
+            // Floating times are read in the machine's timezone
+            .unwrap_or_else(local_timezone)
+    };
+    Some((at.date(), Some((at.time(), timezone))))
+}
+
+fn component_to_task(kind: &str, props: &[Property], default_category: &str) -> Option<Task> {
+    let get = |name: &str| props.iter().find(|p| p.name == name);
+
+    let id = get("UID").map(|p| p.value.trim().to_string()).filter(|id| !id.is_empty())?;
+    // Overrides of single occurrences share the UID of the recurring component
+    if get("RECURRENCE-ID").is_some() {
+        return None;
+    }
+    let status = get("STATUS").map(|p| p.value.trim().to_ascii_uppercase());
+    if kind == "VEVENT" && status.as_deref() == Some("CANCELLED") {
+        return None;
+    }
+
+    let categories = get("CATEGORIES").map(|p| split_text_list(&p.value)).unwrap_or_default();
+    let mut task = Task {
+        id,
+        title: get("SUMMARY").map(|p| unescape_text(&p.value)).unwrap_or_else(|| "(No title)".to_string()),
+        description: get("DESCRIPTION").map(|p| unescape_text(&p.value)).filter(|d| !d.is_empty()),
+        completed: kind == "VTODO" && (status.as_deref() == Some("COMPLETED") || get("COMPLETED").is_some()),
+        priority: priority_name(get("PRIORITY").map(|p| p.value.as_str()).unwrap_or("0")).to_string(),
+        category: categories.first().cloned().unwrap_or_else(|| default_category.to_string()),
+        recurrence: get("RRULE").map(|p| p.value.trim().to_string()),
+        parent_id: props
+            .iter()
+            .find(|p| p.name == "RELATED-TO" && p.param("RELTYPE").is_none_or(|t| t.eq_ignore_ascii_case("PARENT")))
+            .map(|p| p.value.trim().to_string()),
+        tags: categories
+            .iter()
+            .skip(1)
+            .map(|c| c.trim().trim_start_matches('#').replace(char::is_whitespace, "-"))
+            .filter(|c| !c.is_empty())
+            .collect(),
+        ..Default::default()
+    };
+
+    // An event is due when it starts, a to-do has its own DUE
+    let (due, start) = match kind {
+        "VEVENT" => (get("DTSTART"), None),
+        _ => (get("DUE"), get("DTSTART")),
+    };
+    if let Some((date, time)) = due.and_then(parse_date_value) {
+        task.due_date = Some(date);
+        if let Some((time, timezone)) = time {
+            task.due_time = Some(time);
+            task.timezone = Some(timezone);
+        }
+    }
+    task.start_date = start
+        .and_then(parse_date_value)
+        .map(|(date, _)| date)
+        .filter(|start| task.due_date.is_none_or(|due| *start <= due));
+    Some(task)
+}
+
+/// Parses VTODO and VEVENT components into tasks. Other components (VTIMEZONE, VALARM...) are ignored.
+pub fn parse_tasks(input: &str) -> Result<ParsedCalendar, String> {
+    parse_tasks_with_category(input, DEFAULT_CATEGORY)
+}
+
+/// Like `parse_tasks`, with the category given to components without CATEGORIES.
+pub fn parse_tasks_with_category(input: &str, default_category: &str) -> Result<ParsedCalendar, String> {
+    let mut parsed = ParsedCalendar::default();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut stack: Vec<String> = Vec::new();
+    let mut props: Vec<Property> = Vec::new();
+    let mut saw_calendar = false;
+
+    for line in unfold(input) {
+        let Some(prop) = parse_property(&line) else {
+            continue;
+        };
+        match prop.name.as_str() {
+            "BEGIN" => {
+                let kind = prop.value.trim().to_ascii_uppercase();
+                saw_calendar |= kind == "VCALENDAR";
+                if kind == "VTODO" || kind == "VEVENT" {
+                    props.clear();
+                }
+                stack.push(kind);
+            }
+            "END" => {
+                let kind = stack.pop().ok_or("Unexpected END without BEGIN")?;
+                if kind != prop.value.trim().to_ascii_uppercase() {
+                    return Err(format!("Mismatched END:{} for BEGIN:{}", prop.value.trim(), kind));
+                }
+                if kind != "VTODO" && kind != "VEVENT" {
+                    continue;
+                }
+                match component_to_task(&kind, &props, default_category) {
+                    Some(task) => match index.get(&task.id) {
+                        Some(&i) => parsed.tasks[i] = task,
+                        None => {
+                            index.insert(task.id.clone(), parsed.tasks.len());
+                            parsed.tasks.push(task);
+                        }
+                    },
+                    None => parsed.skipped += 1,
+                }
+            }
+            // Only direct properties of the component, not of nested VALARMs
+            _ if matches!(stack.last().map(String::as_str), Some("VTODO" | "VEVENT")) => props.push(prop),
+            _ => {}
+        }
+    }
+
+    if !saw_calendar {
+        return Err("Not an iCalendar file (missing BEGIN:VCALENDAR)".to_string());
+    }
+    if !stack.is_empty() {
+        return Err(format!("Unterminated component: {}", stack.join(" > ")));
+    }
+    Ok(parsed)
+}
+
+/// Imports an iCalendar file, updating tasks whose UID matches an existing task id, in one transaction.
+/// Nothing is written unless every task in the file is valid.
+pub fn import_tasks(db: &Database, input: &str) -> Result<IcsImportSummary, String> {
+    let parsed = parse_tasks(input)?;
+    for task in &parsed.tasks {
+        task.validate().map_err(|e| format!("{}: {}", task.id, e))?;
+    }
+
+    let (added, updated, _) = db.import_data(&parsed.tasks, &[], false).map_err(|e| e.to_string())?;
+    Ok(IcsImportSummary { added, updated, skipped: parsed.skipped })
+}
@@ -1,12 +1,33 @@
-mod db;
-mod google;
+pub mod backup;
+pub mod bulk;
+pub mod caldav;
+pub mod changes;
+pub mod data;
+pub mod db;
+pub mod dependencies;
+pub mod feed;
+pub mod google;
+pub mod ical;
+pub mod plaintext;
+pub mod query;
+pub mod quickadd;
+pub mod reminders;
+pub mod secrets;
+pub mod smartlists;
 
 use db::{Database, Task};
+use google::GoogleEndpoints;
+use std::collections::HashMap;
 use std::sync::Mutex;
-use tauri::{Manager, State};
+use tauri::{Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
 
 struct AppState {
     db: Mutex<Option<Database>>,
+    google: GoogleEndpoints,
+    feed: Mutex<Option<feed::FeedServer>>,
+    // Delivered reminders not yet snoozed or dismissed, for a window that wasn't listening yet
+    reminders: Mutex<Vec<reminders::DueReminder>>,
 }
 
 #[tauri::command]
@@ -37,6 +58,15 @@ fn update_task(task: Task, state: State<AppState>) -> Result<(), String> {
     db.update_task(task).map_err(|e| e.to_string())
 }
 
+/// Changes only the fields in `patch`. Pass the `revision` the edit started from to get a
+/// "Conflict: ..." error instead of overwriting a change made elsewhere in the meantime.
+#[tauri::command]
+fn patch_task(id: String, patch: db::TaskPatch, revision: Option<i64>, state: State<AppState>) -> Result<Task, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    db.patch_task(&id, &patch, revision).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn delete_task(id: String, state: State<AppState>) -> Result<(), String> {
     let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
@@ -51,20 +81,338 @@ fn update_task_order(ordered_ids: Vec<String>, state: State<AppState>) -> Result
     db.update_task_order(ordered_ids).map_err(|e| e.to_string())
 }
 
+// Bulk Commands: one transaction, one undo entry and one change event per call
+
+#[tauri::command]
+fn bulk_update_tasks(ids: Vec<String>, patch: db::TaskPatch, state: State<AppState>) -> Result<Vec<Task>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    bulk::update(db, &ids, &patch)
+}
+
+#[tauri::command]
+fn bulk_delete(ids: Vec<String>, state: State<AppState>) -> Result<usize, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    bulk::delete(db, &ids).map(|tasks| tasks.len())
+}
+
+#[tauri::command]
+fn bulk_move_to_list(ids: Vec<String>, category: String, state: State<AppState>) -> Result<Vec<Task>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    bulk::move_to_list(db, &ids, &category)
+}
+
+#[tauri::command]
+fn bulk_tag(ids: Vec<String>, add: Vec<String>, remove: Vec<String>, state: State<AppState>) -> Result<Vec<Task>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    bulk::tag(db, &ids, &add, &remove)
+}
+
+#[tauri::command]
+fn get_last_undo(state: State<AppState>) -> Result<Option<db::UndoEntry>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    db.last_undo().map_err(|e| e.to_string())
+}
+
+/// Reverts the newest bulk edit, returning what was undone (None when there is nothing to undo).
+#[tauri::command]
+fn undo(state: State<AppState>) -> Result<Option<db::UndoEntry>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    db.undo().map_err(|e| e.to_string())
+}
+
+/// Pre-fills a task from a quick-add line such as "Call mom tomorrow 3pm #family".
+#[tauri::command]
+fn parse_quick_add(text: String) -> quickadd::QuickAdd {
+    quickadd::parse(&text, chrono::Local::now().naive_local(), &db::local_timezone())
+}
+
+#[tauri::command]
+fn export_ics(state: State<AppState>) -> Result<String, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    let tasks = db.get_tasks().map_err(|e| e.to_string())?;
+    Ok(ical::export_tasks(&tasks, chrono::Utc::now()))
+}
+
+#[tauri::command]
+fn import_ics(content: String, state: State<AppState>) -> Result<ical::IcsImportSummary, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    ical::import_tasks(db, &content)
+}
+
+#[tauri::command]
+fn export_data(format: data::DataFormat, state: State<AppState>) -> Result<String, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    data::export(db, format)
+}
+
+#[tauri::command]
+fn import_data(format: data::DataFormat, content: String, mode: data::ImportMode, state: State<AppState>) -> Result<data::DataImportSummary, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    data::import(db, &content, format, mode)
+}
+
+#[tauri::command]
+fn export_plaintext(format: plaintext::PlainTextFormat, state: State<AppState>) -> Result<String, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    let tasks = db.get_tasks().map_err(|e| e.to_string())?;
+    Ok(plaintext::export(&tasks, format))
+}
+
+/// Dry run: parses without touching the database so the UI can show what would be imported.
+#[tauri::command]
+fn preview_plaintext_import(format: plaintext::PlainTextFormat, content: String) -> plaintext::PlainTextImport {
+    plaintext::parse(&content, format)
+}
+
+#[tauri::command]
+fn import_plaintext(format: plaintext::PlainTextFormat, content: String, state: State<AppState>) -> Result<plaintext::PlainTextImport, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    plaintext::import(db, &content, format)
+}
+
+// Backup Commands
+
+#[tauri::command]
+fn list_backups(state: State<AppState>) -> Result<Vec<backup::BackupInfo>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    backup::list(db)
+}
+
+#[tauri::command]
+fn create_backup(state: State<AppState>) -> Result<backup::BackupInfo, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    let backup = backup::create(db, chrono::Utc::now())?;
+    backup::prune(db, backup::DEFAULT_RETENTION)?;
+    Ok(backup)
+}
+
+/// Returns the backup of the data that was replaced.
+#[tauri::command]
+fn restore_backup(name: String, state: State<AppState>) -> Result<backup::BackupInfo, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    backup::restore(db, &name, chrono::Utc::now())
+}
+
+// Reminder Commands
+
+#[tauri::command]
+fn get_reminders(task_id: String, state: State<AppState>) -> Result<Vec<db::Reminder>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    db.get_reminders(&task_id).map_err(|e| e.to_string())
+}
+
+/// Either `remind_at` (unix seconds) or `offset_minutes` before the task is due.
+#[tauri::command]
+fn add_reminder(task_id: String, remind_at: Option<i64>, offset_minutes: Option<i64>, state: State<AppState>) -> Result<db::Reminder, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    reminders::add(db, &task_id, remind_at, offset_minutes)
+}
+
+#[tauri::command]
+fn delete_reminder(id: String, state: State<AppState>) -> Result<(), String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    db.delete_reminder(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_due_reminders(state: State<AppState>) -> Result<Vec<reminders::DueReminder>, String> {
+    Ok(state.reminders.lock().map_err(|_| "Failed to lock mutex")?.clone())
+}
+
+#[tauri::command]
+fn snooze_reminder(id: String, minutes: Option<i64>, state: State<AppState>) -> Result<db::Reminder, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    let minutes = minutes.unwrap_or(reminders::DEFAULT_SNOOZE_MINUTES);
+    let reminder = reminders::snooze(db, &id, minutes, chrono::Utc::now().timestamp())?;
+    state.reminders.lock().map_err(|_| "Failed to lock mutex")?.retain(|due| due.reminder.id != id);
+    Ok(reminder)
+}
+
+#[tauri::command]
+fn dismiss_reminder(id: String, state: State<AppState>) -> Result<db::Reminder, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    let reminder = reminders::dismiss(db, &id)?;
+    state.reminders.lock().map_err(|_| "Failed to lock mutex")?.retain(|due| due.reminder.id != id);
+    Ok(reminder)
+}
+
+// Search Commands
+
+/// Tasks matching a filter query, see `query::parse` for the syntax.
+#[tauri::command]
+fn search_tasks(query: String, state: State<AppState>) -> Result<Vec<Task>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    query::search(db, &query, query::local_now())
+}
+
+/// The first problem in a query with its position, None when it parses.
+#[tauri::command]
+fn check_query(query: String) -> Option<query::QueryError> {
+    query::parse(&query).err()
+}
+
+// Smart List Commands
+
+/// Smart lists with live counts, for the sidebar.
+#[tauri::command]
+fn get_smart_lists(state: State<AppState>) -> Result<Vec<smartlists::SmartListCount>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    smartlists::counts(db, query::local_now())
+}
+
+#[tauri::command]
+fn get_smart_list_tasks(id: String, state: State<AppState>) -> Result<Vec<Task>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    smartlists::tasks(db, &id, query::local_now())
+}
+
+#[tauri::command]
+fn create_smart_list(name: String, filter: smartlists::SmartFilter, sort: Option<smartlists::SmartSort>, state: State<AppState>) -> Result<db::SmartList, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    smartlists::create(db, &name, filter, sort.unwrap_or_default())
+}
+
+#[tauri::command]
+fn update_smart_list(list: db::SmartList, state: State<AppState>) -> Result<db::SmartList, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    smartlists::update(db, list)
+}
+
+#[tauri::command]
+fn delete_smart_list(id: String, state: State<AppState>) -> Result<(), String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    db.delete_smart_list(&id).map_err(|e| e.to_string())
+}
+
+// Dependency Commands
+
+#[tauri::command]
+fn get_blockers(task_id: String, state: State<AppState>) -> Result<Vec<Task>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    db.get_blockers(&task_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_dependents(task_id: String, state: State<AppState>) -> Result<Vec<Task>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    db.get_dependents(&task_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn add_dependency(task_id: String, blocked_by: String, state: State<AppState>) -> Result<(), String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    dependencies::link(db, &task_id, &blocked_by)
+}
+
+#[tauri::command]
+fn remove_dependency(task_id: String, blocked_by: String, state: State<AppState>) -> Result<(), String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    dependencies::unlink(db, &task_id, &blocked_by)
+}
+
+// Secrets Commands
+
+#[tauri::command]
+fn get_secrets_status(state: State<AppState>) -> Result<secrets::SecretsStatus, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    secrets::status(db)
+}
+
+#[tauri::command]
+fn unlock_secrets(passphrase: String, app: tauri::AppHandle, state: State<AppState>) -> Result<(), String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    secrets::unlock(db, &passphrase)?;
+    migrate_google_tokens(&app, db.clone());
+    Ok(())
+}
+
+// Moves the single-account version's Google tokens in the background, at startup or, with a
+// passphrase, once unlocked. The settings page reloads its accounts on "google-accounts-changed".
+fn migrate_google_tokens(app: &tauri::AppHandle, db: Database) {
+    if matches!(db.secrets_state(), secrets::SecretsState::Locked) {
+        return;
+    }
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match google::migrate_legacy_tokens(&db, &handle.state::<AppState>().google).await {
+            Ok(Some(_)) => {
+                let _ = handle.emit("google-accounts-changed", ());
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to migrate Google sign-in: {}", e),
+        }
+    });
+}
+
+#[tauri::command]
+fn set_secrets_passphrase(passphrase: Option<String>, state: State<AppState>) -> Result<(), String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    secrets::change_backend(db, passphrase.as_deref())
+}
+
 // Google Tasks Commands
 
 #[tauri::command]
-async fn get_google_user(state: State<'_, AppState>) -> Result<google::GoogleUser, String> {
+fn get_google_accounts(state: State<AppState>) -> Result<Vec<db::Account>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    google::get_accounts(db)
+}
+
+#[tauri::command]
+async fn get_google_user(account_id: String, state: State<'_, AppState>) -> Result<google::GoogleUser, String> {
     let db = {
         let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
         db_guard.as_ref().ok_or("Database not initialized")?.clone()
     };
-    google::get_user_profile(&db).await
+    google::get_user_profile(&db, &state.google, &account_id).await
 }
 
 #[tauri::command]
-fn get_google_auth_url() -> String {
-    google::get_auth_url()
+fn get_google_auth_url(state: State<AppState>) -> Result<String, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    google::get_auth_url(db, &state.google)
+}
+
+#[tauri::command]
+fn set_google_client_credentials(credentials: google::GoogleCredentials, state: State<AppState>) -> Result<(), String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    google::save_credentials(db, &credentials)
 }
 
 #[tauri::command]
@@ -73,7 +421,7 @@ async fn finish_google_auth(code: String, state: State<'_, AppState>) -> Result<
         let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
         db_guard.as_ref().ok_or("Database not initialized")?.clone()
     };
-    google::exchange_code(&code, &db).await
+    google::exchange_code(&code, &db, &state.google).await
 }
 
 #[tauri::command]
@@ -86,52 +434,192 @@ async fn complete_google_auth(state: State<'_, AppState>) -> Result<google::Goog
         let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
         db_guard.as_ref().ok_or("Database not initialized")?.clone()
     };
-    google::exchange_code(&code, &db).await
+    google::exchange_code(&code, &db, &state.google).await
 }
 
 #[tauri::command]
-async fn get_google_task_lists(state: State<'_, AppState>) -> Result<Vec<google::TaskList>, String> {
+async fn get_google_task_lists(account_id: String, state: State<'_, AppState>) -> Result<Vec<google::TaskList>, String> {
     let db = {
         let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
         db_guard.as_ref().ok_or("Database not initialized")?.clone()
     };
-    google::fetch_task_lists(&db).await
+    google::fetch_task_lists(&db, &state.google, &account_id).await
 }
 
 #[tauri::command]
-async fn import_google_tasks(list_id: String, state: State<'_, AppState>) -> Result<usize, String> {
+async fn import_google_tasks(account_id: String, list_id: String, state: State<'_, AppState>) -> Result<usize, String> {
     let db = {
         let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
         db_guard.as_ref().ok_or("Database not initialized")?.clone()
     };
-    
-    let g_tasks = google::fetch_tasks(&db, &list_id).await?;
-    let mut count = 0;
-    
-    for g_task in g_tasks {
-        // Map Google Task to local Task
-        let task = Task {
-            id: g_task.id,
-            title: g_task.title,
-            completed: g_task.status == "completed",
-            priority: "medium".to_string(), // Default, Google doesn't have simple priority like high/low easily accessible without parsing notes or something
-            category: "Google Tasks".to_string(), // Or use list title if passed
-            due_date: g_task.due.map(|d| d.chars().take(10).collect()), // Simple truncate to YYYY-MM-DD
-            position: 0, // Default position, will be sorted to top/bottom depending on logic
-        };
-        
-        // Upsert
-        if db.get_task_by_id(&task.id).map_err(|e| e.to_string())?.is_some() {
-            db.update_task(task).map_err(|e| e.to_string())?;
-        } else {
-            db.add_task(task).map_err(|e| e.to_string())?;
-        }
-        count += 1;
+    google::import_tasks(&db, &state.google, &account_id, &list_id).await
+}
+
+#[tauri::command]
+async fn import_all_google_tasks(account_id: String, state: State<'_, AppState>) -> Result<HashMap<String, usize>, String> {
+    let db = {
+        let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+        db_guard.as_ref().ok_or("Database not initialized")?.clone()
+    };
+    google::import_all_tasks(&db, &state.google, &account_id).await
+}
+
+#[tauri::command]
+fn get_google_list_categories(account_id: String, state: State<AppState>) -> Result<HashMap<String, String>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    google::get_list_categories(db, &account_id)
+}
+
+#[tauri::command]
+fn set_google_list_category(account_id: String, list_id: String, category: String, state: State<AppState>) -> Result<(), String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    google::set_list_category(db, &account_id, &list_id, &category)
+}
+
+#[tauri::command]
+async fn disconnect_google(account_id: String, imported_tasks: google::ImportedTaskAction, state: State<'_, AppState>) -> Result<google::DisconnectSummary, String> {
+    let db = {
+        let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+        db_guard.as_ref().ok_or("Database not initialized")?.clone()
+    };
+    google::disconnect(&db, &state.google, &account_id, imported_tasks).await
+}
+
+#[tauri::command]
+async fn sync_google_calendar(account_id: String, range: google::TimeRange, state: State<'_, AppState>) -> Result<usize, String> {
+    let db = {
+        let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+        db_guard.as_ref().ok_or("Database not initialized")?.clone()
+    };
+    let (start, end) = range.bounds()?;
+    google::sync_events(&db, &state.google, &account_id, start, end).await
+}
+
+#[tauri::command]
+fn get_events(range: google::TimeRange, state: State<AppState>) -> Result<Vec<db::Event>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    let (start, end) = range.bounds()?;
+    db.get_events(start.timestamp(), end.timestamp()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_task_event(task_id: String, state: State<AppState>) -> Result<Option<db::TaskEventLink>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    db.get_task_event(&task_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn schedule_task_on_google_calendar(
+    task_id: String,
+    account_id: String,
+    calendar_id: Option<String>,
+    time_block: Option<google::TimeBlock>,
+    state: State<'_, AppState>,
+) -> Result<db::TaskEventLink, String> {
+    let db = {
+        let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+        db_guard.as_ref().ok_or("Database not initialized")?.clone()
+    };
+    let calendar_id = calendar_id.unwrap_or_else(|| "primary".to_string());
+    google::schedule_task(&db, &state.google, &task_id, &account_id, &calendar_id, time_block).await
+}
+
+#[tauri::command]
+async fn unschedule_task(task_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let db = {
+        let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+        db_guard.as_ref().ok_or("Database not initialized")?.clone()
+    };
+    google::unschedule_task(&db, &state.google, &task_id).await
+}
+
+#[tauri::command]
+async fn connect_caldav(server_url: String, username: String, password: String, state: State<'_, AppState>) -> Result<db::Account, String> {
+    let db = {
+        let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+        db_guard.as_ref().ok_or("Database not initialized")?.clone()
+    };
+    caldav::connect(&db, &server_url, &username, &password).await
+}
+
+#[tauri::command]
+fn get_caldav_accounts(state: State<AppState>) -> Result<Vec<db::Account>, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    caldav::get_accounts(db)
+}
+
+#[tauri::command]
+async fn get_caldav_collections(account_id: String, state: State<'_, AppState>) -> Result<Vec<caldav::Collection>, String> {
+    let db = {
+        let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+        db_guard.as_ref().ok_or("Database not initialized")?.clone()
+    };
+    caldav::fetch_collections(&db, &account_id).await
+}
+
+#[tauri::command]
+async fn sync_caldav(account_id: String, state: State<'_, AppState>) -> Result<caldav::SyncSummary, String> {
+    let db = {
+        let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+        db_guard.as_ref().ok_or("Database not initialized")?.clone()
+    };
+    caldav::sync(&db, &account_id).await
+}
+
+#[tauri::command]
+fn add_task_to_caldav(account_id: String, collection: String, task_id: String, state: State<AppState>) -> Result<(), String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    caldav::add_task(db, &account_id, &collection, &task_id)
+}
+
+#[tauri::command]
+fn disconnect_caldav(account_id: String, imported_tasks: google::ImportedTaskAction, state: State<AppState>) -> Result<google::DisconnectSummary, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    caldav::disconnect(db, &account_id, imported_tasks)
+}
+
+#[tauri::command]
+fn get_ics_feed_status(state: State<AppState>) -> Result<feed::FeedStatus, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    let server = state.feed.lock().map_err(|_| "Failed to lock mutex")?;
+    feed::status(db, server.as_ref())
+}
+
+#[tauri::command]
+fn set_ics_feed(enabled: bool, port: Option<u16>, state: State<AppState>) -> Result<feed::FeedStatus, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    let mut server = state.feed.lock().map_err(|_| "Failed to lock mutex")?;
+
+    let port = match port {
+        Some(port) => port,
+        None => feed::settings(db)?.1,
+    };
+    feed::save_settings(db, enabled, port)?;
+    // Stop the old server first so the port can be reused
+    *server = None;
+    if enabled {
+        *server = Some(feed::FeedServer::start(db.clone(), port)?);
     }
-    
-    Ok(count)
+    feed::status(db, server.as_ref())
 }
 
+#[tauri::command]
+fn reset_ics_feed_url(state: State<AppState>) -> Result<feed::FeedStatus, String> {
+    let db_guard = state.db.lock().map_err(|_| "Failed to lock mutex")?;
+    let db = db_guard.as_ref().ok_or("Database not initialized")?;
+    feed::reset_token(db)?;
+    let server = state.feed.lock().map_err(|_| "Failed to lock mutex")?;
+    feed::status(db, server.as_ref())
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -140,20 +628,97 @@ pub fn run() {
     tauri::Builder::default()
         .manage(AppState {
             db: Mutex::new(None),
+            google: GoogleEndpoints::default(),
+            feed: Mutex::new(None),
+            reminders: Mutex::new(Vec::new()),
         })
         .setup(|app| {
             let db = Database::new(app.handle());
             db.init().expect("Failed to initialize database");
+            // A missing or wrong key leaves secrets locked instead of aborting startup
+            if let Err(e) = secrets::open(&db) {
+                eprintln!("Failed to open secrets: {}", e);
+            }
+            migrate_google_tokens(app.handle(), db.clone());
             
+            // Pages reload their tasks on "tasks-changed" instead of only after their own edits.
+            // Every change is also checked for tasks whose last blocker was just completed, and
+            // pushed to the calendar events of the changed tasks, whoever wrote it (commands, sync,
+            // imports, undo). The local change always wins; an event that can't be updated is only logged.
+            let handle = app.handle().clone();
+            let listener_db = db.clone();
+            let tracker = Mutex::new(dependencies::UnblockTracker::new(&db).expect("Failed to read task dependencies"));
+            // One event sync at a time, so quick edits of a task reach its event in order
+            let event_sync = std::sync::Arc::new(tokio::sync::Mutex::new(()));
+            db.set_change_listener(move |change| {
+                let _ = handle.emit("tasks-changed", change);
+                if change.kind != changes::ChangeKind::Reordered {
+                    let (db, handle, ids, event_sync) = (listener_db.clone(), handle.clone(), change.ids.clone(), event_sync.clone());
+                    tauri::async_runtime::spawn(async move {
+                        let _running = event_sync.lock().await;
+                        if let Err(e) = google::sync_task_events(&db, &handle.state::<AppState>().google, &ids).await {
+                            eprintln!("Failed to update calendar events: {}", e);
+                        }
+                    });
+                }
+                let Ok(mut tracker) = tracker.lock() else { return };
+                match tracker.check(&listener_db) {
+                    Ok(unblocked) => {
+                        for ready in unblocked {
+                            if let Err(e) = handle.notification().builder().title(&ready.task.title).body(dependencies::message(&ready)).show() {
+                                eprintln!("Failed to show notification: {}", e);
+                            }
+                            let _ = handle.emit("task-unblocked", &ready);
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to check blocked tasks: {}", e),
+                }
+            });
+            changes::start_watcher(db.clone());
+
             let state = app.state::<AppState>();
+            match feed::settings(&db) {
+                Ok((true, port)) => match feed::FeedServer::start(db.clone(), port) {
+                    Ok(server) => *state.feed.lock().unwrap() = Some(server),
+                    Err(e) => eprintln!("{}", e),
+                },
+                Ok(_) => {}
+                Err(e) => eprintln!("Failed to read feed settings: {}", e),
+            }
+            backup::start_scheduler(db.clone());
+            // The window listens for "reminder" to offer snooze and dismiss
+            let handle = app.handle().clone();
+            reminders::start_scheduler(db.clone(), move |due| {
+                if let Err(e) = handle.notification().builder().title(&due.task.title).body(reminders::message(due)).show() {
+                    eprintln!("Failed to show reminder: {}", e);
+                }
+                if let Ok(mut pending) = handle.state::<AppState>().reminders.lock() {
+                    pending.retain(|d| d.reminder.id != due.reminder.id);
+                    pending.push(due.clone());
+                }
+                let _ = handle.emit("reminder", due);
+            });
             *state.db.lock().unwrap() = Some(db);
             
             Ok(())
         })
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init())
         .invoke_handler(tauri::generate_handler![
-            get_tasks, get_task, add_task, update_task, delete_task, update_task_order,
-            get_google_auth_url, finish_google_auth, complete_google_auth, get_google_user, get_google_task_lists, import_google_tasks
+            get_tasks, get_task, add_task, update_task, patch_task, delete_task, update_task_order, parse_quick_add,
+            bulk_update_tasks, bulk_delete, bulk_move_to_list, bulk_tag, get_last_undo, undo,
+            export_ics, import_ics, export_plaintext, preview_plaintext_import, import_plaintext, export_data, import_data,
+            list_backups, create_backup, restore_backup,
+            get_reminders, add_reminder, delete_reminder, get_due_reminders, snooze_reminder, dismiss_reminder,
+            search_tasks, check_query, get_smart_lists, get_smart_list_tasks, create_smart_list, update_smart_list, delete_smart_list,
+            get_blockers, get_dependents, add_dependency, remove_dependency,
+            get_secrets_status, unlock_secrets, set_secrets_passphrase,
+            get_google_auth_url, set_google_client_credentials, finish_google_auth, complete_google_auth, get_google_accounts, get_google_user, get_google_task_lists, import_google_tasks,
+            import_all_google_tasks, get_google_list_categories, set_google_list_category,
+            disconnect_google, sync_google_calendar, get_events,
+            get_task_event, schedule_task_on_google_calendar, unschedule_task,
+            connect_caldav, get_caldav_accounts, get_caldav_collections, sync_caldav, add_task_to_caldav, disconnect_caldav,
+            get_ics_feed_status, set_ics_feed, reset_ics_feed_url
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
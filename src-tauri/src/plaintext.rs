@@ -0,0 +1,439 @@
+use crate::db::{new_task_id, Database, Task, DEFAULT_CATEGORY};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Plain-text task formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlainTextFormat {
+    /// todo.txt, one task per line (http://todotxt.org)
+    TodoTxt,
+    /// GitHub-flavored Markdown task list, headings name categories
+    Markdown,
+}
+
+/// Tasks read from a plain-text file. Returned as-is by the dry run, and after writing by the import.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PlainTextImport {
+    pub tasks: Vec<Task>,
+    /// Lines that were skipped or only partly understood, e.g. "Line 3: invalid date in due:2026-13-01"
+    pub warnings: Vec<String>,
+}
+
+fn new_task(title: String, category: &str) -> Task {
+    Task {
+        id: new_task_id(),
+        title,
+        category: category.to_string(),
+        ..Default::default()
+    }
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+// todo.txt and Obsidian style repeats ("1w", "every 2 weeks") share the same units
+fn rrule(unit: &str, interval: u32) -> Option<String> {
+    let freq = match unit {
+        "d" | "day" | "days" => "DAILY",
+        "w" | "week" | "weeks" => "WEEKLY",
+        "m" | "month" | "months" => "MONTHLY",
+        "y" | "year" | "years" => "YEARLY",
+        _ => return None,
+    };
+    Some(match interval {
+        0 => return None,
+        1 => format!("FREQ={}", freq),
+        n => format!("FREQ={};INTERVAL={}", freq, n),
+    })
+}
+
+// (interval, unit letter) of rules that are only FREQ and INTERVAL
+fn simple_repeat(rule: &str) -> Option<(u32, char)> {
+    let mut unit = None;
+    let mut interval = 1;
+    for part in rule.split(';') {
+        match part.split_once('=')? {
+            ("FREQ", "DAILY") => unit = Some('d'),
+            ("FREQ", "WEEKLY") => unit = Some('w'),
+            ("FREQ", "MONTHLY") => unit = Some('m'),
+            ("FREQ", "YEARLY") => unit = Some('y'),
+            ("INTERVAL", n) => interval = n.parse().ok()?,
+            _ => return None,
+        }
+    }
+    Some((interval, unit?))
+}
+
+// Finishes a parsed task: empty titles and invalid field combinations skip the line
+fn accept(mut task: Task, title: Vec<&str>, line_no: usize, result: &mut PlainTextImport) -> Option<String> {
+    task.title = title.join(" ");
+    if task.title.is_empty() {
+        result.warnings.push(format!("Line {}: task has no title", line_no));
+        return None;
+    }
+    if let Err(e) = task.validate() {
+        result.warnings.push(format!("Line {}: {}", line_no, e));
+        return None;
+    }
+    let id = task.id.clone();
+    result.tasks.push(task);
+    Some(id)
+}
+
+// (A) high, (B) medium, (C) and below low
+fn todotxt_priority(letter: char) -> &'static str {
+    match letter {
+        'A' => "High",
+        'B' => "Medium",
+        _ => "Low",
+    }
+}
+
+fn todotxt_priority_token(token: &str) -> Option<char> {
+    let letter = token.strip_prefix('(')?.strip_suffix(')')?;
+    let mut chars = letter.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_uppercase() => Some(c),
+        _ => None,
+    }
+}
+
+// "rec:2w" / "rec:+1m" (the strict "+" form is treated like the normal one)
+fn todotxt_repeat(value: &str) -> Option<String> {
+    let value = value.trim_start_matches('+');
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let (count, unit) = value.split_at(split);
+    rrule(unit, if count.is_empty() { 1 } else { count.parse().ok()? })
+}
+
+fn parse_todotxt(input: &str) -> PlainTextImport {
+    let mut result = PlainTextImport::default();
+    for (index, line) in input.lines().enumerate() {
+        let line_no = index + 1;
+        let mut tokens = line.split_whitespace().peekable();
+        if tokens.peek().is_none() {
+            continue;
+        }
+
+        let mut task = new_task(String::new(), DEFAULT_CATEGORY);
+        if tokens.peek() == Some(&"x") {
+            tokens.next();
+            task.completed = true;
+            // Completion date
+            tokens.next_if(|t| parse_date(t).is_some());
+        }
+        if let Some(letter) = tokens.peek().and_then(|t| todotxt_priority_token(t)) {
+            tokens.next();
+            task.priority = todotxt_priority(letter).to_string();
+        }
+        // Creation date
+        tokens.next_if(|t| parse_date(t).is_some());
+
+        let mut category = None;
+        let mut title = Vec::new();
+        for token in tokens {
+            if let Some(project) = token.strip_prefix('+').filter(|p| !p.is_empty())
+                && category.is_none()
+            {
+                category = Some(project.replace('_', " "));
+                continue;
+            }
+            if let Some(context) = token.strip_prefix('@').filter(|c| !c.is_empty()) {
+                task.tags.push(context.to_string());
+                continue;
+            }
+            let Some((key, value)) = token.split_once(':') else {
+                title.push(token);
+                continue;
+            };
+            let parsed = match key {
+                "due" => parse_date(value).map(|date| task.due_date = Some(date)),
+                "t" => parse_date(value).map(|date| task.start_date = Some(date)),
+                "rec" => todotxt_repeat(value).map(|rule| task.recurrence = Some(rule)),
+                // Completed tasks keep their priority as pri:A
+                "pri" => value.chars().next().filter(|c| c.is_ascii_uppercase() && value.len() == 1)
+                    .map(|letter| task.priority = todotxt_priority(letter).to_string()),
+                // Other key:value pairs (and URLs) are part of the text
+                _ => {
+                    title.push(token);
+                    continue;
+                }
+            };
+            if parsed.is_none() {
+                result.warnings.push(format!("Line {}: could not read {}", line_no, token));
+                title.push(token);
+            }
+        }
+        if let Some(category) = category {
+            task.category = category;
+        }
+        accept(task, title, line_no, &mut result);
+    }
+    result
+}
+
+fn export_todotxt(tasks: &[Task]) -> String {
+    let mut out = String::new();
+    for task in tasks {
+        let letter = match task.priority.as_str() {
+            "High" => 'A',
+            "Low" => 'C',
+            _ => 'B',
+        };
+        let mut parts = Vec::new();
+        if task.completed {
+            parts.push("x".to_string());
+        } else {
+            parts.push(format!("({})", letter));
+        }
+        parts.push(task.title.replace(['\n', '\r'], " "));
+        if !task.category.is_empty() {
+            // Projects can't contain spaces
+            parts.push(format!("+{}", task.category.split_whitespace().collect::<Vec<_>>().join("_")));
+        }
+        parts.extend(task.tags.iter().map(|tag| format!("@{}", tag)));
+        if let Some(due) = task.due_date {
+            parts.push(format!("due:{}", due));
+        }
+        if let Some(start) = task.start_date {
+            parts.push(format!("t:{}", start));
+        }
+        if let Some((interval, unit)) = task.recurrence.as_deref().and_then(simple_repeat) {
+            parts.push(format!("rec:{}{}", interval, unit));
+        }
+        if task.completed {
+            parts.push(format!("pri:{}", letter));
+        }
+        out.push_str(&parts.join(" "));
+        out.push('\n');
+    }
+    out
+}
+
+// Obsidian Tasks markers, the common convention for metadata in Markdown task lists
+const DUE_MARKER: &str = "📅";
+const START_MARKER: &str = "🛫";
+const REPEAT_MARKER: &str = "🔁";
+const HIGH_MARKERS: [&str; 2] = ["⏫", "🔺"];
+const MEDIUM_MARKER: &str = "🔼";
+const LOW_MARKERS: [&str; 2] = ["🔽", "⏬"];
+
+// (indent, checked, text) of a list item with a checkbox
+fn markdown_item(line: &str) -> Option<(usize, bool, &str)> {
+    let content = line.trim_start_matches([' ', '\t']);
+    // Tabs count as four spaces for nesting
+    let indent: usize = line[..line.len() - content.len()].chars().map(|c| if c == '\t' { 4 } else { 1 }).sum();
+
+    let rest = match content.strip_prefix(['-', '*', '+']) {
+        Some(rest) => rest,
+        None => {
+            let digits = content.find(|c: char| !c.is_ascii_digit()).filter(|&n| n > 0)?;
+            content[digits..].strip_prefix(['.', ')'])?
+        }
+    };
+    let rest = rest.strip_prefix(' ')?.trim_start();
+    let (checked, text) = if let Some(text) = rest.strip_prefix("[ ]") {
+        (false, text)
+    } else if let Some(text) = rest.strip_prefix("[x]").or_else(|| rest.strip_prefix("[X]")) {
+        (true, text)
+    } else {
+        return None;
+    };
+    Some((indent, checked, text.trim()))
+}
+
+// "every week" / "every 2 days"
+fn markdown_repeat(words: &[&str]) -> Option<(String, usize)> {
+    if words.first() != Some(&"every") {
+        return None;
+    }
+    match words.get(1).and_then(|w| w.parse::<u32>().ok()) {
+        Some(n) => Some((rrule(words.get(2)?, n)?, 3)),
+        None => Some((rrule(words.get(1)?, 1)?, 2)),
+    }
+}
+
+fn parse_markdown(input: &str) -> PlainTextImport {
+    let mut result = PlainTextImport::default();
+    let mut category = DEFAULT_CATEGORY.to_string();
+    // (indent, task id) of the open parents; None when the parent line was skipped
+    let mut parents: Vec<(usize, Option<String>)> = Vec::new();
+
+    for (index, line) in input.lines().enumerate() {
+        let line_no = index + 1;
+        let trimmed = line.trim();
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            let heading = heading.trim_start_matches('#').trim();
+            if !heading.is_empty() {
+                category = heading.to_string();
+            }
+            parents.clear();
+            continue;
+        }
+        // Prose and plain list items are ignored
+        let Some((indent, checked, text)) = markdown_item(line) else {
+            continue;
+        };
+
+        while parents.last().is_some_and(|(parent_indent, _)| *parent_indent >= indent) {
+            parents.pop();
+        }
+        let mut task = new_task(String::new(), &category);
+        task.completed = checked;
+        task.parent_id = parents.last().and_then(|(_, id)| id.clone());
+
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let mut title = Vec::new();
+        let mut i = 0;
+        while i < words.len() {
+            let word = words[i];
+            let date = |target: &mut Option<NaiveDate>, result: &mut PlainTextImport| match words.get(i + 1).and_then(|d| parse_date(d)) {
+                Some(date) => {
+                    *target = Some(date);
+                    2
+                }
+                None => {
+                    result.warnings.push(format!("Line {}: {} is not followed by a YYYY-MM-DD date", line_no, word));
+                    1
+                }
+            };
+            i += match word {
+                DUE_MARKER => date(&mut task.due_date, &mut result),
+                START_MARKER => date(&mut task.start_date, &mut result),
+                REPEAT_MARKER => match markdown_repeat(&words[i + 1..]) {
+                    Some((rule, used)) => {
+                        task.recurrence = Some(rule);
+                        used + 1
+                    }
+                    None => {
+                        result.warnings.push(format!("Line {}: unsupported repeat after {}", line_no, word));
+                        1
+                    }
+                },
+                _ if HIGH_MARKERS.contains(&word) => {
+                    task.priority = "High".to_string();
+                    1
+                }
+                MEDIUM_MARKER => 1,
+                _ if LOW_MARKERS.contains(&word) => {
+                    task.priority = "Low".to_string();
+                    1
+                }
+                _ if markdown_tag(word).is_some() => {
+                    task.tags.extend(markdown_tag(word).map(str::to_string));
+                    1
+                }
+                _ => {
+                    title.push(word);
+                    1
+                }
+            };
+        }
+
+        let id = accept(task, title, line_no, &mut result);
+        parents.push((indent, id));
+    }
+    result
+}
+
+// `#tag` as Obsidian reads it: "#123" is an issue number, not a tag
+fn markdown_tag(word: &str) -> Option<&str> {
+    word.strip_prefix('#').filter(|tag| !tag.is_empty() && !tag.starts_with('#') && !tag.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn export_markdown(tasks: &[Task]) -> String {
+    let mut children: HashMap<&str, Vec<&Task>> = HashMap::new();
+    let mut categories: Vec<(&str, Vec<&Task>)> = Vec::new();
+    for task in tasks {
+        // Subtasks are listed under their parent, unless it's not part of the export or in another category
+        let parent = task.parent_id.as_deref().and_then(|id| tasks.iter().find(|t| t.id == id));
+        if let Some(parent) = parent.filter(|p| p.category == task.category) {
+            children.entry(parent.id.as_str()).or_default().push(task);
+            continue;
+        }
+        match categories.iter_mut().find(|(name, _)| *name == task.category) {
+            Some((_, roots)) => roots.push(task),
+            None => categories.push((task.category.as_str(), vec![task])),
+        }
+    }
+
+    fn write_item(task: &Task, depth: usize, children: &HashMap<&str, Vec<&Task>>, out: &mut String) {
+        let mut line = format!(
+            "{}- [{}] {}",
+            "  ".repeat(depth),
+            if task.completed { "x" } else { " " },
+            task.title.replace(['\n', '\r'], " ")
+        );
+        for tag in &task.tags {
+            line.push_str(&format!(" #{}", tag));
+        }
+        match task.priority.as_str() {
+            "High" => line.push_str(&format!(" {}", HIGH_MARKERS[0])),
+            "Low" => line.push_str(&format!(" {}", LOW_MARKERS[0])),
+            _ => {}
+        }
+        if let Some((interval, unit)) = task.recurrence.as_deref().and_then(simple_repeat) {
+            let unit = match unit {
+                'd' => "day",
+                'w' => "week",
+                'm' => "month",
+                _ => "year",
+            };
+            match interval {
+                1 => line.push_str(&format!(" {} every {}", REPEAT_MARKER, unit)),
+                n => line.push_str(&format!(" {} every {} {}s", REPEAT_MARKER, n, unit)),
+            }
+        }
+        if let Some(start) = task.start_date {
+            line.push_str(&format!(" {} {}", START_MARKER, start));
+        }
+        if let Some(due) = task.due_date {
+            line.push_str(&format!(" {} {}", DUE_MARKER, due));
+        }
+        out.push_str(&line);
+        out.push('\n');
+        for child in children.get(task.id.as_str()).into_iter().flatten() {
+            write_item(child, depth + 1, children, out);
+        }
+    }
+
+    let mut out = String::new();
+    for (i, (category, roots)) in categories.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format!("## {}\n\n", category));
+        for task in roots {
+            write_item(task, 0, &children, &mut out);
+        }
+    }
+    out
+}
+
+/// Reads tasks without writing anything; ids are generated for the new tasks.
+pub fn parse(input: &str, format: PlainTextFormat) -> PlainTextImport {
+    match format {
+        PlainTextFormat::TodoTxt => parse_todotxt(input),
+        PlainTextFormat::Markdown => parse_markdown(input),
+    }
+}
+
+/// Fields without a plain-text form (description, due time) are left out.
+pub fn export(tasks: &[Task], format: PlainTextFormat) -> String {
+    match format {
+        PlainTextFormat::TodoTxt => export_todotxt(tasks),
+        PlainTextFormat::Markdown => export_markdown(tasks),
+    }
+}
+
+/// Adds every task `parse` returns. Plain text has no stable ids, so importing twice duplicates tasks.
+pub fn import(db: &Database, input: &str, format: PlainTextFormat) -> Result<PlainTextImport, String> {
+    let parsed = parse(input, format);
+    for task in &parsed.tasks {
+        db.add_task(task.clone()).map_err(|e| e.to_string())?;
+    }
+    Ok(parsed)
+}
@@ -0,0 +1,519 @@
+use crate::db::{Database, Task};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use rusqlite::types::Value;
+use serde::Serialize;
+
+/// Furthest a relative date (`7d`, `-2w`) may point, about a hundred years.
+pub const MAX_RELATIVE_DAYS: i64 = 36_500;
+const OUT_OF_RANGE: &str = "Date out of range";
+
+/// Parsed filter query, e.g. `due:<7d priority:high -tag:waiting list:Work "release"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// Every term must match; empty matches everything
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    /// Bare word or "quoted phrase" in the title or description
+    Text(String),
+    /// "High", "Medium" or "Low"
+    Priority(String),
+    Tag(String),
+    /// List (category) name, case-insensitive
+    List(String),
+    Date(DateField, Op, DateValue),
+    Is(State),
+    Has(Attribute),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateField {
+    Due,
+    Start,
+    Created,
+    Updated,
+    Completed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateValue {
+    Date(NaiveDate),
+    /// Days from today, negative in the past ("7d", "-2w", "tomorrow")
+    Days(i64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Done,
+    Open,
+    /// Open and due before today
+    Overdue,
+    Recurring,
+    /// Waits for an open task
+    Blocked,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attribute {
+    Due,
+    Start,
+    Tag,
+    Description,
+    Reminder,
+}
+
+/// What is wrong with a query and where. Offsets are UTF-16 code units, like `QuickAddSpan`,
+/// so the search box can highlight `query.slice(start, end)`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QueryError {
+    pub message: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl std::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (at {})", self.message, self.start + 1)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// WHERE clause over `tasks` with its parameters, built by `compile`. Values are always bound,
+/// never spliced into the SQL.
+#[derive(Debug, Clone)]
+pub struct SqlFilter {
+    pub(crate) sql: String,
+    pub(crate) params: Vec<Value>,
+}
+
+// Lexing and parsing
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    Minus,
+    Or,
+    /// Bare word or quoted phrase
+    Word(String),
+    /// `key:value`, with the byte range of the value
+    Field(String, String, usize),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+struct Lexer<'a> {
+    query: &'a str,
+    pos: usize,
+}
+
+fn utf16_offset(text: &str, byte: usize) -> usize {
+    text[..byte].encode_utf16().count()
+}
+
+fn error(query: &str, message: String, start: usize, end: usize) -> QueryError {
+    QueryError { message, start: utf16_offset(query, start), end: utf16_offset(query, end) }
+}
+
+fn ends_word(c: char) -> bool {
+    c.is_whitespace() || c == '(' || c == ')'
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&self) -> Option<char> {
+        self.query[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    // After the opening quote; returns the phrase without quotes
+    fn quoted(&mut self, open: usize) -> Result<String, QueryError> {
+        let start = self.pos;
+        while let Some(c) = self.bump() {
+            if c == '"' {
+                return Ok(self.query[start..self.pos - 1].to_string());
+            }
+        }
+        Err(error(self.query, "Missing closing quote".to_string(), open, self.pos))
+    }
+
+    fn until_word_end(&mut self) -> &'a str {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| !ends_word(c)) {
+            self.bump();
+        }
+        &self.query[start..self.pos]
+    }
+
+    fn tokens(mut self) -> Result<Vec<Token>, QueryError> {
+        let mut tokens = Vec::new();
+        while let Some(c) = self.peek() {
+            let start = self.pos;
+            if c.is_whitespace() {
+                self.bump();
+                continue;
+            }
+            let kind = match c {
+                '(' => {
+                    self.bump();
+                    TokenKind::LParen
+                }
+                ')' => {
+                    self.bump();
+                    TokenKind::RParen
+                }
+                '-' => {
+                    self.bump();
+                    if self.peek().is_none_or(|c| c.is_whitespace() || c == ')') {
+                        return Err(error(self.query, "Nothing to exclude after '-'".to_string(), start, self.pos));
+                    }
+                    TokenKind::Minus
+                }
+                '"' => {
+                    self.bump();
+                    TokenKind::Word(self.quoted(start)?)
+                }
+                _ => {
+                    while self.peek().is_some_and(|c| !ends_word(c) && c != ':') {
+                        self.bump();
+                    }
+                    let key = &self.query[start..self.pos];
+                    if self.peek() == Some(':') {
+                        self.bump();
+                        let value_start = self.pos;
+                        let value = if self.peek() == Some('"') {
+                            self.bump();
+                            self.quoted(value_start)?
+                        } else {
+                            self.until_word_end().to_string()
+                        };
+                        if value.is_empty() {
+                            return Err(error(self.query, format!("Missing value after \"{}:\"", key), start, self.pos));
+                        }
+                        TokenKind::Field(key.to_string(), value, value_start)
+                    } else if key == "OR" {
+                        TokenKind::Or
+                    } else {
+                        TokenKind::Word(key.to_string())
+                    }
+                }
+            };
+            tokens.push(Token { kind, start, end: self.pos });
+        }
+        Ok(tokens)
+    }
+}
+
+struct Parser<'a> {
+    query: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn or(&mut self) -> Result<Expr, QueryError> {
+        let mut terms = vec![self.and()?];
+        while self.peek().is_some_and(|t| t.kind == TokenKind::Or) {
+            self.pos += 1;
+            terms.push(self.and()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Expr::Or(terms) })
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError> {
+        let mut terms = Vec::new();
+        while let Some(token) = self.peek() {
+            if matches!(token.kind, TokenKind::Or | TokenKind::RParen) {
+                break;
+            }
+            terms.push(self.unary()?);
+        }
+        match (terms.len(), self.peek()) {
+            (0, Some(token)) if token.kind == TokenKind::Or => {
+                Err(error(self.query, "OR needs a term on both sides".to_string(), token.start, token.end))
+            }
+            (0, _) => {
+                let at = self.peek().map_or(self.query.len(), |t| t.start);
+                let end = self.peek().map_or(at, |t| t.end);
+                Err(error(self.query, "Expected a search term".to_string(), at, end))
+            }
+            (1, _) => Ok(terms.remove(0)),
+            _ => Ok(Expr::And(terms)),
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, QueryError> {
+        let token = self.tokens[self.pos].clone();
+        self.pos += 1;
+        match token.kind {
+            TokenKind::Minus => Ok(Expr::Not(Box::new(self.unary()?))),
+            TokenKind::LParen => {
+                let inner = self.or()?;
+                match self.peek() {
+                    Some(t) if t.kind == TokenKind::RParen => {
+                        self.pos += 1;
+                        Ok(inner)
+                    }
+                    _ => Err(error(self.query, "Missing closing parenthesis".to_string(), token.start, token.end)),
+                }
+            }
+            TokenKind::RParen => Err(error(self.query, "Unmatched ')'".to_string(), token.start, token.end)),
+            TokenKind::Or => Err(error(self.query, "OR needs a term on both sides".to_string(), token.start, token.end)),
+            TokenKind::Word(word) => match word.strip_prefix('#') {
+                Some(tag) if !tag.is_empty() => Ok(Expr::Tag(tag.to_string())),
+                _ => Ok(Expr::Text(word)),
+            },
+            TokenKind::Field(key, value, value_start) => self.field(&key, &value, token.start, value_start, token.end),
+        }
+    }
+
+    fn field(&self, key: &str, value: &str, start: usize, value_start: usize, end: usize) -> Result<Expr, QueryError> {
+        let bad_value = |what: &str| error(self.query, format!("{} \"{}\"", what, value), value_start, end);
+        let date_field = match key.to_ascii_lowercase().as_str() {
+            "due" => DateField::Due,
+            "start" => DateField::Start,
+            "created" => DateField::Created,
+            "updated" => DateField::Updated,
+            "completed" => DateField::Completed,
+            "priority" | "p" => {
+                return match value.to_ascii_lowercase().as_str() {
+                    "high" => Ok(Expr::Priority("High".to_string())),
+                    "medium" => Ok(Expr::Priority("Medium".to_string())),
+                    "low" => Ok(Expr::Priority("Low".to_string())),
+                    _ => Err(bad_value("Unknown priority")),
+                };
+            }
+            "tag" => return Ok(Expr::Tag(value.trim_start_matches('#').to_string())),
+            "list" => return Ok(Expr::List(value.to_string())),
+            "is" => {
+                return match value.to_ascii_lowercase().as_str() {
+                    "done" | "completed" => Ok(Expr::Is(State::Done)),
+                    "open" | "active" => Ok(Expr::Is(State::Open)),
+                    "overdue" => Ok(Expr::Is(State::Overdue)),
+                    "recurring" => Ok(Expr::Is(State::Recurring)),
+                    "blocked" => Ok(Expr::Is(State::Blocked)),
+                    _ => Err(bad_value("Unknown state")),
+                };
+            }
+            "has" => {
+                return match value.to_ascii_lowercase().as_str() {
+                    "due" => Ok(Expr::Has(Attribute::Due)),
+                    "start" => Ok(Expr::Has(Attribute::Start)),
+                    "tag" | "tags" => Ok(Expr::Has(Attribute::Tag)),
+                    "description" | "notes" => Ok(Expr::Has(Attribute::Description)),
+                    "reminder" | "reminders" => Ok(Expr::Has(Attribute::Reminder)),
+                    _ => Err(bad_value("Unknown attribute")),
+                };
+            }
+            _ => return Err(error(self.query, format!("Unknown field \"{}\"", key), start, value_start - 1)),
+        };
+        let (op, date) = split_op(value);
+        let date = parse_date_value(date).map_err(bad_value)?;
+        Ok(Expr::Date(date_field, op, date))
+    }
+}
+
+fn split_op(value: &str) -> (Op, &str) {
+    for (prefix, op) in [("<=", Op::Le), (">=", Op::Ge), ("<", Op::Lt), (">", Op::Gt), ("=", Op::Eq)] {
+        if let Some(rest) = value.strip_prefix(prefix) {
+            return (op, rest);
+        }
+    }
+    (Op::Eq, value)
+}
+
+// "today", "2026-10-20", "7d", "-2w"
+fn parse_date_value(value: &str) -> Result<DateValue, &'static str> {
+    match value.to_ascii_lowercase().as_str() {
+        "today" => return Ok(DateValue::Days(0)),
+        "tomorrow" => return Ok(DateValue::Days(1)),
+        "yesterday" => return Ok(DateValue::Days(-1)),
+        _ => {}
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(DateValue::Date(date));
+    }
+    let (number, unit_days) = if let Some(number) = value.strip_suffix(['d', 'D']) {
+        (number, 1)
+    } else if let Some(number) = value.strip_suffix(['w', 'W']) {
+        (number, 7)
+    } else {
+        return Err("Invalid date");
+    };
+    let n: i64 = number.strip_prefix('+').unwrap_or(number).parse().map_err(|_| "Invalid date")?;
+    match n.checked_mul(unit_days) {
+        Some(days) if days.abs() <= MAX_RELATIVE_DAYS => Ok(DateValue::Days(days)),
+        _ => Err("Date too far away"),
+    }
+}
+
+/// Parses a query. Terms are ANDed; `OR` (upper case) and parentheses group, `-` excludes.
+///
+/// Fields: `due:`, `start:`, `created:`, `updated:`, `completed:` take a date (`today`,
+/// `tomorrow`, `yesterday`, `2026-10-20`, `7d`, `-2w`) with an optional `<`, `<=`, `>`, `>=`, `=`;
+/// `priority:` (`p:`) high/medium/low; `tag:` (or `#tag`); `list:`; `is:` done/open/overdue/
+/// recurring/blocked; `has:` due/start/tag/description/reminder. Other words and "quoted phrases"
+/// search the title and description. An empty query matches every task.
+pub fn parse(query: &str) -> Result<Expr, QueryError> {
+    let tokens = Lexer { query, pos: 0 }.tokens()?;
+    if tokens.is_empty() {
+        return Ok(Expr::And(Vec::new()));
+    }
+    let mut parser = Parser { query, tokens, pos: 0 };
+    let expr = parser.or()?;
+    match parser.peek() {
+        Some(token) => Err(error(query, "Unmatched ')'".to_string(), token.start, token.end)),
+        None => Ok(expr),
+    }
+}
+
+// Compiling
+
+fn like_pattern(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+// Start of the local day as the timestamp columns store it
+fn start_of_day(date: NaiveDate, tz: Tz) -> String {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    let instant = tz.from_local_datetime(&midnight).earliest().map_or_else(|| midnight.and_utc(), |t| t.with_timezone(&Utc));
+    instant.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+struct Compiler {
+    today: NaiveDate,
+    tz: Tz,
+    params: Vec<Value>,
+}
+
+impl Compiler {
+    fn bind(&mut self, value: impl Into<Value>) -> &'static str {
+        self.params.push(value.into());
+        "?"
+    }
+
+    fn date(&self, value: DateValue) -> Result<NaiveDate, String> {
+        match value {
+            DateValue::Date(date) => Some(date),
+            DateValue::Days(days) => self.today.checked_add_signed(Duration::try_days(days).ok_or(OUT_OF_RANGE)?),
+        }
+        .ok_or_else(|| OUT_OF_RANGE.to_string())
+    }
+
+    fn join(&mut self, terms: &[Expr], separator: &str) -> Result<String, String> {
+        let terms = terms.iter().map(|t| Ok(format!("({})", self.sql(t)?))).collect::<Result<Vec<_>, String>>()?;
+        Ok(terms.join(separator))
+    }
+
+    fn sql(&mut self, expr: &Expr) -> Result<String, String> {
+        Ok(match expr {
+            Expr::And(terms) if terms.is_empty() => "1".to_string(),
+            Expr::And(terms) => self.join(terms, " AND ")?,
+            Expr::Or(terms) => self.join(terms, " OR ")?,
+            // NULL (a missing due date) counts as not matching, so the negation matches it
+            Expr::Not(inner) => format!("NOT IFNULL(({}), 0)", self.sql(inner)?),
+            Expr::Text(text) => {
+                let pattern = like_pattern(text);
+                self.bind(pattern.clone());
+                self.bind(pattern);
+                "title LIKE ? ESCAPE '\\' OR IFNULL(description, '') LIKE ? ESCAPE '\\'".to_string()
+            }
+            Expr::Priority(priority) => format!("priority = {}", self.bind(priority.clone())),
+            Expr::Tag(tag) => format!(
+                "EXISTS (SELECT 1 FROM json_each(tasks.tags) WHERE json_each.value = {} COLLATE NOCASE)",
+                self.bind(tag.clone())
+            ),
+            Expr::List(list) => format!("category = {} COLLATE NOCASE", self.bind(list.clone())),
+            Expr::Date(field, op, value) => {
+                let date = self.date(*value)?;
+                match field {
+                    DateField::Due | DateField::Start => {
+                        let column = if *field == DateField::Due { "due_date" } else { "start_date" };
+                        let op = match op {
+                            Op::Lt => "<",
+                            Op::Le => "<=",
+                            Op::Eq => "=",
+                            Op::Ge => ">=",
+                            Op::Gt => ">",
+                        };
+                        // Older rows may hold "" for a cleared date
+                        format!("IFNULL(NULLIF({}, '') {} {}, 0)", column, op, self.bind(date.to_string()))
+                    }
+                    DateField::Created | DateField::Updated | DateField::Completed => {
+                        let column = match field {
+                            DateField::Created => "created_at",
+                            DateField::Updated => "updated_at",
+                            _ => "completed_at",
+                        };
+                        let next_day = date.succ_opt().ok_or(OUT_OF_RANGE)?;
+                        let (from, to) = (start_of_day(date, self.tz), start_of_day(next_day, self.tz));
+                        match op {
+                            Op::Lt => format!("IFNULL({} < {}, 0)", column, self.bind(from)),
+                            Op::Le => format!("IFNULL({} < {}, 0)", column, self.bind(to)),
+                            Op::Ge => format!("IFNULL({} >= {}, 0)", column, self.bind(from)),
+                            Op::Gt => format!("IFNULL({} >= {}, 0)", column, self.bind(to)),
+                            Op::Eq => format!("IFNULL({} >= {} AND {} < {}, 0)", column, self.bind(from), column, self.bind(to)),
+                        }
+                    }
+                }
+            }
+            Expr::Is(State::Done) => "completed = 1".to_string(),
+            Expr::Is(State::Open) => "completed = 0".to_string(),
+            Expr::Is(State::Overdue) => {
+                format!("completed = 0 AND IFNULL(NULLIF(due_date, '') < {}, 0)", self.bind(self.today.to_string()))
+            }
+            Expr::Is(State::Recurring) => "IFNULL(recurrence, '') <> ''".to_string(),
+            Expr::Is(State::Blocked) => crate::db::blocked_sql("tasks"),
+            Expr::Has(Attribute::Due) => "IFNULL(due_date, '') <> ''".to_string(),
+            Expr::Has(Attribute::Start) => "IFNULL(start_date, '') <> ''".to_string(),
+            Expr::Has(Attribute::Tag) => "json_array_length(IFNULL(tags, '[]')) > 0".to_string(),
+            Expr::Has(Attribute::Description) => "IFNULL(description, '') <> ''".to_string(),
+            Expr::Has(Attribute::Reminder) => "EXISTS (SELECT 1 FROM reminders WHERE reminders.task_id = tasks.id)".to_string(),
+        })
+    }
+}
+
+/// Compiles to a WHERE clause. Relative dates count from the day of `now`, and created/updated/
+/// completed dates are days in `now`'s timezone. Fails for a date chrono can't represent.
+pub fn compile(expr: &Expr, now: DateTime<Tz>) -> Result<SqlFilter, String> {
+    let mut compiler = Compiler { today: now.date_naive(), tz: now.timezone(), params: Vec::new() };
+    let sql = compiler.sql(expr)?;
+    Ok(SqlFilter { sql, params: compiler.params })
+}
+
+/// Now in the machine's timezone, which relative dates count from.
+pub fn local_now() -> DateTime<Tz> {
+    let tz: Tz = crate::db::local_timezone().parse().unwrap_or(Tz::UTC);
+    Utc::now().with_timezone(&tz)
+}
+
+/// Tasks matching `query`, in list order.
+pub fn search(db: &Database, query: &str, now: DateTime<Tz>) -> Result<Vec<Task>, String> {
+    let expr = parse(query).map_err(|e| e.to_string())?;
+    db.find_tasks(&compile(&expr, now)?).map_err(|e| e.to_string())
+}
@@ -0,0 +1,521 @@
+use crate::db::{new_task_id, Task};
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpanKind {
+    Date,
+    Time,
+    Priority,
+    Tag,
+    List,
+    Recurrence,
+}
+
+/// Part of the input that was read as a field. Offsets are UTF-16 code units, the same as
+/// JavaScript string indexes, so the UI can highlight `text.slice(start, end)`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuickAddSpan {
+    pub start: usize,
+    pub end: usize,
+    pub kind: SpanKind,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuickAdd {
+    /// Pre-filled task, not saved yet. The title is the input without the consumed words.
+    pub task: Task,
+    pub spans: Vec<QuickAddSpan>,
+}
+
+enum Found {
+    Date(NaiveDate),
+    Time(NaiveTime),
+    Priority(&'static str),
+    Tag(String),
+    List(String),
+    Repeat(String, Option<Weekday>),
+}
+
+impl Found {
+    fn kind(&self) -> SpanKind {
+        match self {
+            Found::Date(_) => SpanKind::Date,
+            Found::Time(_) => SpanKind::Time,
+            Found::Priority(_) => SpanKind::Priority,
+            Found::Tag(_) => SpanKind::Tag,
+            Found::List(_) => SpanKind::List,
+            Found::Repeat(..) => SpanKind::Recurrence,
+        }
+    }
+}
+
+struct Word<'a> {
+    start: usize,
+    end: usize,
+    /// Lowercased, without trailing punctuation and Korean particles like "까지"
+    key: String,
+    text: &'a str,
+}
+
+// "내일까지", "금요일에": the particle doesn't change the date
+const KOREAN_PARTICLES: [&str; 4] = ["까지", "에는", "부터", "에"];
+
+fn words(text: &str) -> Vec<Word<'_>> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                let word = &text[s..i];
+                let trimmed = word.trim_end_matches([',', '.', ';']);
+                let mut key = trimmed.to_lowercase();
+                if let Some(stem) = KOREAN_PARTICLES.iter().find_map(|p| key.strip_suffix(p)).filter(|s| !s.is_empty()) {
+                    key = stem.to_string();
+                }
+                words.push(Word { start: s, end: s + trimmed.len().max(1), key, text: word });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+fn weekday(word: &str) -> Option<Weekday> {
+    Some(match word {
+        "mon" | "monday" | "월요일" => Weekday::Mon,
+        "tue" | "tues" | "tuesday" | "화요일" => Weekday::Tue,
+        "wed" | "wednesday" | "수요일" => Weekday::Wed,
+        "thu" | "thur" | "thurs" | "thursday" | "목요일" => Weekday::Thu,
+        "fri" | "friday" | "금요일" => Weekday::Fri,
+        "sat" | "saturday" | "토요일" => Weekday::Sat,
+        "sun" | "sunday" | "일요일" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+// After "다음주"/"이번주" a single syllable is enough: "다음주 월"
+fn korean_short_weekday(word: &str) -> Option<Weekday> {
+    weekday(word).or(match word {
+        "월" => Some(Weekday::Mon),
+        "화" => Some(Weekday::Tue),
+        "수" => Some(Weekday::Wed),
+        "목" => Some(Weekday::Thu),
+        "금" => Some(Weekday::Fri),
+        "토" => Some(Weekday::Sat),
+        "일" => Some(Weekday::Sun),
+        _ => None,
+    })
+}
+
+fn weekday_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn month(word: &str) -> Option<u32> {
+    const NAMES: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    let position = NAMES.iter().position(|name| word.starts_with(name))?;
+    let full = ["january", "february", "march", "april", "may", "june", "july", "august", "september", "october", "november", "december"];
+    // "mar" and "march" but not "market"
+    (word.len() == 3 || full[position] == word || (word == "sept" && position == 8)).then_some(position as u32 + 1)
+}
+
+fn number(word: &str) -> Option<u32> {
+    match word {
+        "a" | "an" | "one" => Some(1),
+        "two" => Some(2),
+        "three" => Some(3),
+        "four" => Some(4),
+        "five" => Some(5),
+        _ => word.parse().ok().filter(|n| *n > 0),
+    }
+}
+
+/// The next `day`, today included.
+fn upcoming(today: NaiveDate, day: Weekday) -> NaiveDate {
+    let ahead = (7 + day.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+    today + Days::new(ahead as u64)
+}
+
+/// `day` in the week after this one, weeks starting on Monday.
+fn next_week(today: NaiveDate, day: Weekday) -> NaiveDate {
+    let monday = today - Days::new(today.weekday().num_days_from_monday() as u64) + Days::new(7);
+    monday + Days::new(day.num_days_from_monday() as u64)
+}
+
+fn first_of_next_month(today: NaiveDate) -> Option<NaiveDate> {
+    today.with_day(1)?.checked_add_months(Months::new(1))
+}
+
+/// The next `month`/`day`, this year if it hasn't passed yet.
+fn next_date(today: NaiveDate, month: u32, day: u32) -> Option<NaiveDate> {
+    let this_year = NaiveDate::from_ymd_opt(today.year(), month, day);
+    match this_year {
+        Some(date) if date >= today => Some(date),
+        _ => NaiveDate::from_ymd_opt(today.year() + 1, month, day),
+    }
+}
+
+fn offset(today: NaiveDate, amount: u32, unit: &str) -> Option<NaiveDate> {
+    match unit {
+        "day" | "days" | "일" => today.checked_add_days(Days::new(amount as u64)),
+        "week" | "weeks" | "주" => today.checked_add_days(Days::new(7 * amount as u64)),
+        "month" | "months" | "개월" | "달" => today.checked_add_months(Months::new(amount)),
+        "year" | "years" | "년" => today.checked_add_months(Months::new(amount.checked_mul(12)?)),
+        _ => None,
+    }
+}
+
+// "3일", "2주", "1개월": (amount, unit)
+fn korean_amount(word: &str) -> Option<(u32, &str)> {
+    let digits = word.find(|c: char| !c.is_ascii_digit())?;
+    let amount = word[..digits].parse().ok().filter(|n| *n > 0)?;
+    Some((amount, &word[digits..]))
+}
+
+fn match_date(w: &[Word], today: NaiveDate) -> Option<(usize, NaiveDate)> {
+    let key = |i: usize| w.get(i).map(|w| w.key.as_str()).unwrap_or("");
+    let (first, second, third) = (key(0), key(1), key(2));
+
+    let single = match first {
+        "today" | "tonight" | "오늘" | "오늘밤" => Some(today),
+        "tomorrow" | "tmrw" | "tmr" | "내일" => today.succ_opt(),
+        "모레" => today.checked_add_days(Days::new(2)),
+        "글피" => today.checked_add_days(Days::new(3)),
+        "다음달" => first_of_next_month(today),
+        _ => None,
+    };
+    if let Some(date) = single {
+        return Some((1, date));
+    }
+
+    match (first, second) {
+        ("next", "week") => return Some((2, next_week(today, Weekday::Mon))),
+        ("next", "month") => return Some((2, first_of_next_month(today)?)),
+        ("next", day) if weekday(day).is_some() => return Some((2, next_week(today, weekday(day)?))),
+        ("this", day) if weekday(day).is_some() => return Some((2, upcoming(today, weekday(day)?))),
+        ("in", amount) if number(amount).is_some() => {
+            return offset(today, number(amount)?, third).map(|date| (3, date));
+        }
+        _ => {}
+    }
+
+    // 다음주 / 다음 주 / 이번주, optionally followed by a weekday
+    let (week_words, next) = match (first, second) {
+        ("다음주", _) => (1, true),
+        ("다음", "주") => (2, true),
+        ("이번주", _) => (1, false),
+        ("이번", "주") => (2, false),
+        _ => (0, false),
+    };
+    if week_words > 0 {
+        return Some(match korean_short_weekday(key(week_words)) {
+            Some(day) if next => (week_words + 1, next_week(today, day)),
+            Some(day) => (week_words + 1, upcoming(today, day)),
+            None if next => (week_words, next_week(today, Weekday::Mon)),
+            // "이번주" alone isn't a date
+            None => return None,
+        });
+    }
+
+    if let Some(day) = weekday(first) {
+        return Some((1, upcoming(today, day)));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(first, "%Y-%m-%d") {
+        return Some((1, date));
+    }
+    // 10/20
+    if let Some((m, d)) = first.split_once('/')
+        && let (Ok(m), Ok(d)) = (m.parse(), d.parse())
+    {
+        return next_date(today, m, d).map(|date| (1, date));
+    }
+    // oct 20, 20 oct
+    if let Some(m) = month(first)
+        && let Ok(d) = second.trim_end_matches(|c: char| c.is_ascii_alphabetic()).parse()
+    {
+        return next_date(today, m, d).map(|date| (2, date));
+    }
+    if let (Ok(d), Some(m)) = (first.parse(), month(second)) {
+        return next_date(today, m, d).map(|date| (2, date));
+    }
+    // 10월 20일, 10월20일
+    if let Some((m, rest)) = first.split_once('월')
+        && let Ok(m) = m.parse()
+    {
+        let (day, used) = if rest.is_empty() { (second, 2) } else { (rest, 1) };
+        if let Some(d) = day.strip_suffix('일').and_then(|d| d.parse().ok()) {
+            return next_date(today, m, d).map(|date| (used, date));
+        }
+    }
+    // 3일 후, 2주 뒤, 3일후
+    if let Some((amount, unit)) = korean_amount(first) {
+        for suffix in ["후", "뒤"] {
+            if let Some(unit) = unit.strip_suffix(suffix) {
+                return offset(today, amount, unit).map(|date| (1, date));
+            }
+        }
+        if matches!(second, "후" | "뒤") {
+            return offset(today, amount, unit).map(|date| (2, date));
+        }
+    }
+    None
+}
+
+// 3pm, 3:30pm, 15:00, noon
+fn clock(word: &str) -> Option<NaiveTime> {
+    match word {
+        "noon" | "정오" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" | "자정" => return NaiveTime::from_hms_opt(0, 0, 0),
+        _ => {}
+    }
+    let (digits, pm) = match (word.strip_suffix("am"), word.strip_suffix("pm")) {
+        (Some(d), _) => (d, Some(false)),
+        (_, Some(d)) => (d, Some(true)),
+        _ => (word, None),
+    };
+    let (hour, minute) = match digits.split_once(':') {
+        Some((h, m)) if m.len() == 2 => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?),
+        // A bare number is only a time with am/pm
+        None if pm.is_some() => (digits.parse::<u32>().ok()?, 0),
+        _ => return None,
+    };
+    let hour = match pm {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(true) => hour % 12 + 12,
+        Some(false) => hour % 12,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+// 3시, 3시반, 3시30분 and 3시 30분: (hour, minute, words used)
+fn korean_clock(w: &[Word]) -> Option<(u32, u32, usize)> {
+    let (hour, rest) = w.first()?.key.split_once('시')?;
+    let hour: u32 = hour.parse().ok()?;
+    let minute_word = |m: &str| m.strip_suffix('분').and_then(|m| m.parse::<u32>().ok());
+    match rest {
+        "" => match w.get(1).and_then(|next| minute_word(&next.key)) {
+            Some(minute) => Some((hour, minute, 2)),
+            None => Some((hour, 0, 1)),
+        },
+        "반" => Some((hour, 30, 1)),
+        m => minute_word(m).map(|minute| (hour, minute, 1)),
+    }
+}
+
+fn match_time(w: &[Word]) -> Option<(usize, NaiveTime)> {
+    let first = w.first()?.key.as_str();
+    if let Some(time) = clock(first) {
+        return Some((1, time));
+    }
+    // "3 pm"
+    if let (Ok(hour), Some(next)) = (first.parse::<u32>(), w.get(1))
+        && matches!(next.key.as_str(), "am" | "pm")
+    {
+        return clock(&format!("{}{}", hour, next.key)).map(|time| (2, time));
+    }
+
+    let (pm, skip) = match first {
+        "오후" | "저녁" | "밤" => (Some(true), 1),
+        "오전" | "아침" | "새벽" => (Some(false), 1),
+        _ => (None, 0),
+    };
+    let (hour, minute, used) = korean_clock(&w[skip..])?;
+    let hour = match pm {
+        Some(true) if hour < 12 => hour + 12,
+        Some(false) if hour == 12 => 0,
+        // "3시" on its own usually means the afternoon, "9시" the morning
+        None if (1..8).contains(&hour) => hour + 12,
+        _ => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0).map(|time| (skip + used, time))
+}
+
+fn match_repeat(w: &[Word]) -> Option<(usize, String, Option<Weekday>)> {
+    let key = |i: usize| w.get(i).map(|w| w.key.as_str()).unwrap_or("");
+    let freq = |unit: &str| match unit {
+        "day" | "days" => Some("DAILY"),
+        "week" | "weeks" => Some("WEEKLY"),
+        "month" | "months" => Some("MONTHLY"),
+        "year" | "years" => Some("YEARLY"),
+        _ => None,
+    };
+    let simple = match key(0) {
+        "daily" | "매일" => Some("DAILY"),
+        "weekly" | "매주" => Some("WEEKLY"),
+        "monthly" | "매달" | "매월" => Some("MONTHLY"),
+        "yearly" | "annually" | "매년" => Some("YEARLY"),
+        _ => None,
+    };
+    if let Some(freq) = simple {
+        // 매주 월요일
+        if freq == "WEEKLY"
+            && let Some(day) = weekday(key(1))
+        {
+            return Some((2, format!("FREQ=WEEKLY;BYDAY={}", weekday_code(day)), Some(day)));
+        }
+        return Some((1, format!("FREQ={}", freq), None));
+    }
+    // 월요일마다
+    if let Some(day) = key(0).strip_suffix("마다").and_then(weekday) {
+        return Some((1, format!("FREQ=WEEKLY;BYDAY={}", weekday_code(day)), Some(day)));
+    }
+
+    if key(0) != "every" {
+        return None;
+    }
+    match key(1) {
+        "weekday" => return Some((2, "FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR".to_string(), None)),
+        "other" => return freq(key(2)).map(|f| (3, format!("FREQ={};INTERVAL=2", f), None)),
+        unit => {
+            if let Some(day) = weekday(unit) {
+                return Some((2, format!("FREQ=WEEKLY;BYDAY={}", weekday_code(day)), Some(day)));
+            }
+            if let Some(f) = freq(unit) {
+                return Some((2, format!("FREQ={}", f), None));
+            }
+        }
+    }
+    let interval = number(key(1))?;
+    let f = freq(key(2))?;
+    Some((3, if interval == 1 { format!("FREQ={}", f) } else { format!("FREQ={};INTERVAL={}", f, interval) }, None))
+}
+
+fn match_marker(word: &Word) -> Option<Found> {
+    if let Some(tag) = word.text.strip_prefix('#') {
+        let tag = tag.trim_end_matches([',', '.', ';']);
+        // "#123" is an issue number
+        return (!tag.is_empty() && !tag.starts_with('#') && !tag.chars().all(|c| c.is_ascii_digit()))
+            .then(|| Found::Tag(tag.to_string()));
+    }
+    if let Some(list) = word.text.strip_prefix('@') {
+        let list = list.trim_end_matches([',', '.', ';']);
+        return (!list.is_empty()).then(|| Found::List(list.replace('_', " ")));
+    }
+    match word.key.as_str() {
+        "p1" | "!!" | "!!!" => Some(Found::Priority("High")),
+        "p2" => Some(Found::Priority("Medium")),
+        "p3" | "p4" => Some(Found::Priority("Low")),
+        _ => None,
+    }
+}
+
+fn match_at(w: &[Word], today: NaiveDate) -> Option<(usize, Found)> {
+    if let Some(found) = match_marker(&w[0]) {
+        return Some((1, found));
+    }
+    if let Some((used, rule, day)) = match_repeat(w) {
+        return Some((used, Found::Repeat(rule, day)));
+    }
+    // "on friday", "by tomorrow", "at 3pm": the preposition belongs to the phrase
+    let (skip, rest) = match w[0].key.as_str() {
+        "on" | "by" | "due" | "at" if w.len() > 1 => (1, &w[1..]),
+        _ => (0, w),
+    };
+    if let Some((used, time)) = match_time(rest) {
+        return Some((skip + used, Found::Time(time)));
+    }
+    if w[0].key != "at"
+        && let Some((used, date)) = match_date(rest, today)
+    {
+        return Some((skip + used, Found::Date(date)));
+    }
+    None
+}
+
+fn utf16_offset(text: &str, byte: usize) -> usize {
+    text[..byte].encode_utf16().count()
+}
+
+/// Reads dates, times, priority, `#tags`, `@list` and recurrence out of a quick-add line.
+/// Each field is taken from its first occurrence; later ones stay in the title.
+pub fn parse(text: &str, now: NaiveDateTime, timezone: &str) -> QuickAdd {
+    let today = now.date();
+    let words = words(text);
+    let mut task = Task {
+        id: new_task_id(),
+        ..Default::default()
+    };
+    let mut spans = Vec::new();
+    let mut title = Vec::new();
+    let (mut date, mut time, mut priority, mut list, mut repeat) = (None, None, false, false, None);
+
+    let mut i = 0;
+    while i < words.len() {
+        let Some((used, found)) = match_at(&words[i..], today) else {
+            title.push(words[i].text);
+            i += 1;
+            continue;
+        };
+        let taken = match &found {
+            Found::Date(d) if date.is_none() => {
+                date = Some(*d);
+                true
+            }
+            Found::Time(t) if time.is_none() => {
+                time = Some(*t);
+                true
+            }
+            Found::Priority(p) if !priority => {
+                priority = true;
+                task.priority = p.to_string();
+                true
+            }
+            Found::Tag(tag) => {
+                if !task.tags.contains(tag) {
+                    task.tags.push(tag.clone());
+                }
+                true
+            }
+            Found::List(name) if !list => {
+                list = true;
+                task.category = name.clone();
+                true
+            }
+            Found::Repeat(rule, day) if repeat.is_none() => {
+                repeat = Some((rule.clone(), *day));
+                true
+            }
+            _ => false,
+        };
+        if taken {
+            let last = &words[i + used - 1];
+            spans.push(QuickAddSpan {
+                start: utf16_offset(text, words[i].start),
+                end: utf16_offset(text, last.end),
+                kind: found.kind(),
+            });
+        } else {
+            title.extend(words[i..i + used].iter().map(|w| w.text));
+        }
+        i += used;
+    }
+
+    if let Some((rule, day)) = repeat {
+        // A repeating task starts on its first occurrence
+        date = date.or_else(|| Some(day.map_or(today, |day| upcoming(today, day))));
+        task.recurrence = Some(rule);
+    }
+    if let Some(time) = time {
+        // A time without a date is the next time the clock shows it
+        let day = date.unwrap_or(if time > now.time() { today } else { today.succ_opt().unwrap_or(today) });
+        date = Some(day);
+        task.due_time = Some(time);
+        task.timezone = Some(timezone.to_string());
+    }
+    task.due_date = date;
+    task.title = title.join(" ");
+    QuickAdd { task, spans }
+}
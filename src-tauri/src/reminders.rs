@@ -0,0 +1,175 @@
+use crate::db::{self, Database, Due, Reminder, Task};
+use chrono::{NaiveTime, TimeZone};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::thread;
+
+/// Local time that "N minutes before" counts back from for tasks due on a whole day.
+pub const ALL_DAY_REMINDER_TIME: NaiveTime = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+pub const DEFAULT_SNOOZE_MINUTES: i64 = 10;
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+// Reminders older than this when delivered were missed while the app was closed or asleep
+const MISSED_AFTER_SECONDS: i64 = 5 * 60;
+
+/// Reminder whose time has come, as sent to the notifier and the frontend's "reminder" event.
+#[derive(Debug, Clone, Serialize)]
+pub struct DueReminder {
+    pub reminder: Reminder,
+    pub task: Task,
+    /// Unix seconds the reminder was set to go off
+    pub at: i64,
+    pub missed: bool,
+}
+
+fn timezone() -> Tz {
+    db::local_timezone().parse().unwrap_or(Tz::UTC)
+}
+
+/// When the reminder goes off for the task's current due date, ignoring snoozes.
+/// None for an offset reminder on a task without a due date.
+pub fn scheduled_at(reminder: &Reminder, task: &Task) -> Option<i64> {
+    if let Some(at) = reminder.remind_at {
+        return Some(at);
+    }
+    let offset = reminder.offset_minutes?;
+    let due = match task.due().ok()?? {
+        Due::At(at) => at.timestamp(),
+        Due::AllDay(date) => timezone()
+            .from_local_datetime(&date.and_time(ALL_DAY_REMINDER_TIME))
+            .earliest()?
+            .timestamp(),
+    };
+    due.checked_sub(offset.checked_mul(60)?)
+}
+
+/// When the reminder goes off next, taking a snooze into account.
+pub fn next_at(reminder: &Reminder, task: &Task) -> Option<i64> {
+    reminder.snoozed_until.or_else(|| scheduled_at(reminder, task))
+}
+
+pub fn add(db: &Database, task_id: &str, remind_at: Option<i64>, offset_minutes: Option<i64>) -> Result<Reminder, String> {
+    let task = db
+        .get_task_by_id(task_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Task {} not found", task_id))?;
+    match (remind_at, offset_minutes) {
+        (Some(_), None) => {}
+        (None, Some(offset)) if offset < 0 => return Err("A reminder can't be after the due time".to_string()),
+        (None, Some(offset)) if offset.checked_mul(60).is_none() => return Err("Reminder offset is too large".to_string()),
+        (None, Some(_)) if task.due_date.is_none() => {
+            return Err("A reminder before the due date needs a due date".to_string());
+        }
+        (None, Some(_)) => {}
+        _ => return Err("A reminder needs either a time or an offset before the due date".to_string()),
+    }
+
+    let reminder = Reminder {
+        id: db::new_task_id(),
+        task_id: task_id.to_string(),
+        remind_at,
+        offset_minutes,
+        snoozed_until: None,
+        fired_for: None,
+    };
+    db.add_reminder(&reminder).map_err(|e| e.to_string())?;
+    Ok(reminder)
+}
+
+fn get(db: &Database, id: &str) -> Result<(Reminder, Task), String> {
+    let reminder = db
+        .get_reminder(id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Reminder {} not found", id))?;
+    let task = db
+        .get_task_by_id(&reminder.task_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Task {} not found", reminder.task_id))?;
+    Ok((reminder, task))
+}
+
+/// Reminders of open tasks that are due at `now` and haven't been delivered for that time.
+pub fn due(db: &Database, now: i64) -> Result<Vec<DueReminder>, String> {
+    let mut due: Vec<DueReminder> = db
+        .get_open_reminders()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter_map(|(reminder, task)| {
+            let at = next_at(&reminder, &task)?;
+            (at <= now && reminder.fired_for != Some(at)).then(|| DueReminder {
+                missed: now - at > MISSED_AFTER_SECONDS,
+                reminder,
+                task,
+                at,
+            })
+        })
+        .collect();
+    due.sort_by_key(|due| due.at);
+    Ok(due)
+}
+
+/// Hands every due reminder to `notify` once and records it as delivered. A delivered snooze
+/// is cleared and counts for the scheduled time it postponed, so the reminder fires again only
+/// when it is moved or the task's due date changes.
+pub fn deliver_due(db: &Database, now: i64, notify: &mut impl FnMut(&DueReminder)) -> Result<Vec<DueReminder>, String> {
+    let due = due(db, now)?;
+    for due in &due {
+        notify(due);
+        let reminder = &due.reminder;
+        let fired_for = match scheduled_at(reminder, &due.task) {
+            Some(scheduled) if reminder.snoozed_until == Some(due.at) && scheduled <= due.at => scheduled,
+            _ => due.at,
+        };
+        db.set_reminder_state(&reminder.id, None, Some(fired_for)).map_err(|e| e.to_string())?;
+    }
+    Ok(due)
+}
+
+pub fn snooze(db: &Database, id: &str, minutes: i64, now: i64) -> Result<Reminder, String> {
+    if minutes <= 0 {
+        return Err("Snooze for at least a minute".to_string());
+    }
+    let (reminder, _) = get(db, id)?;
+    let snoozed_until = minutes.checked_mul(60).and_then(|seconds| now.checked_add(seconds));
+    if snoozed_until.is_none() {
+        return Err("Snooze is too long".to_string());
+    }
+    db.set_reminder_state(id, snoozed_until, reminder.fired_for).map_err(|e| e.to_string())?;
+    Ok(Reminder { snoozed_until, ..reminder })
+}
+
+/// Drops a snooze and marks the scheduled time as handled, so the reminder stays quiet
+/// until it is moved or the task's due date changes.
+pub fn dismiss(db: &Database, id: &str) -> Result<Reminder, String> {
+    let (reminder, task) = get(db, id)?;
+    let fired_for = scheduled_at(&reminder, &task).or(reminder.fired_for);
+    db.set_reminder_state(id, None, fired_for).map_err(|e| e.to_string())?;
+    Ok(Reminder { snoozed_until: None, fired_for, ..reminder })
+}
+
+/// Notification text, e.g. "Due Oct 20, 15:00" or "Missed reminder · Due Oct 20".
+pub fn message(due: &DueReminder) -> String {
+    let when = match due.task.due() {
+        Ok(Some(Due::At(at))) => format!("Due {}", at.format("%b %-d, %H:%M")),
+        Ok(Some(Due::AllDay(date))) => format!("Due {}", date.format("%b %-d")),
+        _ => "Reminder".to_string(),
+    };
+    if due.missed {
+        format!("Missed reminder · {}", when)
+    } else {
+        when
+    }
+}
+
+/// Checks for due reminders every 30 seconds. The first check runs right away, which delivers
+/// reminders that came due while the app was closed; each is delivered once.
+pub fn start_scheduler<F>(db: Database, mut notify: F)
+where
+    F: FnMut(&DueReminder) + Send + 'static,
+{
+    thread::spawn(move || loop {
+        if let Err(e) = deliver_due(&db, chrono::Utc::now().timestamp(), &mut notify) {
+            eprintln!("Failed to deliver reminders: {}", e);
+        }
+        thread::sleep(CHECK_INTERVAL);
+    });
+}
@@ -0,0 +1,290 @@
+use crate::db::Database;
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+const KEY_FILE: &str = "secret.key";
+
+const BACKEND_SETTING: &str = "secrets_backend";
+const SALT_SETTING: &str = "secrets_salt";
+// Encrypted known value, used to tell a wrong passphrase from corrupted data
+const CHECK_SETTING: &str = "secrets_check";
+const CHECK_PLAINTEXT: &str = "tasker-secrets";
+
+/// Settings describing the key setup of this machine, meaningless with another key.
+pub const KEY_SETTINGS: &[&str] = &[BACKEND_SETTING, SALT_SETTING, CHECK_SETTING];
+
+const BACKEND_KEY_FILE: &str = "keyfile";
+const BACKEND_PASSPHRASE: &str = "passphrase";
+
+/// Source of the 256-bit key used to encrypt secrets at rest.
+/// Implement this to add another backend, e.g. the OS keyring.
+pub trait KeyBackend {
+    fn name(&self) -> &'static str;
+    fn load_key(&self) -> Result<[u8; 32], String>;
+}
+
+/// Random key stored in a file next to the database, created on first use.
+pub struct KeyFileBackend {
+    path: PathBuf,
+}
+
+impl KeyFileBackend {
+    pub fn new(dir: &Path) -> Self {
+        Self { path: dir.join(KEY_FILE) }
+    }
+}
+
+impl KeyBackend for KeyFileBackend {
+    fn name(&self) -> &'static str {
+        BACKEND_KEY_FILE
+    }
+
+    fn load_key(&self) -> Result<[u8; 32], String> {
+        if self.path.exists() {
+            let bytes = std::fs::read(&self.path)
+                .map_err(|e| format!("Failed to read {}: {}", self.path.display(), e))?;
+            return bytes
+                .try_into()
+                .map_err(|_| format!("Invalid key file {}", self.path.display()));
+        }
+
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        write_private_file(&self.path, &key)
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))?;
+        Ok(key)
+    }
+}
+
+#[cfg(unix)]
+fn write_private_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(contents)
+}
+
+#[cfg(not(unix))]
+fn write_private_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    std::fs::write(path, contents)
+}
+
+/// Key derived from a user passphrase with Argon2id.
+pub struct PassphraseBackend {
+    passphrase: String,
+    salt: Vec<u8>,
+}
+
+impl PassphraseBackend {
+    pub fn new(passphrase: &str, salt: Vec<u8>) -> Self {
+        Self { passphrase: passphrase.to_string(), salt }
+    }
+}
+
+impl KeyBackend for PassphraseBackend {
+    fn name(&self) -> &'static str {
+        BACKEND_PASSPHRASE
+    }
+
+    fn load_key(&self) -> Result<[u8; 32], String> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(self.passphrase.as_bytes(), &self.salt, &mut key)
+            .map_err(|e| format!("Failed to derive key: {}", e))?;
+        Ok(key)
+    }
+}
+
+/// Encrypts and decrypts secret values with ChaCha20-Poly1305.
+/// Ciphertexts are stored as `enc:v1:<base64(nonce || ciphertext)>`.
+#[derive(Clone)]
+pub struct SecretStore {
+    cipher: ChaCha20Poly1305,
+}
+
+impl SecretStore {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self { cipher: ChaCha20Poly1305::new(Key::from_slice(&key)) }
+    }
+
+    pub fn from_backend(backend: &dyn KeyBackend) -> Result<Self, String> {
+        Ok(Self::new(backend.load_key()?))
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, String> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| "Failed to encrypt secret".to_string())?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", PREFIX, BASE64.encode(payload)))
+    }
+
+    pub fn decrypt(&self, value: &str) -> Result<String, String> {
+        let encoded = value.strip_prefix(PREFIX).ok_or("Value is not encrypted")?;
+        let payload = BASE64.decode(encoded).map_err(|_| "Malformed encrypted value")?;
+        if payload.len() < NONCE_LEN {
+            return Err("Malformed encrypted value".to_string());
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Failed to decrypt secret (wrong key?)".to_string())?;
+        String::from_utf8(plaintext).map_err(|e| e.to_string())
+    }
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+/// Whether secrets can currently be read and written.
+#[derive(Clone, Default)]
+pub enum SecretsState {
+    /// No secrets subsystem configured, values are stored as given (tests, tools)
+    #[default]
+    Disabled,
+    /// Passphrase backend selected but not unlocked yet
+    Locked,
+    Unlocked(SecretStore),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SecretsStatus {
+    pub backend: String,
+    pub unlocked: bool,
+}
+
+fn backend_name(db: &Database) -> Result<String, String> {
+    Ok(db
+        .get_setting(BACKEND_SETTING)
+        .map_err(|e| e.to_string())?
+        .unwrap_or_else(|| BACKEND_KEY_FILE.to_string()))
+}
+
+// Verifies the key against the stored check value, writing one on first use
+fn verify_key(db: &Database, store: &SecretStore) -> Result<(), String> {
+    match db.get_setting(CHECK_SETTING).map_err(|e| e.to_string())? {
+        Some(check) => match store.decrypt(&check) {
+            Ok(plain) if plain == CHECK_PLAINTEXT => Ok(()),
+            _ => Err("Incorrect passphrase or key".to_string()),
+        },
+        None => db
+            .set_setting(CHECK_SETTING, &store.encrypt(CHECK_PLAINTEXT)?)
+            .map_err(|e| e.to_string()),
+    }
+}
+
+fn activate(db: &Database, store: SecretStore) -> Result<(), String> {
+    verify_key(db, &store)?;
+    db.set_secrets_state(SecretsState::Unlocked(store.clone()));
+    // Tokens written before encryption existed are sealed the first time we have a key
+    db.reseal_secrets(
+        |value| {
+            if is_encrypted(value) {
+                Ok(value.to_string())
+            } else {
+                store.encrypt(value)
+            }
+        },
+        &[],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Sets up the secrets subsystem at startup. The key file backend unlocks immediately,
+/// the passphrase backend stays locked until `unlock` is called.
+pub fn open(db: &Database) -> Result<(), String> {
+    match backend_name(db)?.as_str() {
+        BACKEND_PASSPHRASE => {
+            db.set_secrets_state(SecretsState::Locked);
+            Ok(())
+        }
+        _ => {
+            let result = SecretStore::from_backend(&KeyFileBackend::new(&db.dir()))
+                .and_then(|store| activate(db, store));
+            // Never fall back to writing plaintext when the key can't be used
+            if result.is_err() {
+                db.set_secrets_state(SecretsState::Locked);
+            }
+            result
+        }
+    }
+}
+
+pub fn unlock(db: &Database, passphrase: &str) -> Result<(), String> {
+    if backend_name(db)? != BACKEND_PASSPHRASE {
+        return Err("Secrets are not protected by a passphrase".to_string());
+    }
+    let salt = db
+        .get_setting(SALT_SETTING)
+        .map_err(|e| e.to_string())?
+        .ok_or("Missing passphrase salt")?;
+    let salt = BASE64.decode(salt).map_err(|_| "Malformed passphrase salt")?;
+    activate(db, SecretStore::from_backend(&PassphraseBackend::new(passphrase, salt))?)
+}
+
+/// Switches to a passphrase-derived key, or back to the key file when `passphrase` is `None`.
+/// Every stored secret is re-encrypted with the new key.
+pub fn change_backend(db: &Database, passphrase: Option<&str>) -> Result<(), String> {
+    let current = match db.secrets_state() {
+        SecretsState::Unlocked(store) => store,
+        SecretsState::Locked => return Err("Unlock secrets before changing the passphrase".to_string()),
+        SecretsState::Disabled => return Err("Secrets subsystem is not initialized".to_string()),
+    };
+
+    let (backend, salt): (Box<dyn KeyBackend>, Option<String>) = match passphrase {
+        Some("") => return Err("Passphrase must not be empty".to_string()),
+        Some(passphrase) => {
+            let mut salt = vec![0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            let encoded = BASE64.encode(&salt);
+            (Box::new(PassphraseBackend::new(passphrase, salt)), Some(encoded))
+        }
+        None => (Box::new(KeyFileBackend::new(&db.dir())), None),
+    };
+    let next = SecretStore::from_backend(backend.as_ref())?;
+
+    // Secrets and the settings describing their key change in one transaction
+    let settings = [
+        (CHECK_SETTING, Some(next.encrypt(CHECK_PLAINTEXT)?)),
+        (SALT_SETTING, salt),
+        (BACKEND_SETTING, Some(backend.name().to_string())),
+    ];
+    db.reseal_secrets(
+        |value| {
+            let plain = if is_encrypted(value) { current.decrypt(value)? } else { value.to_string() };
+            next.encrypt(&plain)
+        },
+        &settings,
+    )
+    .map_err(|e| e.to_string())?;
+
+    db.set_secrets_state(SecretsState::Unlocked(next));
+    Ok(())
+}
+
+pub fn status(db: &Database) -> Result<SecretsStatus, String> {
+    Ok(SecretsStatus {
+        backend: backend_name(db)?,
+        unlocked: matches!(db.secrets_state(), SecretsState::Unlocked(_)),
+    })
+}
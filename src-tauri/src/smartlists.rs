@@ -0,0 +1,179 @@
+use crate::db::{self, Database, SmartList, Task};
+use crate::query::{self, DateField, DateValue, Expr, Op, State};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+const PRIORITIES: &[&str] = &["High", "Medium", "Low"];
+
+/// Which tasks a smart list shows. Every condition must hold; empty lists and `None` match everything.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmartFilter {
+    pub completed: Option<bool>,
+    /// Any of these priorities
+    pub priorities: Vec<String>,
+    /// Any of these lists (categories)
+    pub categories: Vec<String>,
+    pub exclude_categories: Vec<String>,
+    /// All of these tags, without the leading '#'
+    pub tags: Vec<String>,
+    /// None of these tags
+    pub exclude_tags: Vec<String>,
+    /// Due today or within this many days, overdue included; tasks without a due date don't match
+    pub due_within_days: Option<i64>,
+    /// Case-insensitive text in the title or description
+    pub text: Option<String>,
+    /// Query in the `query` language, e.g. `due:<7d -tag:waiting`, combined with the fields above
+    pub query: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartSort {
+    /// Drag-and-drop order, as in the task list
+    #[default]
+    Position,
+    /// Soonest first, undated last
+    DueDate,
+    /// High to Low
+    Priority,
+    Title,
+    /// Newest first
+    CreatedAt,
+    /// Most recently changed first
+    UpdatedAt,
+}
+
+impl SmartSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SmartSort::Position => "position",
+            SmartSort::DueDate => "due_date",
+            SmartSort::Priority => "priority",
+            SmartSort::Title => "title",
+            SmartSort::CreatedAt => "created_at",
+            SmartSort::UpdatedAt => "updated_at",
+        }
+    }
+}
+
+/// A smart list with the number of tasks it currently shows, for the sidebar.
+#[derive(Debug, Clone, Serialize)]
+pub struct SmartListCount {
+    #[serde(flatten)]
+    pub list: SmartList,
+    pub count: usize,
+}
+
+impl SmartFilter {
+    /// The filter as a query expression, so smart lists are evaluated like searches.
+    pub fn to_expr(&self) -> Result<Expr, query::QueryError> {
+        let any_of = |values: &[String], term: fn(String) -> Expr| Expr::Or(values.iter().cloned().map(term).collect());
+        let mut terms = Vec::new();
+        match self.completed {
+            Some(true) => terms.push(Expr::Is(State::Done)),
+            Some(false) => terms.push(Expr::Is(State::Open)),
+            None => {}
+        }
+        if !self.priorities.is_empty() {
+            terms.push(any_of(&self.priorities, Expr::Priority));
+        }
+        if !self.categories.is_empty() {
+            terms.push(any_of(&self.categories, Expr::List));
+        }
+        terms.extend(self.exclude_categories.iter().map(|c| Expr::Not(Box::new(Expr::List(c.clone())))));
+        terms.extend(self.tags.iter().map(|t| Expr::Tag(t.clone())));
+        terms.extend(self.exclude_tags.iter().map(|t| Expr::Not(Box::new(Expr::Tag(t.clone())))));
+        if let Some(days) = self.due_within_days {
+            terms.push(Expr::Date(DateField::Due, Op::Le, DateValue::Days(days)));
+        }
+        if let Some(text) = self.text.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+            terms.push(Expr::Text(text.to_string()));
+        }
+        if let Some(query) = self.query.as_deref() {
+            terms.push(query::parse(query)?);
+        }
+        Ok(Expr::And(terms))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some(priority) = self.priorities.iter().find(|p| !PRIORITIES.contains(&p.as_str())) {
+            return Err(format!("Unknown priority: {}", priority));
+        }
+        if let Some(tag) = self.tags.iter().chain(&self.exclude_tags).find(|tag| tag.is_empty() || tag.starts_with('#')) {
+            return Err(format!("Invalid tag: {:?}", tag));
+        }
+        if self.due_within_days.is_some_and(|days| days < 0) {
+            return Err("Due within days can't be negative".to_string());
+        }
+        if self.due_within_days.is_some_and(|days| days > query::MAX_RELATIVE_DAYS) {
+            return Err(format!("Due within days can't be more than {}", query::MAX_RELATIVE_DAYS));
+        }
+        self.to_expr().map(|_| ()).map_err(|e| format!("Invalid query: {}", e))
+    }
+}
+
+fn priority_rank(priority: &str) -> usize {
+    PRIORITIES.iter().position(|p| *p == priority).unwrap_or(PRIORITIES.len())
+}
+
+/// Orders tasks that come in list order by `sort`. Ties keep the list order.
+pub fn sort(tasks: &mut [Task], sort: SmartSort) {
+    match sort {
+        SmartSort::Position => {}
+        SmartSort::DueDate => tasks.sort_by_key(|task| (task.due_date.is_none(), task.due_date, task.due_time)),
+        SmartSort::Priority => tasks.sort_by_key(|task| priority_rank(&task.priority)),
+        SmartSort::Title => tasks.sort_by_key(|task| task.title.to_lowercase()),
+        SmartSort::CreatedAt => tasks.sort_by_key(|task| std::cmp::Reverse(task.created_at)),
+        SmartSort::UpdatedAt => tasks.sort_by_key(|task| std::cmp::Reverse(task.updated_at)),
+    }
+}
+
+fn validate(list: &SmartList) -> Result<(), String> {
+    if list.name.trim().is_empty() {
+        return Err("Smart list name is empty".to_string());
+    }
+    list.filter.validate()
+}
+
+pub fn create(db: &Database, name: &str, filter: SmartFilter, sort: SmartSort) -> Result<SmartList, String> {
+    let list = SmartList { id: db::new_task_id(), name: name.trim().to_string(), filter, sort, position: 0 };
+    validate(&list)?;
+    db.add_smart_list(&list).map_err(|e| e.to_string())?;
+    db.get_smart_list(&list.id).map_err(|e| e.to_string())?.ok_or_else(|| "Smart list disappeared".to_string())
+}
+
+pub fn update(db: &Database, list: SmartList) -> Result<SmartList, String> {
+    let list = SmartList { name: list.name.trim().to_string(), ..list };
+    validate(&list)?;
+    if !db.update_smart_list(&list).map_err(|e| e.to_string())? {
+        return Err(format!("Smart list {} not found", list.id));
+    }
+    Ok(list)
+}
+
+fn compile(list: &SmartList, now: DateTime<Tz>) -> Result<query::SqlFilter, String> {
+    let expr = list.filter.to_expr().map_err(|e| format!("{}: invalid query: {}", list.name, e))?;
+    query::compile(&expr, now).map_err(|e| format!("{}: {}", list.name, e))
+}
+
+/// Tasks currently shown by the smart list `id`.
+pub fn tasks(db: &Database, id: &str, now: DateTime<Tz>) -> Result<Vec<Task>, String> {
+    let list = db.get_smart_list(id).map_err(|e| e.to_string())?.ok_or_else(|| format!("Smart list {} not found", id))?;
+    let mut tasks = db.find_tasks(&compile(&list, now)?).map_err(|e| e.to_string())?;
+    sort(&mut tasks, list.sort);
+    Ok(tasks)
+}
+
+/// Every smart list with the number of tasks it shows.
+pub fn counts(db: &Database, now: DateTime<Tz>) -> Result<Vec<SmartListCount>, String> {
+    let lists = db.get_smart_lists().map_err(|e| e.to_string())?;
+    lists
+        .into_iter()
+        .map(|list| {
+            let count = db.count_tasks(&compile(&list, now)?).map_err(|e| e.to_string())?;
+            Ok(SmartListCount { list, count })
+        })
+        .collect()
+}
@@ -0,0 +1,132 @@
+mod common;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use tasker_lib::backup::{self, BackupInfo, Retention};
+use tasker_lib::db::{Account, AccountTokens, Database};
+use tasker_lib::secrets;
+
+fn at(day: u32, hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 9, day, hour, 0, 0).unwrap()
+}
+
+fn ids(db: &Database) -> Vec<String> {
+    db.get_tasks().unwrap().into_iter().map(|t| t.id).collect()
+}
+
+#[test]
+fn backup_and_restore_round_trip() {
+    let (_dir, db) = common::temp_db();
+    db.add_task(common::task("kept")).unwrap();
+    let snapshot = backup::create(&db, at(1, 9)).unwrap();
+    assert!(snapshot.size > 0);
+
+    db.delete_task("kept").unwrap();
+    db.add_task(common::task("later")).unwrap();
+
+    let safety = backup::restore(&db, &snapshot.name, at(1, 10)).unwrap();
+    assert_eq!(ids(&db), ["kept"]);
+
+    // The data replaced by the restore can be brought back
+    let names: Vec<_> = backup::list(&db).unwrap().into_iter().map(|b| b.name).collect();
+    assert_eq!(names, [safety.name.clone(), snapshot.name]);
+    backup::restore(&db, &safety.name, at(1, 11)).unwrap();
+    assert_eq!(ids(&db), ["later"]);
+}
+
+#[test]
+fn restore_reopens_secrets_for_the_restored_key() {
+    let (_dir, db) = common::temp_db();
+    secrets::open(&db).unwrap();
+    db.upsert_account(&Account {
+        provider: "google".to_string(),
+        account_id: "acct".to_string(),
+        email: "acct@example.com".to_string(),
+        name: "Account".to_string(),
+        picture: None,
+    }).unwrap();
+    let tokens = AccountTokens { access_token: "access".to_string(), refresh_token: None, expires_at: None };
+    db.set_account_tokens("google", "acct", &tokens).unwrap();
+    let key_file = backup::create(&db, at(1, 9)).unwrap();
+
+    secrets::change_backend(&db, Some("correct horse")).unwrap();
+    let passphrase = backup::create(&db, at(1, 10)).unwrap();
+
+    // Sealed with the key file, while the passphrase key is in memory
+    backup::restore(&db, &key_file.name, at(1, 11)).unwrap();
+    assert_eq!(db.get_account_tokens("google", "acct").unwrap().unwrap().access_token, "access");
+    assert_eq!(secrets::status(&db).unwrap().backend, "keyfile");
+
+    // Sealed with a passphrase: locked until it is entered again
+    backup::restore(&db, &passphrase.name, at(1, 12)).unwrap();
+    assert!(!secrets::status(&db).unwrap().unlocked);
+    secrets::unlock(&db, "correct horse").unwrap();
+    assert_eq!(db.get_account_tokens("google", "acct").unwrap().unwrap().access_token, "access");
+}
+
+#[test]
+fn restore_refuses_corrupt_and_unknown_backups() {
+    let (_dir, db) = common::temp_db();
+    db.add_task(common::task("live")).unwrap();
+    let good = backup::create(&db, at(1, 9)).unwrap();
+
+    let corrupt = "tasks-20260902T090000000Z.db";
+    let mut bytes = std::fs::read(backup::dir(&db).join(&good.name)).unwrap();
+    bytes.truncate(bytes.len() / 2);
+    bytes[100..200].fill(0xff);
+    std::fs::write(backup::dir(&db).join(corrupt), bytes).unwrap();
+
+    let err = backup::restore(&db, corrupt, at(3, 9)).unwrap_err();
+    assert!(err.contains("integrity check"), "{}", err);
+    assert!(backup::restore(&db, "../tasks.db", at(3, 9)).unwrap_err().contains("not found"));
+
+    assert_eq!(ids(&db), ["live"]);
+    assert_eq!(backup::list(&db).unwrap().len(), 2, "failed restores take no safety backup");
+}
+
+#[test]
+fn retention_keeps_recent_days_and_weeks() {
+    // Two backups a day for the 40 days of 2026-08-23 ..= 2026-10-01, newest first
+    let newest = Utc.with_ymd_and_hms(2026, 10, 1, 18, 0, 0).unwrap();
+    let backups: Vec<BackupInfo> = (0..80)
+        .map(|i| {
+            let created_at = newest - Duration::hours(12 * i);
+            BackupInfo { name: created_at.to_rfc3339(), created_at, size: 0 }
+        })
+        .collect();
+
+    let keep = backup::retained(&backups, Retention { daily: 7, weekly: 4 });
+    let mut kept: Vec<_> = keep.iter().map(String::as_str).collect();
+    kept.sort_unstable();
+    assert_eq!(
+        kept,
+        [
+            // Newest of the two weeks before that (ISO weeks start on Monday)
+            "2026-09-13T18:00:00+00:00",
+            "2026-09-20T18:00:00+00:00",
+            // Seven days, Sunday 09-27 also being the newest of the previous week
+            "2026-09-25T18:00:00+00:00",
+            "2026-09-26T18:00:00+00:00",
+            "2026-09-27T18:00:00+00:00",
+            "2026-09-28T18:00:00+00:00",
+            "2026-09-29T18:00:00+00:00",
+            "2026-09-30T18:00:00+00:00",
+            "2026-10-01T18:00:00+00:00",
+        ]
+    );
+}
+
+#[test]
+fn scheduled_backups_run_daily_and_prune() {
+    let (_dir, db) = common::temp_db();
+    db.add_task(common::task("a")).unwrap();
+
+    assert!(backup::backup_if_due(&db, at(1, 9)).unwrap().is_some());
+    assert!(backup::backup_if_due(&db, at(2, 8)).unwrap().is_none(), "less than a day since the last one");
+    for day in 2..=20 {
+        backup::backup_if_due(&db, at(day, 9)).unwrap();
+    }
+
+    let days: Vec<u32> = backup::list(&db).unwrap().iter().map(|b| chrono::Datelike::day(&b.created_at)).collect();
+    // 7 daily, plus the newest of each earlier week
+    assert_eq!(days, [20, 19, 18, 17, 16, 15, 14, 13, 6]);
+}
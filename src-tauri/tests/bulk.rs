@@ -0,0 +1,130 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+use tasker_lib::bulk;
+use tasker_lib::changes::ChangeKind;
+use tasker_lib::db::{Database, Task, TaskPatch};
+use tasker_lib::reminders;
+
+fn task(id: &str) -> Task {
+    Task {
+        due_date: Some("2026-10-20".parse().unwrap()),
+        tags: vec!["old".to_string()],
+        ..common::task(id)
+    }
+}
+
+fn seed() -> (tempfile::TempDir, Database) {
+    let (dir, db) = common::temp_db();
+    for id in ["a", "b", "c"] {
+        db.add_task(task(id)).unwrap();
+    }
+    (dir, db)
+}
+
+fn ids(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+fn get(db: &Database, id: &str) -> Task {
+    db.get_task_by_id(id).unwrap().unwrap()
+}
+
+#[test]
+fn patch_only_touches_given_fields() {
+    let patch: TaskPatch = serde_json::from_str(r#"{"completed": true, "due_date": null, "priority": "High"}"#).unwrap();
+    let mut patched = Task { description: Some("Keep".to_string()), ..task("a") };
+    patch.apply(&mut patched);
+    assert!(patched.completed);
+    assert_eq!(patched.due_date, None);
+    assert_eq!(patched.priority, "High");
+    assert_eq!(patched.description.as_deref(), Some("Keep"));
+    assert_eq!(patched.category, "Work");
+
+    assert!(serde_json::from_str::<TaskPatch>(r#"{"complete": true}"#).is_err(), "typos are errors");
+}
+
+#[test]
+fn bulk_update_writes_once_and_undoes_in_one_step() {
+    let (_dir, db) = seed();
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&changes);
+    db.set_change_listener(move |change| sink.lock().unwrap().push(change.clone()));
+
+    let patch = TaskPatch { completed: Some(true), ..Default::default() };
+    let updated = bulk::update(&db, &ids(&["a", "c", "a"]), &patch).unwrap();
+    assert_eq!(updated.len(), 2, "duplicates are ignored");
+    assert!(get(&db, "a").completed && !get(&db, "b").completed && get(&db, "c").completed);
+
+    let changes: Vec<_> = changes.lock().unwrap().drain(..).collect();
+    assert_eq!(changes.len(), 1);
+    assert_eq!((changes[0].kind, changes[0].ids.clone()), (ChangeKind::Updated, ids(&["a", "c"])));
+
+    assert_eq!(db.last_undo().unwrap().unwrap().label, "Complete 2 tasks");
+    assert_eq!(db.undo().unwrap().unwrap().label, "Complete 2 tasks");
+    assert!(!get(&db, "a").completed && !get(&db, "c").completed);
+    assert!(db.undo().unwrap().is_none());
+}
+
+#[test]
+fn nothing_is_written_when_one_task_fails() {
+    let (_dir, db) = seed();
+    // Clearing the due date of a task with a start date is fine, moving it before the start is not
+    db.update_task(Task { start_date: Some("2026-10-10".parse().unwrap()), ..task("b") }).unwrap();
+    let patch = TaskPatch { due_date: Some(Some("2026-10-01".parse().unwrap())), ..Default::default() };
+    let err = bulk::update(&db, &ids(&["a", "b"]), &patch).unwrap_err();
+    assert!(err.starts_with("b:"), "{}", err);
+    assert_eq!(get(&db, "a").due_date, task("a").due_date);
+
+    assert_eq!(bulk::update(&db, &ids(&["a", "missing"]), &patch).unwrap_err(), "Task missing not found");
+    assert_eq!(bulk::update(&db, &[], &patch).unwrap_err(), "No tasks selected");
+    assert!(db.last_undo().unwrap().is_none());
+}
+
+#[test]
+fn move_tag_and_delete_with_undo() {
+    let (_dir, db) = seed();
+    bulk::move_to_list(&db, &ids(&["a", "b"]), " Home ").unwrap();
+    assert_eq!((get(&db, "a").category, get(&db, "c").category), ("Home".to_string(), "Work".to_string()));
+    assert!(bulk::move_to_list(&db, &ids(&["a"]), "  ").is_err());
+
+    bulk::tag(&db, &ids(&["a", "c"]), &ids(&["#urgent", "old"]), &ids(&["old"])).unwrap();
+    assert_eq!(get(&db, "a").tags, ["urgent", "old"], "add wins over remove");
+    bulk::tag(&db, &ids(&["a"]), &[], &ids(&["old"])).unwrap();
+    assert_eq!(get(&db, "a").tags, ["urgent"]);
+    assert!(bulk::tag(&db, &ids(&["a"]), &ids(&["two words"]), &[]).is_err());
+
+    let reminder = reminders::add(&db, "b", None, Some(30)).unwrap();
+    assert_eq!(bulk::delete(&db, &ids(&["b", "c"])).unwrap().len(), 2);
+    let remaining: Vec<_> = db.get_tasks().unwrap().into_iter().map(|t| t.id).collect();
+    assert_eq!(remaining, ["a"]);
+
+    assert_eq!(db.undo().unwrap().unwrap().label, "Delete 2 tasks");
+    let restored: Vec<_> = db.get_tasks().unwrap().into_iter().map(|t| t.id).collect();
+    assert_eq!(restored, ["a", "b", "c"], "deleted tasks return to their place");
+    assert_eq!(get(&db, "b").category, "Home");
+    assert_eq!(db.get_reminders("b").unwrap()[0].id, reminder.id);
+
+    assert_eq!(db.undo().unwrap().unwrap().label, "Tag 1 task");
+    assert_eq!(get(&db, "a").tags, ["urgent", "old"]);
+    assert_eq!(db.undo().unwrap().unwrap().label, "Tag 2 tasks");
+    assert_eq!((get(&db, "a").tags, get(&db, "c").tags), (ids(&["old"]), ids(&["old"])), "c was deleted and restored in between");
+}
+
+#[test]
+fn undo_keeps_tasks_edited_since() {
+    let (_dir, db) = seed();
+    let patch = TaskPatch { completed: Some(true), ..Default::default() };
+    bulk::update(&db, &ids(&["a", "b"]), &patch).unwrap();
+    bulk::delete(&db, &ids(&["c"])).unwrap();
+    db.update_task(Task { title: "Edited".to_string(), ..get(&db, "a") }).unwrap();
+    db.add_task(Task { title: "New c".to_string(), ..task("c") }).unwrap();
+
+    db.undo().unwrap().unwrap();
+    assert_eq!(get(&db, "c").title, "New c", "created again after the delete");
+    db.undo().unwrap().unwrap();
+    let a = get(&db, "a");
+    assert!(a.completed && a.title == "Edited", "edited after the bulk update");
+    assert!(!get(&db, "b").completed);
+    assert!(db.undo().unwrap().is_none());
+}
@@ -0,0 +1,425 @@
+mod common;
+
+use common::{MockResponse, MockServer, RecordedRequest};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tasker_lib::caldav;
+use tasker_lib::db::{Database, Task};
+
+const PRINCIPAL: &str = "/dav/principals/alice/";
+const HOME: &str = "/dav/calendars/alice/";
+const TASKS: &str = "/dav/calendars/alice/tasks/";
+const EVENTS: &str = "/dav/calendars/alice/events/";
+
+// In-memory CalDAV collection: every change bumps `version`, which doubles as ctag and sync token
+#[derive(Default)]
+struct Store {
+    // path -> (etag, calendar data)
+    resources: BTreeMap<String, (String, String)>,
+    version: u64,
+    changes: Vec<(u64, String)>,
+    // Reject every sync token, as after the server expired them
+    expire_tokens: bool,
+}
+
+impl Store {
+    fn bump(&mut self, path: &str) -> String {
+        self.version += 1;
+        self.changes.push((self.version, path.to_string()));
+        format!("\"{}\"", self.version)
+    }
+
+    fn put(&mut self, path: &str, data: String) -> String {
+        let etag = self.bump(path);
+        self.resources.insert(path.to_string(), (etag.clone(), data));
+        etag
+    }
+
+    fn delete(&mut self, path: &str) {
+        self.resources.remove(path);
+        self.bump(path);
+    }
+
+    fn token(&self) -> String {
+        format!("http://example.com/sync/{}", self.version)
+    }
+}
+
+fn vtodo(uid: &str, summary: &str, extra: &str) -> String {
+    format!(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VTODO\r\nUID:{}\r\nSUMMARY:{}\r\n{}END:VTODO\r\nEND:VCALENDAR\r\n",
+        uid, summary, extra
+    )
+}
+
+fn escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn multistatus(responses: &[String], sync_token: Option<String>) -> MockResponse {
+    let token = sync_token.map(|t| format!("<d:sync-token>{}</d:sync-token>", t)).unwrap_or_default();
+    MockResponse::text(
+        207,
+        format!(
+            r#"<?xml version="1.0"?><d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav" xmlns:cs="http://calendarserver.org/ns/">{}{}</d:multistatus>"#,
+            responses.concat(),
+            token
+        ),
+        "application/xml",
+    )
+}
+
+fn prop_response(href: &str, props: &str) -> String {
+    format!(
+        "<d:response><d:href>{}</d:href><d:propstat><d:prop>{}</d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+        href, props
+    )
+}
+
+fn body_hrefs(body: &str) -> Vec<String> {
+    body.split("<d:href>")
+        .skip(1)
+        .filter_map(|part| part.split_once("</d:href>").map(|(href, _)| href.to_string()))
+        .collect()
+}
+
+fn etag_matches(request: &RecordedRequest, current: Option<&String>) -> bool {
+    match (request.header("If-Match"), request.header("If-None-Match")) {
+        (Some(expected), _) => current.is_some_and(|etag| etag == expected),
+        (None, Some("*")) => current.is_none(),
+        _ => true,
+    }
+}
+
+fn handle(store: &Mutex<Store>, request: &RecordedRequest) -> MockResponse {
+    let mut store = store.lock().unwrap();
+    match (request.method.as_str(), request.path.as_str()) {
+        ("PROPFIND", "/dav/") => multistatus(
+            &[prop_response("/dav/", &format!("<d:current-user-principal><d:href>{}</d:href></d:current-user-principal>", PRINCIPAL))],
+            None,
+        ),
+        ("PROPFIND", PRINCIPAL) => multistatus(
+            &[prop_response(PRINCIPAL, &format!("<c:calendar-home-set><d:href>{}</d:href></c:calendar-home-set>", HOME))],
+            None,
+        ),
+        ("PROPFIND", HOME) => multistatus(
+            &[
+                prop_response(HOME, "<d:resourcetype><d:collection/></d:resourcetype>"),
+                prop_response(
+                    TASKS,
+                    &format!(
+                        "<d:resourcetype><d:collection/><c:calendar/></d:resourcetype><d:displayname>Tasks</d:displayname>\
+                         <c:supported-calendar-component-set><c:comp name=\"VTODO\"/></c:supported-calendar-component-set>\
+                         <cs:getctag>{}</cs:getctag><d:sync-token>{}</d:sync-token>",
+                        store.version,
+                        store.token()
+                    ),
+                ),
+                prop_response(
+                    EVENTS,
+                    "<d:resourcetype><d:collection/><c:calendar/></d:resourcetype><d:displayname>Events</d:displayname>\
+                     <c:supported-calendar-component-set><c:comp name=\"VEVENT\"/></c:supported-calendar-component-set>",
+                ),
+            ],
+            None,
+        ),
+        ("REPORT", TASKS) if request.body.contains("sync-collection") => {
+            let since = request
+                .body
+                .split("/sync/")
+                .nth(1)
+                .and_then(|rest| rest.split('<').next())
+                .and_then(|n| n.parse::<u64>().ok());
+            let Some(since) = since.filter(|_| !store.expire_tokens) else {
+                return MockResponse::text(403, r#"<d:error xmlns:d="DAV:"><d:valid-sync-token/></d:error>"#, "application/xml");
+            };
+            let mut paths: Vec<&String> = store.changes.iter().filter(|(v, _)| *v > since).map(|(_, p)| p).collect();
+            paths.sort();
+            paths.dedup();
+            let responses: Vec<String> = paths
+                .into_iter()
+                .map(|path| match store.resources.get(path) {
+                    Some((etag, _)) => prop_response(path, &format!("<d:getetag>{}</d:getetag>", escape(etag))),
+                    None => format!("<d:response><d:href>{}</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>", path),
+                })
+                .collect();
+            multistatus(&responses, Some(store.token()))
+        }
+        ("REPORT", TASKS) if request.body.contains("calendar-query") => {
+            let responses: Vec<String> = store
+                .resources
+                .iter()
+                .map(|(path, (etag, _))| prop_response(path, &format!("<d:getetag>{}</d:getetag>", escape(etag))))
+                .collect();
+            multistatus(&responses, None)
+        }
+        ("REPORT", TASKS) if request.body.contains("calendar-multiget") => {
+            let responses: Vec<String> = body_hrefs(&request.body)
+                .iter()
+                .filter_map(|path| {
+                    let (etag, data) = store.resources.get(path)?;
+                    Some(prop_response(
+                        path,
+                        &format!("<d:getetag>{}</d:getetag><c:calendar-data>{}</c:calendar-data>", escape(etag), escape(data)),
+                    ))
+                })
+                .collect();
+            multistatus(&responses, None)
+        }
+        ("PUT", path) if path.starts_with(TASKS) => {
+            if !etag_matches(request, store.resources.get(path).map(|(etag, _)| etag)) {
+                return MockResponse::empty(412);
+            }
+            let etag = store.put(path, request.body.clone());
+            MockResponse::empty(201).with_header("ETag", &etag)
+        }
+        ("DELETE", path) if path.starts_with(TASKS) => {
+            if !store.resources.contains_key(path) {
+                return MockResponse::empty(404);
+            }
+            if !etag_matches(request, store.resources.get(path).map(|(etag, _)| etag)) {
+                return MockResponse::empty(412);
+            }
+            store.delete(path);
+            MockResponse::empty(204)
+        }
+        _ => MockResponse::empty(404),
+    }
+}
+
+struct Fixture {
+    server: MockServer,
+    store: Arc<Mutex<Store>>,
+    _dir: tempfile::TempDir,
+    db: Database,
+    account_id: String,
+}
+
+fn path(uid: &str) -> String {
+    format!("{}{}.ics", TASKS, uid)
+}
+
+async fn connected(seed: &[(&str, String)]) -> Fixture {
+    let store = Arc::new(Mutex::new(Store::default()));
+    for (uid, data) in seed {
+        store.lock().unwrap().put(&path(uid), data.clone());
+    }
+    let handler_store = Arc::clone(&store);
+    let server = MockServer::start(move |request| handle(&handler_store, request));
+    let (dir, db) = common::temp_db();
+
+    let account = caldav::connect(&db, &format!("{}/dav/", server.base_url), "alice", "secret").await.unwrap();
+    Fixture { account_id: account.account_id, server, store, _dir: dir, db }
+}
+
+fn seed() -> Vec<(&'static str, String)> {
+    vec![
+        ("todo-1", vtodo("todo-1", "Buy milk", "CATEGORIES:Errands\r\nPRIORITY:1\r\n")),
+        ("todo-2", vtodo("todo-2", "Write report", "DUE;VALUE=DATE:20261020\r\n")),
+    ]
+}
+
+fn reports(fixture: &Fixture) -> Vec<RecordedRequest> {
+    fixture.server.requests().into_iter().filter(|r| r.method == "REPORT").collect()
+}
+
+#[tokio::test]
+async fn connect_discovers_calendar_home_and_todo_collections() {
+    let fixture = connected(&[]).await;
+    assert_eq!(fixture.account_id, format!("{}{}", fixture.server.base_url, PRINCIPAL));
+
+    let accounts = caldav::get_accounts(&fixture.db).unwrap();
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0].email, "alice");
+    assert_eq!(accounts[0].name, "127.0.0.1");
+
+    let collections = caldav::fetch_collections(&fixture.db, &fixture.account_id).await.unwrap();
+    assert_eq!(collections.len(), 1, "event-only calendars are left out");
+    assert_eq!(collections[0].href, format!("{}{}", fixture.server.base_url, TASKS));
+    assert_eq!(collections[0].display_name, "Tasks");
+
+    let propfinds = fixture.server.requests();
+    assert!(propfinds.iter().all(|r| r.header("Authorization") == Some("Basic YWxpY2U6c2VjcmV0")));
+    assert_eq!(fixture.server.requests_to(HOME)[0].header("Depth"), Some("1"));
+    assert_eq!(fixture.server.requests_to(PRINCIPAL)[0].header("Depth"), Some("0"));
+}
+
+#[tokio::test]
+async fn first_sync_imports_todos_and_unchanged_ctag_skips_reports() {
+    let fixture = connected(&seed()).await;
+
+    let summary = caldav::sync(&fixture.db, &fixture.account_id).await.unwrap();
+    assert_eq!((summary.added, summary.updated, summary.deleted), (2, 0, 0));
+
+    let milk = fixture.db.get_task_by_id("todo-1").unwrap().unwrap();
+    assert_eq!(milk.title, "Buy milk");
+    assert_eq!(milk.category, "Errands");
+    assert_eq!(milk.priority, "High");
+    let report = fixture.db.get_task_by_id("todo-2").unwrap().unwrap();
+    assert_eq!(report.category, "Tasks", "todos without CATEGORIES go to the collection's category");
+    assert_eq!(report.due_date, Some("2026-10-20".parse().unwrap()));
+
+    let mut linked = fixture.db.get_imported_task_ids("caldav", &fixture.account_id).unwrap();
+    linked.sort();
+    assert_eq!(linked, ["todo-1", "todo-2"]);
+
+    let before = reports(&fixture).len();
+    let summary = caldav::sync(&fixture.db, &fixture.account_id).await.unwrap();
+    assert_eq!((summary.added, summary.updated, summary.pushed), (0, 0, 0));
+    assert_eq!(reports(&fixture).len(), before, "unchanged ctag needs no REPORT");
+}
+
+#[tokio::test]
+async fn server_changes_arrive_through_the_sync_token() {
+    let fixture = connected(&seed()).await;
+    caldav::sync(&fixture.db, &fixture.account_id).await.unwrap();
+
+    let mut milk = fixture.db.get_task_by_id("todo-1").unwrap().unwrap();
+    milk.category = "Groceries".to_string();
+    fixture.db.update_task(milk.clone()).unwrap();
+    // Category is local, so this edit must not be uploaded either
+    {
+        let mut store = fixture.store.lock().unwrap();
+        store.put(&path("todo-1"), vtodo("todo-1", "Buy oat milk", "CATEGORIES:Errands\r\n"));
+        store.delete(&path("todo-2"));
+        store.put(&path("todo-3"), vtodo("todo-3", "New on server", ""));
+    }
+
+    let summary = caldav::sync(&fixture.db, &fixture.account_id).await.unwrap();
+    assert_eq!((summary.added, summary.updated, summary.deleted, summary.pushed), (1, 1, 1, 0));
+
+    let milk = fixture.db.get_task_by_id("todo-1").unwrap().unwrap();
+    assert_eq!(milk.title, "Buy oat milk");
+    assert_eq!(milk.category, "Groceries");
+    assert!(fixture.db.get_task_by_id("todo-2").unwrap().is_none());
+    assert!(fixture.db.get_task_by_id("todo-3").unwrap().is_some());
+
+    let sync_report = reports(&fixture).into_iter().find(|r| r.body.contains("sync-collection")).unwrap();
+    assert!(sync_report.body.contains("http://example.com/sync/2"));
+    assert_eq!(
+        reports(&fixture).iter().filter(|r| r.body.contains("calendar-query")).count(),
+        1,
+        "only the first sync lists every member"
+    );
+}
+
+#[tokio::test]
+async fn local_edits_and_deletions_are_pushed_with_etags() {
+    let fixture = connected(&seed()).await;
+    caldav::sync(&fixture.db, &fixture.account_id).await.unwrap();
+    let (etag_1, etag_2) = {
+        let store = fixture.store.lock().unwrap();
+        (store.resources[&path("todo-1")].0.clone(), store.resources[&path("todo-2")].0.clone())
+    };
+
+    let milk = fixture.db.get_task_by_id("todo-1").unwrap().unwrap();
+    fixture.db.update_task(Task { title: "Buy bread".to_string(), completed: true, ..milk }).unwrap();
+    fixture.db.delete_task("todo-2").unwrap();
+
+    let summary = caldav::sync(&fixture.db, &fixture.account_id).await.unwrap();
+    assert_eq!((summary.pushed, summary.conflicts, summary.updated), (2, 0, 0));
+
+    let put = fixture.server.requests_to(&path("todo-1")).into_iter().find(|r| r.method == "PUT").unwrap();
+    assert_eq!(put.header("If-Match"), Some(etag_1.as_str()));
+    assert!(put.body.contains("SUMMARY:Buy bread\r\n"));
+    assert!(put.body.contains("STATUS:COMPLETED\r\n"));
+    let delete = fixture.server.requests_to(&path("todo-2")).into_iter().find(|r| r.method == "DELETE").unwrap();
+    assert_eq!(delete.header("If-Match"), Some(etag_2.as_str()));
+
+    {
+        let store = fixture.store.lock().unwrap();
+        assert!(store.resources[&path("todo-1")].1.contains("SUMMARY:Buy bread"));
+        assert!(!store.resources.contains_key(&path("todo-2")));
+    }
+
+    // Our own uploads come back through the sync token with known etags
+    let multigets = |fixture: &Fixture| reports(fixture).iter().filter(|r| r.body.contains("calendar-multiget")).count();
+    let before = multigets(&fixture);
+    let summary = caldav::sync(&fixture.db, &fixture.account_id).await.unwrap();
+    assert_eq!((summary.pushed, summary.updated, summary.added, summary.deleted), (0, 0, 0, 0));
+    assert_eq!(multigets(&fixture), before, "nothing is downloaded again");
+}
+
+#[tokio::test]
+async fn conflicting_edits_keep_the_server_copy() {
+    let fixture = connected(&seed()).await;
+    caldav::sync(&fixture.db, &fixture.account_id).await.unwrap();
+
+    let milk = fixture.db.get_task_by_id("todo-1").unwrap().unwrap();
+    fixture.db.update_task(Task { title: "Local title".to_string(), ..milk }).unwrap();
+    fixture.store.lock().unwrap().put(&path("todo-1"), vtodo("todo-1", "Server title", ""));
+
+    let summary = caldav::sync(&fixture.db, &fixture.account_id).await.unwrap();
+    assert_eq!((summary.conflicts, summary.pushed, summary.updated), (1, 0, 1));
+    assert_eq!(fixture.db.get_task_by_id("todo-1").unwrap().unwrap().title, "Server title");
+    assert!(fixture.store.lock().unwrap().resources[&path("todo-1")].1.contains("Server title"));
+}
+
+#[tokio::test]
+async fn rejected_sync_token_falls_back_to_comparing_etags() {
+    let fixture = connected(&seed()).await;
+    caldav::sync(&fixture.db, &fixture.account_id).await.unwrap();
+    {
+        let mut store = fixture.store.lock().unwrap();
+        store.expire_tokens = true;
+        store.put(&path("todo-1"), vtodo("todo-1", "Changed", ""));
+        store.delete(&path("todo-2"));
+    }
+
+    let summary = caldav::sync(&fixture.db, &fixture.account_id).await.unwrap();
+    assert_eq!((summary.updated, summary.deleted), (1, 1));
+    assert_eq!(fixture.db.get_task_by_id("todo-1").unwrap().unwrap().title, "Changed");
+    assert!(fixture.db.get_task_by_id("todo-2").unwrap().is_none());
+    assert_eq!(reports(&fixture).iter().filter(|r| r.body.contains("calendar-query")).count(), 2);
+
+    // Only the changed member is downloaded again
+    let multiget = reports(&fixture).into_iter().rev().find(|r| r.body.contains("calendar-multiget")).unwrap();
+    assert_eq!(body_hrefs(&multiget.body), [path("todo-1")]);
+}
+
+#[tokio::test]
+async fn local_tasks_can_be_added_to_a_collection() {
+    let fixture = connected(&[]).await;
+    fixture.db.add_task(Task {
+        id: "local-1".to_string(),
+        title: "Made here".to_string(),
+        priority: "Low".to_string(),
+        category: "Work".to_string(),
+        ..Default::default()
+    }).unwrap();
+    let collection = format!("{}{}", fixture.server.base_url, TASKS);
+    caldav::add_task(&fixture.db, &fixture.account_id, &collection, "local-1").unwrap();
+
+    let summary = caldav::sync(&fixture.db, &fixture.account_id).await.unwrap();
+    assert_eq!((summary.pushed, summary.added, summary.updated), (1, 0, 0));
+
+    let put = fixture.server.requests_to(&path("local-1")).into_iter().find(|r| r.method == "PUT").unwrap();
+    assert_eq!(put.header("If-None-Match"), Some("*"));
+    assert!(fixture.store.lock().unwrap().resources[&path("local-1")].1.contains("SUMMARY:Made here"));
+    assert_eq!(fixture.db.get_task_by_id("local-1").unwrap().unwrap().category, "Work");
+}
+
+#[tokio::test]
+async fn server_todos_never_overwrite_unrelated_local_tasks() {
+    let fixture = connected(&seed()).await;
+    fixture.db.add_task(Task { title: "Imported elsewhere".to_string(), ..common::task("todo-1") }).unwrap();
+
+    let summary = caldav::sync(&fixture.db, &fixture.account_id).await.unwrap();
+    assert_eq!((summary.added, summary.updated), (2, 0));
+    assert_eq!(fixture.db.get_task_by_id("todo-1").unwrap().unwrap().title, "Imported elsewhere");
+    let linked = fixture.db.get_imported_task_ids("caldav", &fixture.account_id).unwrap();
+    assert!(!linked.contains(&"todo-1".to_string()));
+    let milk = fixture.db.get_tasks().unwrap().into_iter().find(|t| t.title == "Buy milk").unwrap();
+    assert!(linked.contains(&milk.id));
+
+    // Edits of the renamed copy keep the server's UID
+    fixture.db.update_task(Task { title: "Buy bread".to_string(), ..milk.clone() }).unwrap();
+    fixture.store.lock().unwrap().put(&path("todo-2"), vtodo("todo-2", "Report v2", ""));
+    let summary = caldav::sync(&fixture.db, &fixture.account_id).await.unwrap();
+    assert_eq!((summary.pushed, summary.updated, summary.added), (1, 1, 0));
+    let put = fixture.server.requests_to(&path("todo-1")).into_iter().find(|r| r.method == "PUT").unwrap();
+    assert!(put.body.contains("UID:todo-1\r\n"));
+    assert!(put.body.contains("SUMMARY:Buy bread\r\n"));
+    assert_eq!(fixture.db.get_task_by_id("todo-1").unwrap().unwrap().title, "Imported elsewhere");
+    assert_eq!(fixture.db.get_task_by_id(&milk.id).unwrap().unwrap().title, "Buy bread");
+}
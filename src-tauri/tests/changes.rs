@@ -0,0 +1,126 @@
+mod common;
+
+use rusqlite::Connection;
+use chrono::Utc;
+use std::sync::{Arc, Mutex};
+use tasker_lib::backup;
+use tasker_lib::changes::{ChangeKind, ExternalChangeWatcher, TaskChange};
+use tasker_lib::data::{self, DataFormat, ImportMode};
+use tasker_lib::db::{Database, Task};
+
+fn record(db: &Database) -> Arc<Mutex<Vec<TaskChange>>> {
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&changes);
+    db.set_change_listener(move |change| sink.lock().unwrap().push(change.clone()));
+    changes
+}
+
+fn take(changes: &Mutex<Vec<TaskChange>>) -> Vec<(ChangeKind, Vec<String>)> {
+    changes.lock().unwrap().drain(..).map(|c| (c.kind, c.ids)).collect()
+}
+
+fn ids(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+#[test]
+fn every_task_write_reports_a_change() {
+    let (_dir, db) = common::temp_db();
+    let changes = record(&db);
+
+    // Clones share the listener
+    db.clone().add_task(common::task("a")).unwrap();
+    db.add_task(common::task("b")).unwrap();
+    db.update_task(Task { title: "Renamed".to_string(), ..common::task("a") }).unwrap();
+    db.update_task_order(ids(&["b", "a"])).unwrap();
+    db.delete_task("b").unwrap();
+    assert_eq!(
+        take(&changes),
+        [
+            (ChangeKind::Added, ids(&["a"])),
+            (ChangeKind::Added, ids(&["b"])),
+            (ChangeKind::Updated, ids(&["a"])),
+            (ChangeKind::Reordered, ids(&["b", "a"])),
+            (ChangeKind::Deleted, ids(&["b"])),
+        ]
+    );
+
+    // Failed writes report nothing
+    assert!(db.add_task(Task { start_date: Some("2026-10-20".parse().unwrap()), due_date: Some("2026-10-01".parse().unwrap()), ..common::task("bad") }).is_err());
+    assert!(take(&changes).is_empty());
+}
+
+#[test]
+fn an_import_reports_one_change_per_kind() {
+    let (_dir, source) = common::temp_db();
+    source.add_task(common::task("a")).unwrap();
+    source.add_task(common::task("new")).unwrap();
+    let json = data::export(&source, DataFormat::Json).unwrap();
+
+    let (_dir, db) = common::temp_db();
+    db.add_task(common::task("a")).unwrap();
+    db.add_task(common::task("gone")).unwrap();
+    let changes = record(&db);
+    data::import(&db, &json, DataFormat::Json, ImportMode::Replace).unwrap();
+    assert_eq!(
+        take(&changes),
+        [(ChangeKind::Deleted, ids(&["gone"])), (ChangeKind::Updated, ids(&["a"])), (ChangeKind::Added, ids(&["new"]))]
+    );
+}
+
+#[test]
+fn watcher_reports_writes_from_other_processes_only() {
+    let (_dir, db) = common::temp_db();
+    db.add_task(common::task("a")).unwrap();
+    let changes = record(&db);
+    let mut watcher = ExternalChangeWatcher::new(db.clone()).unwrap();
+    assert!(!watcher.check().unwrap());
+
+    // The app's own writes are already reported
+    db.add_task(common::task("b")).unwrap();
+    assert!(!watcher.check().unwrap());
+    take(&changes);
+
+    // Writes that don't touch tasks are ignored
+    db.set_setting("theme", "dark").unwrap();
+    assert!(!watcher.check().unwrap());
+
+    let other = Connection::open(db.path()).unwrap();
+    other.execute("UPDATE tasks SET title = 'Edited elsewhere' WHERE id = 'a'", []).unwrap();
+    assert!(watcher.check().unwrap());
+    assert!(!watcher.check().unwrap(), "reported once");
+    assert_eq!(take(&changes), [(ChangeKind::External, Vec::new())]);
+
+    // Another process's write isn't hidden by one of the app's own in the same poll
+    other.execute("DELETE FROM tasks WHERE id = 'a'", []).unwrap();
+    db.update_task(Task { title: "Renamed".to_string(), ..common::task("b") }).unwrap();
+    assert!(watcher.check().unwrap());
+    assert_eq!(take(&changes), [(ChangeKind::Updated, ids(&["b"])), (ChangeKind::External, Vec::new())]);
+
+    // Dependencies count as task changes
+    db.add_task(common::task("c")).unwrap();
+    assert!(!watcher.check().unwrap());
+    other.execute("INSERT INTO task_dependencies (task_id, blocked_by) VALUES ('b', 'c')", []).unwrap();
+    assert!(watcher.check().unwrap());
+}
+
+#[test]
+fn a_restore_is_reported_once() {
+    let (_dir, db) = common::temp_db();
+    db.add_task(common::task("a")).unwrap();
+    let snapshot = backup::create(&db, Utc::now()).unwrap();
+    // The live counters move away from the backup's, by the app and by another process
+    db.add_task(common::task("b")).unwrap();
+    let other = Connection::open(db.path()).unwrap();
+    other.execute("DELETE FROM tasks WHERE id = 'a'", []).unwrap();
+    let changes = record(&db);
+    let mut watcher = ExternalChangeWatcher::new(db.clone()).unwrap();
+
+    backup::restore(&db, &snapshot.name, Utc::now()).unwrap();
+    assert!(!watcher.check().unwrap(), "the restore already reported itself");
+    assert_eq!(take(&changes), [(ChangeKind::External, Vec::new())]);
+
+    // Later writes from another process are still noticed
+    other.execute("DELETE FROM tasks WHERE id = 'a'", []).unwrap();
+    assert!(watcher.check().unwrap());
+}
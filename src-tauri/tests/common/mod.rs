@@ -0,0 +1,148 @@
+// Minimal HTTP stand-in used by the integration tests to emulate remote APIs.
+#![allow(dead_code)]
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use tasker_lib::db::{Database, Task};
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn query_param(&self, key: &str) -> Option<&str> {
+        self.query.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn bearer_token(&self) -> Option<&str> {
+        self.header("Authorization").and_then(|v| v.strip_prefix("Bearer "))
+    }
+
+    pub fn form_param(&self, key: &str) -> Option<String> {
+        url::form_urlencoded::parse(self.body.as_bytes())
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.into_owned())
+    }
+}
+
+pub struct MockResponse {
+    pub status: u16,
+    pub body: String,
+    pub content_type: &'static str,
+    pub headers: Vec<(String, String)>,
+}
+
+impl MockResponse {
+    pub fn json(status: u16, body: serde_json::Value) -> Self {
+        Self { status, body: body.to_string(), content_type: "application/json", headers: Vec::new() }
+    }
+
+    pub fn text(status: u16, body: impl Into<String>, content_type: &'static str) -> Self {
+        Self { status, body: body.into(), content_type, headers: Vec::new() }
+    }
+
+    pub fn empty(status: u16) -> Self {
+        Self { status, body: String::new(), content_type: "text/plain", headers: Vec::new() }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+pub struct MockServer {
+    pub base_url: String,
+    server: Arc<tiny_http::Server>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockServer {
+    pub fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&RecordedRequest) -> MockResponse + Send + 'static,
+    {
+        let server = Arc::new(tiny_http::Server::http("127.0.0.1:0").expect("mock server"));
+        let port = server.server_addr().to_ip().expect("tcp listener").port();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let thread_server = Arc::clone(&server);
+        let thread_requests = Arc::clone(&requests);
+        thread::spawn(move || {
+            for mut request in thread_server.incoming_requests() {
+                let url = url::Url::parse(&format!("http://localhost{}", request.url())).unwrap();
+                let mut body = String::new();
+                let _ = request.as_reader().read_to_string(&mut body);
+                let recorded = RecordedRequest {
+                    method: request.method().as_str().to_string(),
+                    path: url.path().to_string(),
+                    query: url.query_pairs().map(|(k, v)| (k.into_owned(), v.into_owned())).collect(),
+                    headers: request
+                        .headers()
+                        .iter()
+                        .map(|h| (h.field.as_str().to_string(), h.value.as_str().to_string()))
+                        .collect(),
+                    body,
+                };
+
+                let response = handler(&recorded);
+                thread_requests.lock().unwrap().push(recorded);
+
+                let header = tiny_http::Header::from_bytes(&b"Content-Type"[..], response.content_type.as_bytes()).unwrap();
+                let mut reply = tiny_http::Response::from_string(response.body)
+                    .with_status_code(response.status)
+                    .with_header(header);
+                for (name, value) in &response.headers {
+                    reply.add_header(tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap());
+                }
+                let _ = request.respond(reply);
+            }
+        });
+
+        Self { base_url: format!("http://127.0.0.1:{}", port), server, requests }
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn requests_to(&self, path: &str) -> Vec<RecordedRequest> {
+        self.requests().into_iter().filter(|r| r.path == path).collect()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+pub fn temp_db() -> (tempfile::TempDir, Database) {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let db = Database::from_path(dir.path().join("tasks.db").to_str().unwrap());
+    db.init().expect("init db");
+    (dir, db)
+}
+
+/// Open "Task <id>" in the Work list; tests override what they care about with `..task(id)`.
+pub fn task(id: &str) -> Task {
+    Task {
+        id: id.to_string(),
+        title: format!("Task {}", id),
+        category: "Work".to_string(),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use tasker_lib::data::{self, DataFormat, ImportMode};
+use tasker_lib::db::{Database, Task};
+
+fn task(id: &str, title: &str) -> Task {
+    Task { title: title.to_string(), ..common::task(id) }
+}
+
+fn seed(db: &Database) {
+    db.add_task(Task {
+        description: Some("Line one\nLine \"two\", with comma".to_string()),
+        due_date: Some("2026-10-21".parse().unwrap()),
+        due_time: Some("17:30".parse().unwrap()),
+        timezone: Some("Asia/Seoul".to_string()),
+        recurrence: Some("FREQ=WEEKLY;BYDAY=MO".to_string()),
+        tags: vec!["q4".to_string(), "집중".to_string()],
+        ..task("a", "Report")
+    })
+    .unwrap();
+    db.add_task(Task { parent_id: Some("a".to_string()), completed: true, ..task("b", "Draft") }).unwrap();
+    db.set_setting("ics_feed_port", "18080").unwrap();
+    db.set_setting("ics_feed_token", "abc123").unwrap();
+    db.set_setting("google_client_secret", "shh").unwrap();
+    db.set_setting("secrets_salt", "salt").unwrap();
+    // Only the listed secrets are left out, not every key that mentions one
+    db.set_setting("list_category:secret-project", "Side").unwrap();
+}
+
+fn titles(db: &Database) -> Vec<String> {
+    db.get_tasks().unwrap().into_iter().map(|t| t.title).collect()
+}
+
+#[test]
+fn json_round_trip_moves_everything_but_secrets() {
+    let (_dir, source) = common::temp_db();
+    seed(&source);
+    let json = data::export(&source, DataFormat::Json).unwrap();
+    assert!(!json.contains("abc123") && !json.contains("shh") && !json.contains("secrets_salt"));
+
+    let (_dir, target) = common::temp_db();
+    let summary = data::import(&target, &json, DataFormat::Json, ImportMode::Merge).unwrap();
+    assert_eq!((summary.added, summary.updated, summary.removed, summary.settings), (2, 0, 0, 2));
+
+    let tasks = target.get_tasks().unwrap();
+    let original = source.get_tasks().unwrap();
+    assert_eq!(serde_json::to_value(&tasks).unwrap(), serde_json::to_value(&original).unwrap());
+    assert_eq!(target.get_setting("ics_feed_port").unwrap().as_deref(), Some("18080"));
+    assert_eq!(target.get_setting("list_category:secret-project").unwrap().as_deref(), Some("Side"));
+    assert_eq!(target.get_setting("ics_feed_token").unwrap(), None);
+}
+
+#[test]
+fn csv_round_trip_keeps_task_fields() {
+    let (_dir, source) = common::temp_db();
+    seed(&source);
+    let csv = data::export(&source, DataFormat::Csv).unwrap();
+    assert!(csv.starts_with("id,title,description,completed,priority,category,due_date,due_time,timezone,"));
+
+    let (_dir, target) = common::temp_db();
+    let summary = data::import(&target, &csv, DataFormat::Csv, ImportMode::Merge).unwrap();
+    assert_eq!((summary.added, summary.settings), (2, 0));
+    assert_eq!(
+        serde_json::to_value(target.get_tasks().unwrap()).unwrap(),
+        serde_json::to_value(source.get_tasks().unwrap()).unwrap()
+    );
+}
+
+#[test]
+fn merge_updates_known_tasks_and_replace_removes_the_rest() {
+    let (_dir, source) = common::temp_db();
+    seed(&source);
+    let json = data::export(&source, DataFormat::Json).unwrap();
+
+    let (_dir, db) = common::temp_db();
+    db.add_task(task("local", "Local only")).unwrap();
+    db.add_task(task("a", "Old title")).unwrap();
+
+    let summary = data::import(&db, &json, DataFormat::Json, ImportMode::Merge).unwrap();
+    assert_eq!((summary.added, summary.updated, summary.removed), (1, 1, 0));
+    // Updated tasks keep their place, new ones go to the end
+    assert_eq!(titles(&db), ["Local only", "Report", "Draft"]);
+
+    let summary = data::import(&db, &json, DataFormat::Json, ImportMode::Replace).unwrap();
+    assert_eq!((summary.added, summary.updated, summary.removed), (0, 2, 1));
+    assert_eq!(titles(&db), ["Report", "Draft"]);
+}
+
+#[test]
+fn rejects_unknown_versions_and_invalid_files_without_writing() {
+    let (_dir, db) = common::temp_db();
+    db.add_task(task("keep", "Keep me")).unwrap();
+
+    let newer = r#"{"app":"tasker","version":2,"exported_at":"2026-10-18T00:00:00Z","tasks":[]}"#;
+    let err = data::import(&db, newer, DataFormat::Json, ImportMode::Replace).unwrap_err();
+    assert!(err.contains("Unsupported data version 2"), "{}", err);
+
+    let foreign = r#"{"version":1,"tasks":[]}"#;
+    assert_eq!(data::import(&db, foreign, DataFormat::Json, ImportMode::Replace).unwrap_err(), "Not a tasker data export");
+
+    // The second task is invalid, so the first must not be written either
+    let bad_csv = "id,title,description,completed,priority,category,due_date,due_time,timezone,start_date,recurrence,parent_id,position\n\
+        new,New,,false,Low,Work,,,,,,,0\n\
+        bad,Bad,,false,Low,Work,2026-10-01,,,2026-10-05,,,1\n";
+    assert!(data::import(&db, bad_csv, DataFormat::Csv, ImportMode::Replace).is_err());
+
+    let duplicate = "id,title,description,completed,priority,category,due_date,due_time,timezone,start_date,recurrence,parent_id,position\n\
+        x,One,,false,Low,Work,,,,,,,0\n\
+        x,Two,,false,Low,Work,,,,,,,1\n";
+    assert_eq!(
+        data::import(&db, duplicate, DataFormat::Csv, ImportMode::Merge).unwrap_err(),
+        "Task x appears more than once"
+    );
+    assert_eq!(titles(&db), ["Keep me"]);
+}
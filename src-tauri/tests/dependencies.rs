@@ -0,0 +1,125 @@
+mod common;
+
+use chrono::{DateTime, TimeZone};
+use chrono_tz::Tz;
+use tasker_lib::db::{Database, Dependency, Task};
+use tasker_lib::dependencies::{self, UnblockTracker};
+use tasker_lib::{bulk, query};
+
+fn now() -> DateTime<Tz> {
+    Tz::UTC.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap()
+}
+
+fn seed() -> (tempfile::TempDir, Database) {
+    let (dir, db) = common::temp_db();
+    for id in ["design", "build", "test", "deploy"] {
+        db.add_task(common::task(id)).unwrap();
+    }
+    (dir, db)
+}
+
+fn get(db: &Database, id: &str) -> Task {
+    db.get_task_by_id(id).unwrap().unwrap()
+}
+
+fn complete(db: &Database, id: &str) {
+    db.update_task(Task { completed: true, ..get(db, id) }).unwrap();
+}
+
+fn ids(tasks: &[Task]) -> Vec<&str> {
+    tasks.iter().map(|t| t.id.as_str()).collect()
+}
+
+fn edge(task_id: &str, blocked_by: &str) -> Dependency {
+    Dependency { task_id: task_id.to_string(), blocked_by: blocked_by.to_string() }
+}
+
+#[test]
+fn blocked_until_every_blocker_is_done() {
+    let (_dir, db) = seed();
+    dependencies::link(&db, "deploy", "test").unwrap();
+    dependencies::link(&db, "deploy", "build").unwrap();
+    dependencies::link(&db, "deploy", "build").unwrap();
+    assert_eq!(db.get_dependencies().unwrap(), [edge("deploy", "build"), edge("deploy", "test")], "linking twice is a no-op");
+    assert_eq!(ids(&db.get_dependents("build").unwrap()), ["deploy"]);
+
+    assert!(get(&db, "deploy").blocked);
+    assert!(!get(&db, "build").blocked);
+    let search = |q: &str| ids(&query::search(&db, q, now()).unwrap()).join(",");
+    assert_eq!(search("is:blocked"), "deploy");
+    assert_eq!(search("is:open -is:blocked"), "design,build,test");
+
+    complete(&db, "build");
+    assert!(get(&db, "deploy").blocked, "test is still open");
+    assert_eq!(ids(&db.get_blockers("deploy").unwrap()), ["test", "build"], "open blockers first");
+    complete(&db, "test");
+    assert!(!get(&db, "deploy").blocked);
+    assert!(db.get_tasks().unwrap().iter().all(|t| !t.blocked));
+
+    db.update_task(Task { completed: false, ..get(&db, "test") }).unwrap();
+    assert!(get(&db, "deploy").blocked, "reopening a blocker blocks again");
+    dependencies::unlink(&db, "deploy", "test").unwrap();
+    assert!(!get(&db, "deploy").blocked);
+}
+
+#[test]
+fn cycles_are_rejected() {
+    let (_dir, db) = seed();
+    assert_eq!(dependencies::link(&db, "build", "build").unwrap_err(), "A task can't depend on itself");
+    dependencies::link(&db, "build", "design").unwrap();
+    dependencies::link(&db, "test", "build").unwrap();
+    dependencies::link(&db, "deploy", "test").unwrap();
+
+    let err = dependencies::link(&db, "design", "deploy").unwrap_err();
+    assert_eq!(err, "Circular dependency: Task design → Task deploy → Task test → Task build → Task design");
+    assert_eq!(dependencies::link(&db, "build", "missing").unwrap_err(), "Task missing not found");
+    assert_eq!(db.get_dependencies().unwrap().len(), 3);
+
+    // Diamonds are fine, only loops are not
+    dependencies::link(&db, "deploy", "design").unwrap();
+    let edges = db.get_dependencies().unwrap();
+    assert_eq!(dependencies::path(&edges, "deploy", "design").unwrap(), ["deploy", "design"], "shortest chain");
+    assert_eq!(dependencies::path(&edges, "design", "deploy"), None);
+}
+
+#[test]
+fn tracker_reports_tasks_whose_last_blocker_was_completed() {
+    let (_dir, db) = seed();
+    dependencies::link(&db, "deploy", "test").unwrap();
+    dependencies::link(&db, "deploy", "build").unwrap();
+    dependencies::link(&db, "design", "build").unwrap();
+    let mut tracker = UnblockTracker::new(&db).unwrap();
+
+    complete(&db, "test");
+    assert!(tracker.check(&db).unwrap().is_empty());
+    complete(&db, "build");
+    let unblocked = tracker.check(&db).unwrap();
+    let ready: Vec<_> = unblocked.iter().map(|u| (u.task.id.as_str(), u.blockers.len())).collect();
+    assert_eq!(ready, [("deploy", 2), ("design", 1)]);
+    assert_eq!(dependencies::message(&unblocked[1]), "Ready to start · Task build is done");
+    assert!(tracker.check(&db).unwrap().is_empty(), "reported once");
+
+    // Removing the link or the blocker unblocks too, but nothing was completed
+    db.update_task(Task { completed: false, ..get(&db, "build") }).unwrap();
+    tracker.check(&db).unwrap();
+    dependencies::unlink(&db, "design", "build").unwrap();
+    db.delete_task("build").unwrap();
+    assert!(tracker.check(&db).unwrap().is_empty());
+}
+
+#[test]
+fn deleting_a_task_drops_its_links_until_undo() {
+    let (_dir, db) = seed();
+    dependencies::link(&db, "deploy", "test").unwrap();
+    dependencies::link(&db, "test", "build").unwrap();
+    bulk::delete(&db, &["test".to_string()]).unwrap();
+    assert!(db.get_dependencies().unwrap().is_empty());
+    assert!(!get(&db, "deploy").blocked);
+
+    db.undo().unwrap().unwrap();
+    assert_eq!(db.get_dependencies().unwrap(), [edge("deploy", "test"), edge("test", "build")]);
+    assert!(get(&db, "deploy").blocked);
+
+    db.delete_task("build").unwrap();
+    assert_eq!(db.get_dependencies().unwrap(), [edge("deploy", "test")]);
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use tasker_lib::db::{Database, Task};
+use tasker_lib::feed::{self, FeedQuery, FeedServer};
+
+fn task(id: &str, category: &str, due: Option<&str>) -> Task {
+    Task {
+        category: category.to_string(),
+        due_date: due.map(|d| d.parse().unwrap()),
+        ..common::task(id)
+    }
+}
+
+fn seed(db: &Database) {
+    db.add_task(task("work", "Work", Some("2026-10-20"))).unwrap();
+    db.add_task(Task {
+        due_time: Some("17:30".parse().unwrap()),
+        timezone: Some("Asia/Seoul".to_string()),
+        ..task("home", "Home", Some("2026-10-21"))
+    })
+    .unwrap();
+    db.add_task(Task { completed: true, ..task("done", "Work", Some("2026-10-19")) }).unwrap();
+    db.add_task(task("someday", "Work", None)).unwrap();
+}
+
+fn feed_url(db: &Database, server: &FeedServer) -> String {
+    feed::save_settings(db, true, server.port()).unwrap();
+    feed::status(db, Some(server)).unwrap().url.unwrap()
+}
+
+#[test]
+fn renders_deadlines_as_events() {
+    let (_dir, db) = common::temp_db();
+    seed(&db);
+
+    let ics = feed::render(&db.get_tasks().unwrap(), &FeedQuery::default());
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2, "completed and undated tasks are left out");
+    for line in [
+        "UID:work",
+        "DTSTART;VALUE=DATE:20261020",
+        "DTEND;VALUE=DATE:20261021",
+        "DTSTART;TZID=Asia/Seoul:20261021T173000",
+        "DURATION:PT30M",
+        "TRANSP:TRANSPARENT",
+    ] {
+        assert!(ics.contains(&format!("{}\r\n", line)), "missing {:?} in\n{}", line, ics);
+    }
+}
+
+#[test]
+fn query_filters_by_category_and_completion() {
+    let (_dir, db) = common::temp_db();
+    seed(&db);
+    let tasks = db.get_tasks().unwrap();
+
+    let ics = feed::render(&tasks, &FeedQuery::parse("list=work&completed=true"));
+    assert!(ics.contains("UID:work\r\n"));
+    assert!(ics.contains("UID:done\r\n"));
+    assert!(!ics.contains("UID:home\r\n"));
+
+    let ics = feed::render(&tasks, &FeedQuery::parse("category=Home&category=Errands&format=todo"));
+    assert_eq!(ics.matches("BEGIN:VTODO").count(), 1);
+    assert!(ics.contains("DUE;TZID=Asia/Seoul:20261021T173000\r\n"));
+}
+
+#[test]
+fn query_filters_by_tag() {
+    let (_dir, db) = common::temp_db();
+    seed(&db);
+    db.add_task(Task { tags: vec!["Urgent".to_string(), "client".to_string()], ..task("tagged", "Home", Some("2026-10-22")) }).unwrap();
+    db.add_task(Task { tags: vec!["later".to_string()], ..task("other", "Work", Some("2026-10-23")) }).unwrap();
+    let tasks = db.get_tasks().unwrap();
+
+    let ics = feed::render(&tasks, &FeedQuery::parse("tag=urgent"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+    assert!(ics.contains("UID:tagged\r\n"));
+
+    let ics = feed::render(&tasks, &FeedQuery::parse("tag=LATER&tag=urgent&tag="));
+    assert!(ics.contains("UID:tagged\r\n"));
+    assert!(ics.contains("UID:other\r\n"));
+    assert!(!ics.contains("UID:work\r\n"));
+
+    let ics = feed::render(&tasks, &FeedQuery::parse("tag=urgent&category=Work"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 0, "tags narrow the category filter");
+}
+
+#[tokio::test]
+async fn server_requires_the_token_path() {
+    let (_dir, db) = common::temp_db();
+    seed(&db);
+    let server = FeedServer::start(db.clone(), 0).unwrap();
+    let url = feed_url(&db, &server);
+    assert!(url.starts_with(&format!("http://127.0.0.1:{}/", server.port())));
+
+    let res = reqwest::get(format!("{}?category=Home", url)).await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "text/calendar; charset=utf-8");
+    let body = res.text().await.unwrap();
+    assert!(body.contains("UID:home\r\n"));
+    assert!(!body.contains("UID:work\r\n"));
+
+    let guessed = reqwest::get(format!("http://127.0.0.1:{}/feed.ics", server.port())).await.unwrap();
+    assert_eq!(guessed.status(), 404);
+
+    // A new token locks out old subscriptions
+    feed::reset_token(&db).unwrap();
+    assert_eq!(reqwest::get(&url).await.unwrap().status(), 404);
+    let new_url = feed::status(&db, Some(&server)).unwrap().url.unwrap();
+    assert_ne!(new_url, url);
+    assert_eq!(reqwest::get(&new_url).await.unwrap().status(), 200);
+}
+
+#[test]
+fn feed_is_disabled_by_default() {
+    let (_dir, db) = common::temp_db();
+    let status = feed::status(&db, None).unwrap();
+    assert!(!status.enabled);
+    assert!(!status.running);
+    assert_eq!(status.port, feed::DEFAULT_PORT);
+    assert!(status.url.is_none());
+
+    assert!(feed::save_settings(&db, true, 0).is_err());
+    feed::save_settings(&db, true, 18080).unwrap();
+    assert_eq!(feed::settings(&db).unwrap(), (true, 18080));
+}
@@ -0,0 +1,913 @@
+mod common;
+
+use common::{MockResponse, MockServer, RecordedRequest};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use tasker_lib::db::{Account, AccountTokens, Database, Task, TaskEventLink};
+use tasker_lib::google::{self, GoogleEndpoints, ImportedTaskAction, TimeBlock};
+use tasker_lib::secrets;
+
+const PERSONAL_ID: &str = "1234567890";
+const WORK_ID: &str = "work-1";
+const VALID_TOKENS: [&str; 3] = ["access-1", "access-2", "work-access"];
+
+// Every test database gets the OAuth client through the settings table
+fn temp_db() -> (tempfile::TempDir, Database) {
+    let (dir, db) = common::temp_db();
+    db.set_setting("google_client_id", "test-client-id").unwrap();
+    db.set_setting("google_client_secret", "test-client-secret").unwrap();
+    (dir, db)
+}
+
+// Emulates the subset of Google OAuth + Tasks API that google.rs uses
+fn google_stand_in(req: &RecordedRequest) -> MockResponse {
+    let authorized = req.bearer_token().is_some_and(|t| VALID_TOKENS.contains(&t));
+
+    match (req.method.as_str(), req.path.as_str()) {
+        ("POST", "/token") => match req.form_param("grant_type").as_deref() {
+            Some("authorization_code") if req.form_param("code").as_deref() == Some("good-code") => {
+                MockResponse::json(200, json!({
+                    "access_token": "access-1",
+                    "expires_in": 3599,
+                    "refresh_token": "refresh-1",
+                    "scope": "https://www.googleapis.com/auth/tasks",
+                    "token_type": "Bearer"
+                }))
+            }
+            Some("authorization_code") if req.form_param("code").as_deref() == Some("work-code") => {
+                MockResponse::json(200, json!({
+                    "access_token": "work-access",
+                    "expires_in": 3599,
+                    "refresh_token": "work-refresh",
+                    "scope": "https://www.googleapis.com/auth/tasks",
+                    "token_type": "Bearer"
+                }))
+            }
+            Some("refresh_token") if req.form_param("refresh_token").as_deref() == Some("refresh-1") => {
+                MockResponse::json(200, json!({
+                    "access_token": "access-2",
+                    "expires_in": 3599,
+                    "scope": "https://www.googleapis.com/auth/tasks",
+                    "token_type": "Bearer"
+                }))
+            }
+            _ => MockResponse::json(400, json!({ "error": "invalid_grant" })),
+        },
+        ("POST", "/revoke") => match req.form_param("token").as_deref() {
+            Some("refresh-1") | Some("work-refresh") => MockResponse::json(200, json!({})),
+            _ => MockResponse::json(400, json!({ "error": "invalid_token" })),
+        },
+        _ if !authorized => MockResponse::json(401, json!({ "error": { "code": 401, "message": "Invalid Credentials" } })),
+        ("GET", "/oauth2/v2/userinfo") if req.bearer_token() == Some("work-access") => MockResponse::json(200, json!({
+            "id": WORK_ID,
+            "email": "tester@work.example.com",
+            "name": "Test User (Work)",
+            "picture": null
+        })),
+        ("GET", "/oauth2/v2/userinfo") => MockResponse::json(200, json!({
+            "id": PERSONAL_ID,
+            "email": "tester@example.com",
+            "name": "Test User",
+            "picture": null
+        })),
+        ("GET", "/tasks/v1/users/@me/lists") => match req.query_param("pageToken") {
+            None => MockResponse::json(200, json!({
+                "items": [{ "id": "list-1", "title": "My Tasks", "updated": "2026-01-01T00:00:00.000Z" }],
+                "nextPageToken": "lists-2"
+            })),
+            Some("lists-2") => MockResponse::json(200, json!({
+                "items": [{ "id": "list-2", "title": "Work", "updated": "2026-01-02T00:00:00.000Z" }]
+            })),
+            Some(_) => MockResponse::json(400, json!({ "error": { "code": 400, "message": "Invalid page token" } })),
+        },
+        ("GET", "/tasks/v1/lists/list-1/tasks") => match req.query_param("pageToken") {
+            None => MockResponse::json(200, json!({
+                "items": [
+                    { "id": "t1", "title": "First", "updated": "2026-01-01T00:00:00.000Z", "status": "needsAction", "position": "00000000000000000000" },
+                    { "id": "t2", "title": "Second", "updated": "2026-01-01T00:00:00.000Z", "status": "completed", "due": "2026-01-10T00:00:00.000Z", "position": "00000000000000000002" }
+                ],
+                "nextPageToken": "tasks-2"
+            })),
+            Some("tasks-2") => MockResponse::json(200, json!({
+                "items": [
+                    { "id": "t3", "title": "Third", "updated": "2026-01-01T00:00:00.000Z", "status": "needsAction", "position": "00000000000000000001" }
+                ]
+            })),
+            Some(_) => MockResponse::json(400, json!({ "error": { "code": 400, "message": "Invalid page token" } })),
+        },
+        ("GET", "/tasks/v1/lists/list-2/tasks") => MockResponse::json(200, json!({
+            "items": [
+                { "id": "w1", "title": "Quarterly report", "updated": "2026-01-02T00:00:00.000Z", "status": "needsAction" }
+            ]
+        })),
+        ("GET", "/calendar/v3/users/me/calendarList") => match req.query_param("pageToken") {
+            None => MockResponse::json(200, json!({
+                "items": [{ "id": "primary-cal", "summary": "Tester", "primary": true, "selected": true, "backgroundColor": "#9fe1e7" }],
+                "nextPageToken": "cals-2"
+            })),
+            Some("cals-2") => MockResponse::json(200, json!({
+                "items": [
+                    { "id": "en.usa#holiday@group.v.calendar.google.com", "summary": "Holidays", "backgroundColor": "#16a765" },
+                    { "id": "hidden-cal", "summary": "Hidden", "selected": false }
+                ]
+            })),
+            Some(_) => MockResponse::json(400, json!({ "error": { "code": 400, "message": "Invalid page token" } })),
+        },
+        ("GET", "/calendar/v3/calendars/primary-cal/events") => match req.query_param("pageToken") {
+            None => MockResponse::json(200, json!({
+                "items": [
+                    {
+                        "id": "standup_20260105", "status": "confirmed", "summary": "Standup",
+                        "start": { "dateTime": "2026-01-05T09:00:00Z" }, "end": { "dateTime": "2026-01-05T09:15:00Z" },
+                        "recurringEventId": "standup"
+                    },
+                    {
+                        "id": "standup_20260106", "status": "cancelled",
+                        "start": { "dateTime": "2026-01-06T09:00:00Z" }, "end": { "dateTime": "2026-01-06T09:15:00Z" },
+                        "recurringEventId": "standup"
+                    }
+                ],
+                "nextPageToken": "events-2"
+            })),
+            Some("events-2") => MockResponse::json(200, json!({
+                "items": [{
+                    "id": "review", "status": "confirmed", "summary": "Review", "location": "Room 1",
+                    "start": { "dateTime": "2026-01-07T14:00:00+01:00" }, "end": { "dateTime": "2026-01-07T15:00:00+01:00" }
+                }]
+            })),
+            Some(_) => MockResponse::json(400, json!({ "error": { "code": 400, "message": "Invalid page token" } })),
+        },
+        ("GET", "/calendar/v3/calendars/en.usa%23holiday@group.v.calendar.google.com/events") => MockResponse::json(200, json!({
+            "items": [
+                {
+                    "id": "new-year", "status": "confirmed", "summary": "New Year's Day",
+                    "start": { "date": "2026-01-01" }, "end": { "date": "2026-01-02" }
+                },
+                {
+                    "id": "pushed", "status": "confirmed", "summary": "Pushed task",
+                    "start": { "date": "2026-01-03" }, "end": { "date": "2026-01-04" },
+                    "extendedProperties": { "private": { "taskerTaskId": "local-1" } }
+                }
+            ]
+        })),
+        // Event writes echo the request body, "gone" emulates an event deleted on the calendar side
+        ("POST", "/calendar/v3/calendars/primary-cal/events") => {
+            let mut event: serde_json::Value = serde_json::from_str(&req.body).unwrap();
+            event["id"] = json!("created-1");
+            MockResponse::json(200, event)
+        }
+        ("PATCH", "/calendar/v3/calendars/primary-cal/events/created-1") => {
+            let mut event: serde_json::Value = serde_json::from_str(&req.body).unwrap();
+            event["id"] = json!("created-1");
+            MockResponse::json(200, event)
+        }
+        ("DELETE", "/calendar/v3/calendars/primary-cal/events/created-1") => MockResponse::empty(204),
+        ("PATCH", "/calendar/v3/calendars/primary-cal/events/gone") => MockResponse::json(404, json!({ "error": { "code": 404, "message": "Not Found" } })),
+        ("DELETE", "/calendar/v3/calendars/primary-cal/events/gone") => MockResponse::json(410, json!({ "error": { "code": 410, "message": "Resource has been deleted" } })),
+        _ => MockResponse::json(404, json!({ "error": { "code": 404, "message": "Not Found" } })),
+    }
+}
+
+fn setup() -> (MockServer, GoogleEndpoints) {
+    let server = MockServer::start(google_stand_in);
+    let endpoints = GoogleEndpoints::with_base_url(&server.base_url);
+    (server, endpoints)
+}
+
+fn connect(db: &Database, account_id: &str, access_token: &str, refresh_token: Option<&str>, expires_at: Option<i64>) {
+    db.upsert_account(&Account {
+        provider: "google".to_string(),
+        account_id: account_id.to_string(),
+        email: format!("{}@example.com", account_id),
+        name: account_id.to_string(),
+        picture: None,
+    }).unwrap();
+    db.set_account_tokens("google", account_id, &AccountTokens {
+        access_token: access_token.to_string(),
+        refresh_token: refresh_token.map(str::to_string),
+        expires_at,
+    }).unwrap();
+}
+
+fn tokens(db: &Database, account_id: &str) -> Option<AccountTokens> {
+    db.get_account_tokens("google", account_id).unwrap()
+}
+
+#[test]
+fn auth_url_uses_configured_endpoint() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    let url = google::get_auth_url(&db, &endpoints).unwrap();
+    assert!(url.starts_with(&format!("{}/o/oauth2/v2/auth?", server.base_url)));
+    assert!(url.contains("client_id=test-client-id"));
+    assert!(url.contains("access_type=offline"));
+}
+
+#[test]
+fn credentials_are_read_from_config_file() {
+    let (dir, db) = common::temp_db();
+    std::fs::write(
+        dir.path().join("google_oauth.json"),
+        r#"{ "client_id": "file-client-id", "client_secret": "file-secret" }"#,
+    ).unwrap();
+
+    let credentials = google::load_credentials(&db).unwrap();
+    assert_eq!(credentials.client_id, "file-client-id");
+    assert_eq!(credentials.client_secret, "file-secret");
+
+    // The settings table overrides the file
+    db.set_setting("google_client_id", "settings-client-id").unwrap();
+    db.set_setting("google_client_secret", "settings-secret").unwrap();
+    assert_eq!(google::load_credentials(&db).unwrap().client_id, "settings-client-id");
+}
+
+#[test]
+fn incomplete_credentials_are_an_error() {
+    let (_dir, db) = common::temp_db();
+    db.set_setting("google_client_id", "only-the-id").unwrap();
+
+    let err = google::load_credentials(&db).unwrap_err();
+    assert!(err.contains("Incomplete Google OAuth client in settings"), "{}", err);
+}
+
+#[test]
+fn missing_credentials_are_an_error_not_a_panic() {
+    let configured_elsewhere = option_env!("GOOGLE_CLIENT_ID").is_some() || std::env::var("GOOGLE_CLIENT_ID").is_ok();
+    if configured_elsewhere {
+        return;
+    }
+    let (_dir, db) = common::temp_db();
+
+    let err = google::get_auth_url(&db, &GoogleEndpoints::default()).unwrap_err();
+    assert!(err.contains("not configured"), "{}", err);
+}
+
+#[tokio::test]
+async fn exchange_code_stores_account_and_returns_profile() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+
+    let user = google::exchange_code("good-code", &db, &endpoints).await.unwrap();
+    assert_eq!(user.id, PERSONAL_ID);
+    assert_eq!(user.email, "tester@example.com");
+
+    let accounts = google::get_accounts(&db).unwrap();
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0].account_id, PERSONAL_ID);
+    assert_eq!(accounts[0].email, "tester@example.com");
+
+    let stored = tokens(&db, PERSONAL_ID).unwrap();
+    assert_eq!(stored.access_token, "access-1");
+    assert_eq!(stored.refresh_token.as_deref(), Some("refresh-1"));
+    assert!(stored.expires_at.is_some());
+
+    let token_req = &server.requests_to("/token")[0];
+    assert_eq!(token_req.form_param("client_id").as_deref(), Some("test-client-id"));
+    assert_eq!(token_req.form_param("client_secret").as_deref(), Some("test-client-secret"));
+    assert_eq!(token_req.form_param("redirect_uri").as_deref(), Some("http://localhost:14123"));
+}
+
+#[tokio::test]
+async fn multiple_accounts_are_kept_apart() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+
+    google::exchange_code("good-code", &db, &endpoints).await.unwrap();
+    google::exchange_code("work-code", &db, &endpoints).await.unwrap();
+
+    let ids: Vec<_> = google::get_accounts(&db).unwrap().into_iter().map(|a| a.account_id).collect();
+    assert_eq!(ids, [PERSONAL_ID, WORK_ID]);
+    assert_eq!(tokens(&db, PERSONAL_ID).unwrap().access_token, "access-1");
+    assert_eq!(tokens(&db, WORK_ID).unwrap().access_token, "work-access");
+
+    google::fetch_task_lists(&db, &endpoints, WORK_ID).await.unwrap();
+    let list_requests = server.requests_to("/tasks/v1/users/@me/lists");
+    assert!(list_requests.iter().all(|r| r.bearer_token() == Some("work-access")));
+
+    google::disconnect(&db, &endpoints, WORK_ID, ImportedTaskAction::Keep).await.unwrap();
+    let ids: Vec<_> = google::get_accounts(&db).unwrap().into_iter().map(|a| a.account_id).collect();
+    assert_eq!(ids, [PERSONAL_ID]);
+    assert!(tokens(&db, PERSONAL_ID).is_some());
+}
+
+#[tokio::test]
+async fn exchange_code_reports_invalid_grant() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+
+    let err = google::exchange_code("bad-code", &db, &endpoints).await.unwrap_err();
+    assert!(err.contains("Failed to exchange code"), "{}", err);
+    assert!(err.contains("invalid_grant"), "{}", err);
+    assert!(google::get_accounts(&db).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn expired_access_token_is_refreshed_before_request() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", Some("refresh-1"), Some(0));
+
+    google::get_user_profile(&db, &endpoints, PERSONAL_ID).await.unwrap();
+
+    let stored = tokens(&db, PERSONAL_ID).unwrap();
+    assert_eq!(stored.access_token, "access-2");
+    // Refresh responses carry no refresh token; the stored one must survive
+    assert_eq!(stored.refresh_token.as_deref(), Some("refresh-1"));
+    let userinfo = server.requests_to("/oauth2/v2/userinfo");
+    assert_eq!(userinfo.len(), 1);
+    assert_eq!(userinfo[0].bearer_token(), Some("access-2"));
+}
+
+#[tokio::test]
+async fn rejected_access_token_is_refreshed_and_retried() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "revoked", Some("refresh-1"), None);
+
+    let lists = google::fetch_task_lists(&db, &endpoints, PERSONAL_ID).await.unwrap();
+    assert_eq!(lists.len(), 2);
+    assert_eq!(tokens(&db, PERSONAL_ID).unwrap().access_token, "access-2");
+    assert_eq!(server.requests_to("/token").len(), 1);
+}
+
+#[tokio::test]
+async fn failed_refresh_is_reported() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "revoked", Some("refresh-unknown"), None);
+
+    let err = google::fetch_task_lists(&db, &endpoints, PERSONAL_ID).await.unwrap_err();
+    assert!(err.contains("Failed to refresh token"), "{}", err);
+}
+
+#[tokio::test]
+async fn unknown_account_is_an_error() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+
+    let err = google::fetch_task_lists(&db, &endpoints, "nobody").await.unwrap_err();
+    assert_eq!(err, "No access token found");
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn legacy_tokens_are_migrated_to_an_account() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    db.set_setting("google_access_token", "stale").unwrap();
+    db.set_setting("google_refresh_token", "refresh-1").unwrap();
+    db.set_setting("google_token_expires_at", "0").unwrap();
+    db.add_task(local_task("old-import")).unwrap();
+    db.link_imported_task("old-import", "google", "", "list-1").unwrap();
+
+    // Sealed at startup, before the migration gets a chance to run
+    secrets::open(&db).unwrap();
+    let user = google::migrate_legacy_tokens(&db, &endpoints).await.unwrap().unwrap();
+    assert_eq!(user.id, PERSONAL_ID);
+
+    let stored = tokens(&db, PERSONAL_ID).unwrap();
+    assert_eq!(stored.access_token, "access-2");
+    assert_eq!(stored.refresh_token.as_deref(), Some("refresh-1"));
+    assert!(db.get_setting("google_access_token").unwrap().is_none());
+    assert!(db.get_setting("google_refresh_token").unwrap().is_none());
+    assert_eq!(db.get_imported_task_ids("google", PERSONAL_ID).unwrap(), ["old-import"]);
+
+    assert!(google::migrate_legacy_tokens(&db, &endpoints).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn rejected_legacy_tokens_are_dropped_and_unreachable_ones_kept() {
+    let (_server, endpoints) = setup();
+    let legacy = |db: &Database| db.get_setting("google_refresh_token").unwrap().or(db.get_setting("google_access_token").unwrap());
+
+    // Offline: try again at the next start
+    let (_dir, db) = temp_db();
+    db.set_setting("google_refresh_token", "refresh-1").unwrap();
+    let offline = GoogleEndpoints::with_base_url("http://127.0.0.1:1");
+    assert!(google::migrate_legacy_tokens(&db, &offline).await.is_err());
+    assert!(legacy(&db).is_some());
+
+    // Revoked refresh token
+    db.set_setting("google_refresh_token", "revoked").unwrap();
+    let err = google::migrate_legacy_tokens(&db, &endpoints).await.unwrap_err();
+    assert!(err.contains("invalid_grant"), "{}", err);
+    assert!(legacy(&db).is_none());
+    assert!(google::migrate_legacy_tokens(&db, &endpoints).await.unwrap().is_none());
+
+    // Expired access token without a refresh token
+    let (_dir, db) = temp_db();
+    db.set_setting("google_access_token", "stale").unwrap();
+    assert!(google::migrate_legacy_tokens(&db, &endpoints).await.is_err());
+    assert!(legacy(&db).is_none());
+    assert!(google::get_accounts(&db).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn fetch_task_lists_follows_pagination() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+
+    let lists = google::fetch_task_lists(&db, &endpoints, PERSONAL_ID).await.unwrap();
+    let titles: Vec<_> = lists.iter().map(|l| l.title.as_str()).collect();
+    assert_eq!(titles, ["My Tasks", "Work"]);
+}
+
+#[tokio::test]
+async fn fetch_tasks_follows_pagination() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+
+    let tasks = google::fetch_tasks(&db, &endpoints, PERSONAL_ID, "list-1").await.unwrap();
+    let ids: Vec<_> = tasks.iter().map(|t| t.id.as_str()).collect();
+    assert_eq!(ids, ["t1", "t2", "t3"]);
+    assert_eq!(tasks[1].due.as_deref(), Some("2026-01-10T00:00:00.000Z"));
+
+    let requests = server.requests_to("/tasks/v1/lists/list-1/tasks");
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].query_param("showCompleted"), Some("true"));
+    assert_eq!(requests[0].query_param("showHidden"), Some("true"));
+    assert_eq!(requests[1].query_param("pageToken"), Some("tasks-2"));
+}
+
+#[tokio::test]
+async fn fetch_tasks_reports_api_errors() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+
+    let err = google::fetch_tasks(&db, &endpoints, PERSONAL_ID, "missing").await.unwrap_err();
+    assert!(err.starts_with("API Error"), "{}", err);
+    assert!(err.contains("Not Found"), "{}", err);
+}
+
+fn local_task(id: &str) -> Task {
+    Task {
+        id: id.to_string(),
+        title: "Local".to_string(),
+        priority: "high".to_string(),
+        category: "Personal".to_string(),
+        ..Default::default()
+    }
+}
+
+async fn connected_db_with_imports(endpoints: &GoogleEndpoints) -> (tempfile::TempDir, Database) {
+    let (dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", Some("refresh-1"), Some(9_999_999_999));
+    db.set_setting("theme", "dark").unwrap();
+    db.add_task(local_task("local-1")).unwrap();
+    assert_eq!(google::import_tasks(&db, endpoints, PERSONAL_ID, "list-1").await.unwrap(), 3);
+    (dir, db)
+}
+
+#[tokio::test]
+async fn import_tasks_records_origin() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = connected_db_with_imports(&endpoints).await;
+
+    let mut imported = db.get_imported_task_ids("google", PERSONAL_ID).unwrap();
+    imported.sort();
+    assert_eq!(imported, ["t1", "t2", "t3"]);
+    assert_eq!(db.get_tasks().unwrap().len(), 4);
+
+    let t2 = db.get_task_by_id("t2").unwrap().unwrap();
+    assert_eq!(t2.category, "My Tasks");
+    assert_eq!(t2.due_date.unwrap().to_string(), "2026-01-10");
+    assert!(t2.due_time.is_none());
+}
+
+#[tokio::test]
+async fn import_appends_new_tasks_in_google_order() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = connected_db_with_imports(&endpoints).await;
+
+    let ids: Vec<_> = db.get_tasks().unwrap().into_iter().map(|t| t.id).collect();
+    assert_eq!(ids, ["local-1", "t1", "t3", "t2"]);
+    assert_eq!(db.get_task_by_id("t1").unwrap().unwrap().priority, "Medium");
+}
+
+#[tokio::test]
+async fn reimport_keeps_local_attributes() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = connected_db_with_imports(&endpoints).await;
+
+    db.update_task_order(vec!["t2".to_string(), "local-1".to_string(), "t3".to_string(), "t1".to_string()]).unwrap();
+    let mut t2 = db.get_task_by_id("t2").unwrap().unwrap();
+    t2.priority = "High".to_string();
+    t2.category = "Errands".to_string();
+    t2.title = "Edited locally".to_string();
+    t2.due_time = Some("18:00".parse().unwrap());
+    t2.timezone = Some("Asia/Seoul".to_string());
+    db.update_task(t2).unwrap();
+
+    google::import_tasks(&db, &endpoints, PERSONAL_ID, "list-1").await.unwrap();
+
+    let ids: Vec<_> = db.get_tasks().unwrap().into_iter().map(|t| t.id).collect();
+    assert_eq!(ids, ["t2", "local-1", "t3", "t1"]);
+    let t2 = db.get_task_by_id("t2").unwrap().unwrap();
+    assert_eq!(t2.priority, "High");
+    assert_eq!(t2.category, "Errands");
+    assert_eq!(t2.title, "Second", "Google owns the title");
+    assert!(t2.completed);
+    assert_eq!(t2.due_date.unwrap().to_string(), "2026-01-10");
+    assert_eq!(t2.due_time.unwrap().format("%H:%M").to_string(), "18:00");
+}
+
+#[tokio::test]
+async fn import_remembers_list_category() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = connected_db_with_imports(&endpoints).await;
+    assert_eq!(google::get_list_categories(&db, PERSONAL_ID).unwrap()["list-1"], "My Tasks");
+
+    // The mapping applies to newly imported tasks, existing ones keep their local category
+    google::set_list_category(&db, PERSONAL_ID, "list-1", "Inbox").unwrap();
+    db.delete_task("t1").unwrap();
+    google::import_tasks(&db, &endpoints, PERSONAL_ID, "list-1").await.unwrap();
+    assert_eq!(db.get_task_by_id("t1").unwrap().unwrap().category, "Inbox");
+    assert_eq!(db.get_task_by_id("t3").unwrap().unwrap().category, "My Tasks");
+
+    assert!(google::set_list_category(&db, PERSONAL_ID, "list-1", "  ").is_err());
+    assert!(google::import_tasks(&db, &endpoints, PERSONAL_ID, "missing").await.is_err());
+}
+
+#[tokio::test]
+async fn import_all_tasks_imports_every_list() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+
+    let counts = google::import_all_tasks(&db, &endpoints, PERSONAL_ID).await.unwrap();
+    assert_eq!(counts["list-1"], 3);
+    assert_eq!(counts["list-2"], 1);
+    assert_eq!(db.get_task_by_id("w1").unwrap().unwrap().category, "Work");
+
+    let categories = google::get_list_categories(&db, PERSONAL_ID).unwrap();
+    assert_eq!(categories.len(), 2);
+}
+
+#[tokio::test]
+async fn disconnect_revokes_refresh_token_and_removes_account() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = connected_db_with_imports(&endpoints).await;
+    db.set_account_setting("google", PERSONAL_ID, "sync_cursor", "abc").unwrap();
+
+    let summary = google::disconnect(&db, &endpoints, PERSONAL_ID, ImportedTaskAction::Keep).await.unwrap();
+    assert!(summary.token_revoked);
+    assert_eq!(server.requests_to("/revoke")[0].form_param("token").as_deref(), Some("refresh-1"));
+
+    assert!(google::get_accounts(&db).unwrap().is_empty());
+    assert!(tokens(&db, PERSONAL_ID).is_none());
+    assert!(db.get_account_setting("google", PERSONAL_ID, "sync_cursor").unwrap().is_none());
+    assert_eq!(db.get_setting("theme").unwrap().as_deref(), Some("dark"));
+
+    assert_eq!(db.get_tasks().unwrap().len(), 4);
+    assert_eq!(db.get_imported_task_ids("google", PERSONAL_ID).unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn disconnect_can_detach_imported_tasks() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = connected_db_with_imports(&endpoints).await;
+
+    let summary = google::disconnect(&db, &endpoints, PERSONAL_ID, ImportedTaskAction::Detach).await.unwrap();
+    assert_eq!(summary.tasks_detached, 3);
+    assert_eq!(db.get_tasks().unwrap().len(), 4);
+    assert!(db.get_imported_task_ids("google", PERSONAL_ID).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn disconnect_can_delete_imported_tasks() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = connected_db_with_imports(&endpoints).await;
+
+    let summary = google::disconnect(&db, &endpoints, PERSONAL_ID, ImportedTaskAction::Delete).await.unwrap();
+    assert_eq!(summary.tasks_deleted, 3);
+    let remaining: Vec<_> = db.get_tasks().unwrap().into_iter().map(|t| t.id).collect();
+    assert_eq!(remaining, ["local-1"]);
+    assert!(db.get_imported_task_ids("google", PERSONAL_ID).unwrap().is_empty());
+    assert!(google::get_accounts(&db).unwrap().is_empty(), "same transaction as the account");
+}
+
+#[tokio::test]
+async fn disconnect_cleans_up_even_when_revocation_fails() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", Some("already-revoked"), None);
+
+    let summary = google::disconnect(&db, &endpoints, PERSONAL_ID, ImportedTaskAction::Keep).await.unwrap();
+    assert!(!summary.token_revoked);
+    assert!(google::get_accounts(&db).unwrap().is_empty());
+}
+
+fn january() -> (DateTime<Utc>, DateTime<Utc>) {
+    (
+        "2026-01-01T00:00:00Z".parse().unwrap(),
+        "2026-02-01T00:00:00Z".parse().unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn fetch_events_expands_recurrences_and_follows_pagination() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+    let (start, end) = january();
+
+    let events = google::fetch_events(&db, &endpoints, PERSONAL_ID, "primary-cal", start, end).await.unwrap();
+    let ids: Vec<_> = events.iter().map(|e| e.id.as_str()).collect();
+    assert_eq!(ids, ["standup_20260105", "standup_20260106", "review"]);
+
+    let requests = server.requests_to("/calendar/v3/calendars/primary-cal/events");
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].query_param("singleEvents"), Some("true"));
+    assert_eq!(requests[0].query_param("orderBy"), Some("startTime"));
+    assert_eq!(requests[0].query_param("timeMin"), Some("2026-01-01T00:00:00+00:00"));
+    assert_eq!(requests[0].query_param("timeMax"), Some("2026-02-01T00:00:00+00:00"));
+    assert_eq!(requests[1].query_param("pageToken"), Some("events-2"));
+}
+
+#[tokio::test]
+async fn sync_events_stores_selected_calendars() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+    let (start, end) = january();
+
+    assert_eq!(google::sync_events(&db, &endpoints, PERSONAL_ID, start, end).await.unwrap(), 3);
+    assert!(server.requests_to("/calendar/v3/calendars/hidden-cal/events").is_empty());
+
+    let events = db.get_events(start.timestamp(), end.timestamp()).unwrap();
+    let titles: Vec<_> = events.iter().map(|e| e.title.as_str()).collect();
+    assert!(titles.contains(&"New Year's Day"));
+    assert!(!titles.contains(&"(No title)"), "cancelled instance must be skipped");
+
+    let holiday = events.iter().find(|e| e.id == "new-year").unwrap();
+    assert!(holiday.all_day);
+    assert_eq!(holiday.start, "2026-01-01");
+    assert_eq!(holiday.color.as_deref(), Some("#16a765"));
+
+    let review = events.iter().find(|e| e.id == "review").unwrap();
+    assert!(!review.all_day);
+    assert_eq!(review.start_ts, "2026-01-07T13:00:00Z".parse::<DateTime<Utc>>().unwrap().timestamp());
+    assert_eq!(review.location.as_deref(), Some("Room 1"));
+
+    // Only events overlapping the queried range are returned
+    let week = db.get_events(
+        "2026-01-05T00:00:00Z".parse::<DateTime<Utc>>().unwrap().timestamp(),
+        "2026-01-06T00:00:00Z".parse::<DateTime<Utc>>().unwrap().timestamp(),
+    ).unwrap();
+    let ids: Vec<_> = week.iter().map(|e| e.id.as_str()).collect();
+    assert_eq!(ids, ["standup_20260105"]);
+}
+
+#[tokio::test]
+async fn sync_events_removes_events_deleted_remotely() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+    let (start, end) = january();
+
+    google::sync_events(&db, &endpoints, PERSONAL_ID, start, end).await.unwrap();
+    let mut removed = db.get_events(start.timestamp(), end.timestamp()).unwrap().remove(0);
+    removed.id = "deleted-remotely".to_string();
+    let calendar_id = removed.calendar_id.clone();
+    db.replace_events("google", PERSONAL_ID, &calendar_id, 0, 1, &[removed]).unwrap();
+    assert_eq!(db.get_events(start.timestamp(), end.timestamp()).unwrap().len(), 4);
+
+    google::sync_events(&db, &endpoints, PERSONAL_ID, start, end).await.unwrap();
+    let events = db.get_events(start.timestamp(), end.timestamp()).unwrap();
+    assert!(events.iter().all(|e| e.id != "deleted-remotely"));
+    assert_eq!(events.len(), 3);
+}
+
+#[tokio::test]
+async fn disconnect_removes_account_events() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", Some("refresh-1"), None);
+    let (start, end) = january();
+    google::sync_events(&db, &endpoints, PERSONAL_ID, start, end).await.unwrap();
+
+    google::disconnect(&db, &endpoints, PERSONAL_ID, ImportedTaskAction::Keep).await.unwrap();
+    assert!(db.get_events(start.timestamp(), end.timestamp()).unwrap().is_empty());
+}
+
+#[test]
+fn time_range_accepts_dates_and_timestamps() {
+    let range = google::TimeRange { start: "2026-01-01".to_string(), end: "2026-02-01T00:00:00Z".to_string() };
+    let (start, end) = range.bounds().unwrap();
+    assert!(start < end);
+    assert_eq!(end.timestamp(), "2026-02-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap().timestamp());
+
+    let reversed = google::TimeRange { start: "2026-02-01".to_string(), end: "2026-01-01".to_string() };
+    assert!(reversed.bounds().is_err());
+    let invalid = google::TimeRange { start: "tomorrow".to_string(), end: "2026-01-01".to_string() };
+    assert!(invalid.bounds().is_err());
+}
+
+const EVENTS_PATH: &str = "/calendar/v3/calendars/primary-cal/events";
+
+fn due_task(id: &str, due_date: &str) -> Task {
+    Task { due_date: Some(due_date.parse().unwrap()), ..local_task(id) }
+}
+
+fn body_json(req: &RecordedRequest) -> serde_json::Value {
+    serde_json::from_str(&req.body).unwrap()
+}
+
+#[tokio::test]
+async fn schedule_task_creates_all_day_event() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+    db.add_task(due_task("local-1", "2026-03-10")).unwrap();
+
+    let link = google::schedule_task(&db, &endpoints, "local-1", PERSONAL_ID, "primary-cal", None).await.unwrap();
+    assert_eq!(link.event_id, "created-1");
+    assert_eq!(db.get_task_event("local-1").unwrap().unwrap().event_id, "created-1");
+
+    let body = body_json(&server.requests_to(EVENTS_PATH)[0]);
+    assert_eq!(body["summary"], "Local");
+    assert_eq!(body["start"]["date"], "2026-03-10");
+    assert_eq!(body["end"]["date"], "2026-03-11");
+    assert_eq!(body["extendedProperties"]["private"]["taskerTaskId"], "local-1");
+}
+
+#[tokio::test]
+async fn schedule_task_creates_time_block() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+    db.add_task(due_task("local-1", "2026-03-10")).unwrap();
+
+    let block = TimeBlock { start_time: "09:30".to_string(), duration_minutes: 90 };
+    google::schedule_task(&db, &endpoints, "local-1", PERSONAL_ID, "primary-cal", Some(block)).await.unwrap();
+
+    let body = body_json(&server.requests_to(EVENTS_PATH)[0]);
+    let start = DateTime::parse_from_rfc3339(body["start"]["dateTime"].as_str().unwrap()).unwrap();
+    let end = DateTime::parse_from_rfc3339(body["end"]["dateTime"].as_str().unwrap()).unwrap();
+    assert_eq!(start.format("%Y-%m-%d %H:%M").to_string(), "2026-03-10 09:30");
+    assert_eq!((end - start).num_minutes(), 90);
+}
+
+#[tokio::test]
+async fn schedule_task_requires_open_task_with_due_date() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+    db.add_task(local_task("no-due")).unwrap();
+
+    assert!(google::schedule_task(&db, &endpoints, "no-due", PERSONAL_ID, "primary-cal", None).await.is_err());
+    assert!(google::schedule_task(&db, &endpoints, "missing", PERSONAL_ID, "primary-cal", None).await.is_err());
+    assert!(server.requests_to(EVENTS_PATH).is_empty());
+    assert!(db.get_task_event("no-due").unwrap().is_none());
+}
+
+#[tokio::test]
+async fn task_changes_update_the_event() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+    db.add_task(due_task("local-1", "2026-03-10")).unwrap();
+    google::schedule_task(&db, &endpoints, "local-1", PERSONAL_ID, "primary-cal", None).await.unwrap();
+
+    let task = Task { title: "Renamed".to_string(), ..due_task("local-1", "2026-03-12") };
+    db.update_task(task.clone()).unwrap();
+    google::sync_task_event(&db, &endpoints, &task).await.unwrap();
+
+    let patches = server.requests_to(&format!("{}/created-1", EVENTS_PATH));
+    assert_eq!(patches.len(), 1);
+    assert_eq!(patches[0].method, "PATCH");
+    let body = body_json(&patches[0]);
+    assert_eq!(body["summary"], "Renamed");
+    assert_eq!(body["start"]["date"], "2026-03-12");
+}
+
+#[tokio::test]
+async fn event_deleted_remotely_is_recreated() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+    let task = due_task("local-1", "2026-03-10");
+    db.add_task(task.clone()).unwrap();
+    db.set_task_event(&TaskEventLink {
+        task_id: "local-1".to_string(),
+        provider: "google".to_string(),
+        account_id: PERSONAL_ID.to_string(),
+        calendar_id: "primary-cal".to_string(),
+        event_id: "gone".to_string(),
+        start_time: None,
+        duration_minutes: None,
+    }).unwrap();
+
+    google::sync_task_event(&db, &endpoints, &task).await.unwrap();
+    assert_eq!(server.requests_to(EVENTS_PATH).len(), 1);
+    assert_eq!(db.get_task_event("local-1").unwrap().unwrap().event_id, "created-1");
+}
+
+#[tokio::test]
+async fn completing_task_removes_the_event() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+    db.add_task(due_task("local-1", "2026-03-10")).unwrap();
+    google::schedule_task(&db, &endpoints, "local-1", PERSONAL_ID, "primary-cal", None).await.unwrap();
+
+    let task = Task { completed: true, ..due_task("local-1", "2026-03-10") };
+    google::sync_task_event(&db, &endpoints, &task).await.unwrap();
+
+    let requests = server.requests_to(&format!("{}/created-1", EVENTS_PATH));
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "DELETE");
+    assert!(db.get_task_event("local-1").unwrap().is_none());
+}
+
+#[tokio::test]
+async fn unschedule_removes_event_and_link() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+    db.add_task(due_task("local-1", "2026-03-10")).unwrap();
+    google::schedule_task(&db, &endpoints, "local-1", PERSONAL_ID, "primary-cal", None).await.unwrap();
+
+    google::unschedule_task(&db, &endpoints, "local-1").await.unwrap();
+    assert_eq!(server.requests_to(&format!("{}/created-1", EVENTS_PATH))[0].method, "DELETE");
+    assert!(db.get_task_event("local-1").unwrap().is_none());
+
+    // Tasks without an event are a no-op
+    google::unschedule_task(&db, &endpoints, "local-1").await.unwrap();
+}
+
+#[tokio::test]
+async fn changes_from_any_writer_reach_the_events_they_touch() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+    db.add_task(due_task("local-1", "2026-03-10")).unwrap();
+    google::schedule_task(&db, &endpoints, "local-1", PERSONAL_ID, "primary-cal", None).await.unwrap();
+    let event_requests = || server.requests_to(&format!("{}/created-1", EVENTS_PATH));
+
+    // As a re-import or undo would write it, without going through a command
+    db.update_task(Task { title: "Renamed".to_string(), ..due_task("local-1", "2026-03-12") }).unwrap();
+    google::sync_task_events(&db, &endpoints, &["other".to_string()]).await.unwrap();
+    assert!(event_requests().is_empty(), "only the changed tasks");
+    google::sync_task_events(&db, &endpoints, &["local-1".to_string()]).await.unwrap();
+    assert_eq!(event_requests()[0].method, "PATCH");
+    assert_eq!(body_json(&event_requests()[0])["summary"], "Renamed");
+}
+
+#[tokio::test]
+async fn deleted_task_loses_its_event_after_the_delete() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+    db.add_task(due_task("local-1", "2026-03-10")).unwrap();
+    google::schedule_task(&db, &endpoints, "local-1", PERSONAL_ID, "primary-cal", None).await.unwrap();
+
+    db.delete_task("local-1").unwrap();
+    assert!(db.get_task_event("local-1").unwrap().is_some(), "kept until the event is removed");
+    // An empty list (an import or restore) checks every event
+    google::sync_task_events(&db, &endpoints, &[]).await.unwrap();
+    assert_eq!(server.requests_to(&format!("{}/created-1", EVENTS_PATH))[0].method, "DELETE");
+    assert!(db.get_task_event("local-1").unwrap().is_none());
+}
+
+#[tokio::test]
+async fn sync_events_skips_events_pushed_for_tasks() {
+    let (_server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+    let (start, end) = january();
+
+    google::sync_events(&db, &endpoints, PERSONAL_ID, start, end).await.unwrap();
+    let events = db.get_events(start.timestamp(), end.timestamp()).unwrap();
+    assert!(events.iter().all(|e| e.id != "pushed"));
+}
+
+#[test]
+fn google_due_keeps_its_calendar_date() {
+    let date = |s: &str| s.parse::<chrono::NaiveDate>().unwrap();
+    assert_eq!(google::parse_google_due("2026-01-10T00:00:00.000Z"), Some(date("2026-01-10")));
+    assert_eq!(google::parse_google_due("2026-01-10"), Some(date("2026-01-10")));
+    assert_eq!(google::parse_google_due("soon"), None);
+}
+
+#[tokio::test]
+async fn timed_task_event_uses_task_timezone() {
+    let (server, endpoints) = setup();
+    let (_dir, db) = temp_db();
+    connect(&db, PERSONAL_ID, "access-1", None, None);
+    let task = Task {
+        due_time: Some("00:30".parse().unwrap()),
+        timezone: Some("Asia/Seoul".to_string()),
+        ..due_task("local-1", "2026-03-10")
+    };
+    db.add_task(task).unwrap();
+
+    google::schedule_task(&db, &endpoints, "local-1", PERSONAL_ID, "primary-cal", None).await.unwrap();
+    let body = body_json(&server.requests_to(EVENTS_PATH)[0]);
+    assert_eq!(body["start"]["dateTime"], "2026-03-10T00:30:00+09:00");
+    assert_eq!(body["start"]["timeZone"], "Asia/Seoul");
+    assert_eq!(body["end"]["dateTime"], "2026-03-10T01:00:00+09:00");
+}
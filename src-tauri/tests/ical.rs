@@ -0,0 +1,212 @@
+mod common;
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use std::sync::{Arc, Mutex};
+use tasker_lib::changes::ChangeKind;
+use tasker_lib::db::Task;
+use tasker_lib::ical;
+
+fn task(id: &str) -> Task {
+    Task { title: "Task".to_string(), ..common::task(id) }
+}
+
+fn date(s: &str) -> NaiveDate {
+    s.parse().unwrap()
+}
+
+fn export(tasks: &[Task]) -> String {
+    ical::export_tasks(tasks, Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap())
+}
+
+#[test]
+fn export_writes_vtodo_properties() {
+    let parent = Task {
+        title: "Release, part 1; prep".to_string(),
+        description: Some("Line one\nLine two".to_string()),
+        priority: "High".to_string(),
+        completed: true,
+        due_date: Some(date("2026-01-10")),
+        recurrence: Some("FREQ=WEEKLY;BYDAY=MO".to_string()),
+        tags: vec!["q1".to_string(), "launch".to_string()],
+        ..task("parent")
+    };
+    let child = Task {
+        parent_id: Some("parent".to_string()),
+        due_date: Some(date("2026-01-10")),
+        due_time: Some("00:30".parse().unwrap()),
+        timezone: Some("Asia/Seoul".to_string()),
+        ..task("child")
+    };
+
+    let ics = export(&[parent, child]);
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    for line in [
+        "UID:parent",
+        "DTSTAMP:20261018T120000Z",
+        "SUMMARY:Release\\, part 1\\; prep",
+        "DESCRIPTION:Line one\\nLine two",
+        "DUE;VALUE=DATE:20260110",
+        "PRIORITY:1",
+        "STATUS:COMPLETED",
+        "CATEGORIES:Work,q1,launch",
+        "RRULE:FREQ=WEEKLY;BYDAY=MO",
+        "UID:child",
+        "DUE;TZID=Asia/Seoul:20260110T003000",
+        "PRIORITY:5",
+        "STATUS:NEEDS-ACTION",
+        "RELATED-TO;RELTYPE=PARENT:parent",
+    ] {
+        assert!(ics.contains(&format!("{}\r\n", line)), "missing {:?} in\n{}", line, ics);
+    }
+}
+
+#[test]
+fn long_lines_are_folded_and_round_trip() {
+    let long = Task { title: "가나다라마바사아자차카타파하".repeat(5), ..task("long") };
+    let ics = export(std::slice::from_ref(&long));
+    assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+
+    let parsed = ical::parse_tasks(&ics).unwrap();
+    assert_eq!(parsed.tasks[0].title, long.title);
+}
+
+#[test]
+fn export_import_round_trip() {
+    let original = vec![
+        Task {
+            title: "Plan; review, ship".to_string(),
+            description: Some("a\\b\nc".to_string()),
+            priority: "Low".to_string(),
+            start_date: Some(date("2026-01-05")),
+            due_date: Some(date("2026-01-10")),
+            due_time: Some("17:45".parse().unwrap()),
+            timezone: Some("America/New_York".to_string()),
+            recurrence: Some("FREQ=DAILY;COUNT=3".to_string()),
+            ..task("a")
+        },
+        Task { parent_id: Some("a".to_string()), completed: true, ..task("b") },
+    ];
+
+    let parsed = ical::parse_tasks(&export(&original)).unwrap();
+    assert_eq!(parsed.skipped, 0);
+    assert_eq!(parsed.tasks.len(), 2);
+    for (parsed, original) in parsed.tasks.iter().zip(&original) {
+        assert_eq!(parsed.id, original.id);
+        assert_eq!(parsed.title, original.title);
+        assert_eq!(parsed.description, original.description);
+        assert_eq!(parsed.completed, original.completed);
+        assert_eq!(parsed.priority, original.priority);
+        assert_eq!(parsed.category, original.category);
+        assert_eq!(parsed.due_date, original.due_date);
+        assert_eq!(parsed.due_time, original.due_time);
+        assert_eq!(parsed.timezone, original.timezone);
+        assert_eq!(parsed.start_date, original.start_date);
+        assert_eq!(parsed.recurrence, original.recurrence);
+        assert_eq!(parsed.parent_id, original.parent_id);
+    }
+}
+
+#[test]
+fn parses_foreign_vtodo_and_vevent() {
+    let ics = "BEGIN:VCALENDAR\n\
+VERSION:2.0\n\
+PRODID:-//Other//EN\n\
+BEGIN:VTIMEZONE\n\
+TZID:Europe/Berlin\n\
+END:VTIMEZONE\n\
+BEGIN:VTODO\n\
+UID:todo-1\n\
+SUMMARY:Buy milk\n\
+PRIORITY:3\n\
+CATEGORIES:Errands,Home,Weekly shop\n\
+DUE;TZID=\"Europe/Berlin\":20260301T090000\n\
+BEGIN:VALARM\n\
+ACTION:DISPLAY\n\
+DESCRIPTION:Alarm text\n\
+END:VALARM\n\
+END:VTODO\n\
+BEGIN:VEVENT\n\
+UID:event-1\n\
+SUMMARY:Standup\n\
+DTSTART:20260302T080000Z\n\
+DTEND:20260302T081500Z\n\
+END:VEVENT\n\
+BEGIN:VEVENT\n\
+UID:event-2\n\
+STATUS:CANCELLED\n\
+DTSTART;VALUE=DATE:20260303\n\
+END:VEVENT\n\
+BEGIN:VTODO\n\
+SUMMARY:No uid\n\
+END:VTODO\n\
+END:VCALENDAR\n";
+
+    let parsed = ical::parse_tasks(ics).unwrap();
+    assert_eq!(parsed.skipped, 2);
+    assert_eq!(parsed.tasks.len(), 2);
+
+    let todo = &parsed.tasks[0];
+    assert_eq!(todo.title, "Buy milk");
+    assert_eq!(todo.priority, "High");
+    assert_eq!(todo.category, "Errands");
+    assert_eq!(todo.tags, ["Home", "Weekly-shop"], "further categories become tags");
+    assert_eq!(todo.description, None, "VALARM properties belong to the alarm");
+    assert_eq!(todo.due_date, Some(date("2026-03-01")));
+    assert_eq!(todo.timezone.as_deref(), Some("Europe/Berlin"));
+
+    let event = &parsed.tasks[1];
+    assert_eq!(event.title, "Standup");
+    assert_eq!(event.due_date, Some(date("2026-03-02")));
+    assert_eq!(event.due_time.unwrap().format("%H:%M").to_string(), "08:00");
+    assert_eq!(event.timezone.as_deref(), Some("UTC"));
+    assert!(!event.completed);
+}
+
+#[test]
+fn malformed_input_is_an_error() {
+    assert!(ical::parse_tasks("hello").is_err());
+    assert!(ical::parse_tasks("BEGIN:VCALENDAR\nBEGIN:VTODO\nUID:x\nEND:VCALENDAR\n").is_err());
+}
+
+#[test]
+fn import_dedups_by_uid() {
+    let (_dir, db) = common::temp_db();
+    db.add_task(task("first")).unwrap();
+    db.add_task(Task { priority: "High".to_string(), ..task("existing") }).unwrap();
+
+    let ics = export(&[
+        Task { title: "Renamed".to_string(), ..task("existing") },
+        task("new"),
+        Task { title: "Duplicate wins".to_string(), ..task("new") },
+    ]);
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&changes);
+    db.set_change_listener(move |change| sink.lock().unwrap().push((change.kind, change.ids.clone())));
+    let summary = ical::import_tasks(&db, &ics).unwrap();
+    assert_eq!((summary.added, summary.updated, summary.skipped), (1, 1, 0));
+    let changes = changes.lock().unwrap().clone();
+    assert_eq!(changes, [(ChangeKind::Updated, vec!["existing".to_string()]), (ChangeKind::Added, vec!["new".to_string()])], "one write");
+
+    let tasks = db.get_tasks().unwrap();
+    let ids: Vec<_> = tasks.iter().map(|t| t.id.as_str()).collect();
+    assert_eq!(ids, ["first", "existing", "new"], "existing tasks keep their position");
+    assert_eq!(tasks[1].title, "Renamed");
+    assert_eq!(tasks[1].priority, "Medium");
+    assert_eq!(tasks[2].title, "Duplicate wins");
+
+    // Importing the same file again only updates
+    let summary = ical::import_tasks(&db, &ics).unwrap();
+    assert_eq!((summary.added, summary.updated), (0, 2));
+}
+
+#[test]
+fn import_writes_nothing_when_a_task_is_invalid() {
+    let (_dir, db) = common::temp_db();
+    let ics = "BEGIN:VCALENDAR\nBEGIN:VTODO\nUID:ok\nSUMMARY:Fine\nEND:VTODO\n\
+BEGIN:VTODO\nUID:bad\nRRULE:not-a-rule\nEND:VTODO\nEND:VCALENDAR\n";
+
+    let err = ical::import_tasks(&db, ics).unwrap_err();
+    assert!(err.starts_with("bad:"), "{}", err);
+    assert!(db.get_tasks().unwrap().is_empty());
+}
@@ -0,0 +1,150 @@
+mod common;
+
+use tasker_lib::db::Task;
+use tasker_lib::plaintext::{self, PlainTextFormat};
+
+fn date(s: &str) -> Option<chrono::NaiveDate> {
+    Some(s.parse().unwrap())
+}
+
+#[test]
+fn parses_todotxt_fields() {
+    let input = "\
+(A) 2026-10-01 Call Mom +Family @phone due:2026-10-20 t:2026-10-18\n\
+x 2026-10-17 2026-10-01 File taxes +Admin pri:C\n\
+\n\
+Water plants rec:2w +Home +Garden see:https://example.com\n\
+Broken due:2026-13-01\n\
+(B)   \n";
+
+    let parsed = plaintext::parse(input, PlainTextFormat::TodoTxt);
+    assert_eq!(parsed.tasks.len(), 4);
+
+    let call = &parsed.tasks[0];
+    assert_eq!(call.title, "Call Mom");
+    assert_eq!(call.tags, ["phone"]);
+    assert_eq!(call.priority, "High");
+    assert_eq!(call.category, "Family");
+    assert_eq!(call.due_date, date("2026-10-20"));
+    assert_eq!(call.start_date, date("2026-10-18"));
+    assert!(!call.completed);
+
+    let taxes = &parsed.tasks[1];
+    assert!(taxes.completed);
+    assert_eq!(taxes.title, "File taxes");
+    assert_eq!(taxes.priority, "Low");
+
+    let plants = &parsed.tasks[2];
+    assert_eq!(plants.title, "Water plants +Garden see:https://example.com");
+    assert_eq!(plants.category, "Home");
+    assert_eq!(plants.priority, "Medium");
+    assert_eq!(plants.recurrence.as_deref(), Some("FREQ=WEEKLY;INTERVAL=2"));
+
+    assert_eq!(parsed.tasks[3].title, "Broken due:2026-13-01");
+    assert_eq!(parsed.tasks[3].category, "In-box");
+    assert_eq!(parsed.warnings, ["Line 5: could not read due:2026-13-01", "Line 6: task has no title"]);
+    assert_ne!(parsed.tasks[0].id, parsed.tasks[1].id);
+}
+
+#[test]
+fn todotxt_round_trip() {
+    let input = "(A) Call Mom +Family_Stuff @phone due:2026-10-20 t:2026-10-18 rec:1m\nx File taxes +Admin pri:C\n";
+    let parsed = plaintext::parse(input, PlainTextFormat::TodoTxt);
+    assert_eq!(parsed.tasks[0].category, "Family Stuff");
+    assert_eq!(plaintext::export(&parsed.tasks, PlainTextFormat::TodoTxt), input);
+}
+
+#[test]
+fn parses_nested_markdown_checklists() {
+    let input = "\
+# Project plan
+
+Some notes that are not tasks.
+
+- [ ] Release #q4 ⏫ 📅 2026-11-01
+  - [x] Write changelog for #123
+  - [ ] Tag version
+    1. [ ] Push tag 🔽
+- Plain bullet
+* [X] Announce 🔁 every 2 weeks
+
+## Home
+- [ ] Groceries 📅 soon
+";
+
+    let parsed = plaintext::parse(input, PlainTextFormat::Markdown);
+    let titles: Vec<_> = parsed.tasks.iter().map(|t| t.title.as_str()).collect();
+    assert_eq!(titles, ["Release", "Write changelog for #123", "Tag version", "Push tag", "Announce", "Groceries soon"]);
+
+    let [release, changelog, tag, push, announce, groceries] = &parsed.tasks[..] else {
+        panic!("expected six tasks");
+    };
+    assert_eq!(release.category, "Project plan");
+    assert_eq!(release.tags, ["q4"]);
+    assert!(changelog.tags.is_empty());
+    assert_eq!(release.priority, "High");
+    assert_eq!(release.due_date, date("2026-11-01"));
+    assert_eq!(release.parent_id, None);
+    assert_eq!(changelog.parent_id.as_ref(), Some(&release.id));
+    assert!(changelog.completed);
+    assert_eq!(tag.parent_id.as_ref(), Some(&release.id));
+    assert_eq!(push.parent_id.as_ref(), Some(&tag.id));
+    assert_eq!(push.priority, "Low");
+    assert_eq!(announce.parent_id, None);
+    assert!(announce.completed);
+    assert_eq!(announce.recurrence.as_deref(), Some("FREQ=WEEKLY;INTERVAL=2"));
+    assert_eq!(groceries.category, "Home");
+    assert_eq!(parsed.warnings, ["Line 13: 📅 is not followed by a YYYY-MM-DD date"]);
+}
+
+#[test]
+fn markdown_export_groups_by_category_and_nests_subtasks() {
+    let task = |id: &str, title: &str, category: &str| Task {
+        id: id.to_string(),
+        title: title.to_string(),
+        category: category.to_string(),
+        ..Default::default()
+    };
+    let tasks = vec![
+        Task {
+            priority: "High".to_string(),
+            due_date: date("2026-11-01"),
+            tags: vec!["q4".to_string()],
+            ..task("a", "Release", "Work")
+        },
+        task("b", "Groceries", "Home"),
+        Task { parent_id: Some("a".to_string()), completed: true, ..task("c", "Changelog", "Work") },
+        Task { parent_id: Some("c".to_string()), recurrence: Some("FREQ=DAILY".to_string()), ..task("d", "Proofread", "Work") },
+    ];
+
+    let markdown = plaintext::export(&tasks, PlainTextFormat::Markdown);
+    assert_eq!(
+        markdown,
+        "## Work\n\n- [ ] Release #q4 ⏫ 📅 2026-11-01\n  - [x] Changelog\n    - [ ] Proofread 🔁 every day\n\n## Home\n\n- [ ] Groceries\n"
+    );
+
+    let parsed = plaintext::parse(&markdown, PlainTextFormat::Markdown);
+    assert!(parsed.warnings.is_empty());
+    assert_eq!(parsed.tasks.len(), 4);
+    assert_eq!(parsed.tasks[2].parent_id.as_ref(), Some(&parsed.tasks[1].id));
+    assert_eq!(parsed.tasks[2].recurrence.as_deref(), Some("FREQ=DAILY"));
+    assert_eq!(parsed.tasks[3].category, "Home");
+}
+
+#[test]
+fn dry_run_writes_nothing_and_import_adds_tasks() {
+    let (_dir, db) = common::temp_db();
+    let input = "- [ ] One\n- [ ] Two 🛫 2026-11-02 📅 2026-11-01\n- [ ] Three\n";
+
+    let preview = plaintext::parse(input, PlainTextFormat::Markdown);
+    assert_eq!(preview.tasks.len(), 2, "start after due is rejected by validation");
+    assert_eq!(preview.warnings.len(), 1);
+    assert!(preview.warnings[0].starts_with("Line 2: "));
+    assert!(db.get_tasks().unwrap().is_empty());
+
+    let imported = plaintext::import(&db, input, PlainTextFormat::Markdown).unwrap();
+    let stored = db.get_tasks().unwrap();
+    let titles: Vec<_> = stored.iter().map(|t| t.title.as_str()).collect();
+    assert_eq!(titles, ["One", "Three"]);
+    assert_eq!(stored[0].id, imported.tasks[0].id);
+}
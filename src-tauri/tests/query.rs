@@ -0,0 +1,137 @@
+mod common;
+
+use chrono::{DateTime, TimeZone};
+use chrono_tz::Tz;
+use rusqlite::Connection;
+use tasker_lib::db::{Database, Task};
+use tasker_lib::query::{self, DateField, DateValue, Expr, Op, QueryError};
+
+// Monday 2026-10-19, 09:00 in Seoul
+fn now() -> DateTime<Tz> {
+    Tz::Asia__Seoul.with_ymd_and_hms(2026, 10, 19, 9, 0, 0).unwrap()
+}
+
+fn search(db: &Database, query: &str) -> Vec<String> {
+    query::search(db, query, now()).unwrap().into_iter().map(|t| t.id).collect()
+}
+
+fn err(query: &str) -> (String, String) {
+    let QueryError { message, start, end } = query::parse(query).unwrap_err();
+    // Offsets are UTF-16, the same as byte offsets for these ASCII queries
+    (message, query[start..end].to_string())
+}
+
+fn seed() -> (tempfile::TempDir, Database) {
+    let (dir, db) = common::temp_db();
+    let due = |d: &str| Some(d.parse().unwrap());
+    db.add_task(Task { priority: "High".to_string(), due_date: due("2026-10-21"), tags: vec!["release".to_string()], ..common::task("ship") }).unwrap();
+    db.add_task(Task { priority: "High".to_string(), due_date: due("2026-10-22"), tags: vec!["release".to_string(), "waiting".to_string()], ..common::task("blocked") }).unwrap();
+    db.add_task(Task { priority: "High".to_string(), due_date: due("2026-11-30"), ..common::task("later") }).unwrap();
+    db.add_task(Task { title: "Write release notes".to_string(), category: "Docs".to_string(), ..common::task("notes") }).unwrap();
+    db.add_task(Task { due_date: due("2026-10-10"), description: Some("100% done?".to_string()), ..common::task("overdue") }).unwrap();
+    (dir, db)
+}
+
+#[test]
+fn parses_fields_words_and_groups() {
+    let expr = query::parse(r#"due:<7d priority:high -tag:waiting list:"Side project" "release notes""#).unwrap();
+    assert_eq!(
+        expr,
+        Expr::And(vec![
+            Expr::Date(DateField::Due, Op::Lt, DateValue::Days(7)),
+            Expr::Priority("High".to_string()),
+            Expr::Not(Box::new(Expr::Tag("waiting".to_string()))),
+            Expr::List("Side project".to_string()),
+            Expr::Text("release notes".to_string()),
+        ])
+    );
+    assert_eq!(
+        query::parse("#urgent OR (p:low -due:>=2026-10-20)").unwrap(),
+        Expr::Or(vec![
+            Expr::Tag("urgent".to_string()),
+            Expr::And(vec![
+                Expr::Priority("Low".to_string()),
+                Expr::Not(Box::new(Expr::Date(DateField::Due, Op::Ge, DateValue::Date("2026-10-20".parse().unwrap())))),
+            ]),
+        ])
+    );
+    assert_eq!(query::parse("created:>-2w").unwrap(), Expr::Date(DateField::Created, Op::Gt, DateValue::Days(-14)));
+    assert_eq!(query::parse("  ").unwrap(), Expr::And(Vec::new()));
+}
+
+#[test]
+fn errors_point_at_the_bad_token() {
+    assert_eq!(err("due:<7d prio:high"), ("Unknown field \"prio\"".to_string(), "prio".to_string()));
+    assert_eq!(err("priority:urgent"), ("Unknown priority \"urgent\"".to_string(), "urgent".to_string()));
+    assert_eq!(err("p:high due:<soon"), ("Invalid date \"<soon\"".to_string(), "<soon".to_string()));
+    assert_eq!(err("release \"notes"), ("Missing closing quote".to_string(), "\"notes".to_string()));
+    assert_eq!(err("(p:high OR p:low"), ("Missing closing parenthesis".to_string(), "(".to_string()));
+    assert_eq!(err("p:high)"), ("Unmatched ')'".to_string(), ")".to_string()));
+    assert_eq!(err("OR p:high"), ("OR needs a term on both sides".to_string(), "OR".to_string()));
+    assert_eq!(err("tag: x"), ("Missing value after \"tag:\"".to_string(), "tag:".to_string()));
+    assert_eq!(err("release - notes").1, "-");
+
+    // Offsets count UTF-16 code units like JavaScript strings
+    let error = query::parse("café prio:high").unwrap_err();
+    assert_eq!((error.start, error.end), (5, 9));
+    assert_eq!(error.to_string(), "Unknown field \"prio\" (at 6)");
+}
+
+#[test]
+fn bad_dates_are_errors_not_panics() {
+    for value in ["내일", "3일", "7ｄ", "d", "+w"] {
+        let error = query::parse(&format!("due:{}", value)).unwrap_err();
+        assert_eq!(error.message, format!("Invalid date \"{}\"", value));
+        assert_eq!(error.start, 4);
+    }
+    assert_eq!(err("due:<100000000d"), ("Date too far away \"<100000000d\"".to_string(), "<100000000d".to_string()));
+    assert_eq!(err("created:-9999999999999w").0, "Date too far away \"-9999999999999w\"");
+    assert_eq!(query::parse("due:<36500d").unwrap(), Expr::Date(DateField::Due, Op::Lt, DateValue::Days(36_500)));
+
+    // Expressions built in code skip the parser's limit
+    let far = Expr::Date(DateField::Due, Op::Lt, DateValue::Days(i64::MAX / 2));
+    assert_eq!(query::compile(&far, now()).unwrap_err(), "Date out of range");
+    let last = Expr::Date(DateField::Created, Op::Eq, DateValue::Date(chrono::NaiveDate::MAX));
+    assert_eq!(query::compile(&last, now()).unwrap_err(), "Date out of range");
+}
+
+#[test]
+fn search_matches_the_example() {
+    let (_dir, db) = seed();
+    assert_eq!(search(&db, "due:<7d priority:high -tag:waiting list:Work"), ["ship"]);
+    assert_eq!(search(&db, "\"release notes\""), ["notes"]);
+    assert_eq!(search(&db, "release"), ["notes"], "words match the title, not tags");
+    assert_eq!(search(&db, "#release"), ["ship", "blocked"]);
+    assert_eq!(search(&db, "list:docs OR is:overdue"), ["notes", "overdue"]);
+    assert_eq!(search(&db, "-has:due"), ["notes"]);
+    assert_eq!(search(&db, "-due:<7d"), ["later", "notes"], "tasks without a due date are not due soon");
+    assert_eq!(search(&db, "due:tomorrow OR due:2026-10-22"), ["blocked"]);
+    assert_eq!(search(&db, "100%"), ["overdue"], "LIKE wildcards are literal");
+    assert_eq!(search(&db, "").len(), 5);
+}
+
+#[test]
+fn timestamps_compare_by_local_day() {
+    let (_dir, db) = seed();
+    let conn = Connection::open(db.path()).unwrap();
+    conn.execute("UPDATE tasks SET created_at = '2026-10-19T01:00:00.000Z'", []).unwrap();
+    // 2026-10-18 23:30 in Seoul is still the 18th there, 00:30 is the 19th
+    conn.execute("UPDATE tasks SET created_at = '2026-10-18T14:30:00.000Z' WHERE id = 'later'", []).unwrap();
+    conn.execute("UPDATE tasks SET created_at = '2026-10-18T15:30:00.000Z' WHERE id = 'notes'", []).unwrap();
+    assert_eq!(search(&db, "created:yesterday"), ["later"]);
+    assert_eq!(search(&db, "created:<today"), ["later"]);
+    assert_eq!(search(&db, "created:today").len(), 4);
+
+    conn.execute("UPDATE tasks SET completed = 1, completed_at = '2026-10-19T00:10:00.000Z' WHERE id = 'overdue'", []).unwrap();
+    assert_eq!(search(&db, "completed:today"), ["overdue"]);
+    assert!(search(&db, "completed:<today").is_empty());
+    assert_eq!(search(&db, "is:done"), ["overdue"]);
+}
+
+#[test]
+fn values_are_bound_not_spliced() {
+    let (_dir, db) = seed();
+    assert!(search(&db, "\"'); DROP TABLE tasks; --\"").is_empty());
+    assert!(search(&db, "list:\"Work' OR 1=1 --\"").is_empty());
+    assert_eq!(db.get_tasks().unwrap().len(), 5);
+}
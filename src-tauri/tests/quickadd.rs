@@ -0,0 +1,154 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use tasker_lib::quickadd::{self, QuickAdd, QuickAddSpan, SpanKind};
+
+// Wednesday morning
+fn now() -> NaiveDateTime {
+    "2026-10-14T10:00:00".parse().unwrap()
+}
+
+fn parse(text: &str) -> QuickAdd {
+    quickadd::parse(text, now(), "Asia/Seoul")
+}
+
+fn due(text: &str) -> Option<NaiveDate> {
+    parse(text).task.due_date
+}
+
+fn date(s: &str) -> Option<NaiveDate> {
+    Some(s.parse().unwrap())
+}
+
+fn time(text: &str) -> String {
+    parse(text).task.due_time.map(|t| t.format("%H:%M").to_string()).unwrap_or_default()
+}
+
+#[test]
+fn reads_every_field_and_leaves_the_title() {
+    let parsed = parse("Call mom tomorrow at 3pm !! #family #calls @Home_Stuff every week");
+    let task = &parsed.task;
+    assert_eq!(task.title, "Call mom");
+    assert_eq!(task.due_date, date("2026-10-15"));
+    assert_eq!(task.due_time.unwrap().format("%H:%M").to_string(), "15:00");
+    assert_eq!(task.timezone.as_deref(), Some("Asia/Seoul"));
+    assert_eq!(task.priority, "High");
+    assert_eq!(task.tags, ["family", "calls"]);
+    assert_eq!(task.category, "Home Stuff");
+    assert_eq!(task.recurrence.as_deref(), Some("FREQ=WEEKLY"));
+    assert!(task.validate().is_ok());
+
+    let kinds: Vec<_> = parsed.spans.iter().map(|s| s.kind).collect();
+    assert_eq!(
+        kinds,
+        [SpanKind::Date, SpanKind::Time, SpanKind::Priority, SpanKind::Tag, SpanKind::Tag, SpanKind::List, SpanKind::Recurrence]
+    );
+    // "at 3pm" includes the preposition
+    assert_eq!(parsed.spans[1], QuickAddSpan { start: 18, end: 24, kind: SpanKind::Time });
+}
+
+#[test]
+fn plain_text_stays_a_plain_task() {
+    let parsed = parse("Fix #123 before the release");
+    assert_eq!(parsed.task.title, "Fix #123 before the release");
+    assert!(parsed.task.tags.is_empty(), "issue numbers are not tags");
+    assert_eq!(parsed.task.category, "In-box");
+    assert_eq!(parsed.task.priority, "Medium");
+
+    let parsed = parse("Write report");
+    assert_eq!(parsed.task.title, "Write report");
+    assert!(parsed.spans.is_empty());
+    assert_eq!(parsed.task.due_date, None);
+}
+
+#[test]
+fn english_dates() {
+    assert_eq!(due("a today"), date("2026-10-14"));
+    assert_eq!(due("a fri"), date("2026-10-16"));
+    assert_eq!(due("a wednesday"), date("2026-10-14"), "a bare weekday includes today");
+    assert_eq!(due("a next fri"), date("2026-10-23"));
+    assert_eq!(due("a next week"), date("2026-10-19"));
+    assert_eq!(due("a next month"), date("2026-11-01"));
+    assert_eq!(due("a in 2 weeks"), date("2026-10-28"));
+    assert_eq!(due("a in a month"), date("2026-11-14"));
+    assert_eq!(due("a by 2026-11-01"), date("2026-11-01"));
+    assert_eq!(due("a on oct 20"), date("2026-10-20"));
+    assert_eq!(due("a 2 oct"), date("2027-10-02"), "passed dates mean next year");
+    assert_eq!(due("a 12/25"), date("2026-12-25"));
+    assert_eq!(due("a in 2 hours"), None);
+    assert_eq!(due("a in 400000000 years"), None, "too far away, not an overflow");
+}
+
+#[test]
+fn korean_dates() {
+    assert_eq!(due("보고서 내일"), date("2026-10-15"));
+    assert_eq!(due("보고서 모레까지"), date("2026-10-16"));
+    assert_eq!(due("회의 다음주 월요일"), date("2026-10-19"));
+    assert_eq!(due("회의 다음 주 금요일까지"), date("2026-10-23"));
+    assert_eq!(due("회의 다음주"), date("2026-10-19"));
+    assert_eq!(due("회의 이번주 금"), date("2026-10-16"));
+    assert_eq!(due("회의 금요일에"), date("2026-10-16"));
+    assert_eq!(due("정산 3일 후"), date("2026-10-17"));
+    assert_eq!(due("정산 2주뒤"), date("2026-10-28"));
+    assert_eq!(due("정산 10월 20일"), date("2026-10-20"));
+    assert_eq!(due("정산 다음달"), date("2026-11-01"));
+    assert_eq!(due("정산 400000000년 후"), None);
+
+    let parsed = parse("보고서 제출 내일까지");
+    assert_eq!(parsed.task.title, "보고서 제출");
+}
+
+#[test]
+fn times() {
+    assert_eq!(time("a 3:30pm"), "15:30");
+    assert_eq!(time("a 3 pm"), "15:00");
+    assert_eq!(time("a 12am"), "00:00");
+    assert_eq!(time("a 17:45"), "17:45");
+    assert_eq!(time("a noon"), "12:00");
+    assert_eq!(time("a 오후 3시 30분"), "15:30");
+    assert_eq!(time("a 오전 9시반"), "09:30");
+    assert_eq!(time("a 3시에"), "15:00");
+    assert_eq!(time("a 9시"), "09:00");
+    assert_eq!(time("a 13"), "");
+
+    // Without a date, the next time the clock shows it
+    assert_eq!(due("a 3pm"), date("2026-10-14"));
+    assert_eq!(due("a 9am"), date("2026-10-15"));
+    assert_eq!(parse("a").task.timezone, None);
+}
+
+#[test]
+fn recurrence_and_priority() {
+    let parsed = parse("Standup every monday");
+    assert_eq!(parsed.task.title, "Standup");
+    assert_eq!(parsed.task.recurrence.as_deref(), Some("FREQ=WEEKLY;BYDAY=MO"));
+    assert_eq!(parsed.task.due_date, date("2026-10-19"), "starts on the first occurrence");
+
+    assert_eq!(parse("a every 2 weeks").task.recurrence.as_deref(), Some("FREQ=WEEKLY;INTERVAL=2"));
+    assert_eq!(parse("a every other month").task.recurrence.as_deref(), Some("FREQ=MONTHLY;INTERVAL=2"));
+    assert_eq!(parse("a every weekday").task.recurrence.as_deref(), Some("FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR"));
+    assert_eq!(parse("a daily").task.due_date, date("2026-10-14"));
+    assert_eq!(parse("운동 매주 금요일").task.recurrence.as_deref(), Some("FREQ=WEEKLY;BYDAY=FR"));
+    assert_eq!(parse("운동 월요일마다").task.due_date, date("2026-10-19"));
+    assert_eq!(parse("a every month on the 1st").task.recurrence.as_deref(), Some("FREQ=MONTHLY"));
+
+    assert_eq!(parse("a p3").task.priority, "Low");
+    // The first marker wins, later ones stay in the title
+    let parsed = parse("Ship it p1 p3");
+    assert_eq!(parsed.task.priority, "High");
+    assert_eq!(parsed.task.title, "Ship it p3");
+}
+
+#[test]
+fn spans_use_javascript_string_offsets() {
+    let text = "🎉 파티 준비 내일 #행사";
+    let parsed = parse(text);
+    assert_eq!(parsed.task.title, "🎉 파티 준비");
+    assert_eq!(
+        parsed.spans,
+        [
+            QuickAddSpan { start: 9, end: 11, kind: SpanKind::Date },
+            QuickAddSpan { start: 12, end: 15, kind: SpanKind::Tag },
+        ]
+    );
+    let utf16: Vec<u16> = text.encode_utf16().collect();
+    assert_eq!(String::from_utf16(&utf16[12..15]).unwrap(), "#행사");
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use tasker_lib::db::{Database, Task};
+use tasker_lib::reminders;
+
+fn task(id: &str) -> Task {
+    Task { title: "Pay rent".to_string(), category: "Home".to_string(), ..common::task(id) }
+}
+
+// Due 2026-10-20 15:00 in Seoul, 06:00 UTC
+fn timed(id: &str) -> Task {
+    Task {
+        due_date: Some("2026-10-20".parse().unwrap()),
+        due_time: Some("15:00".parse().unwrap()),
+        timezone: Some("Asia/Seoul".to_string()),
+        ..task(id)
+    }
+}
+
+fn ts(hour: u32, minute: u32) -> i64 {
+    Utc.with_ymd_and_hms(2026, 10, 20, hour, minute, 0).unwrap().timestamp()
+}
+
+fn deliver(db: &Database, now: i64) -> Vec<String> {
+    let mut notified = Vec::new();
+    let due = reminders::deliver_due(db, now, &mut |due| notified.push(reminders::message(due))).unwrap();
+    assert_eq!(due.len(), notified.len());
+    notified
+}
+
+#[test]
+fn offset_reminders_follow_the_due_time_and_fire_once() {
+    let (_dir, db) = common::temp_db();
+    db.add_task(timed("a")).unwrap();
+    let reminder = reminders::add(&db, "a", None, Some(30)).unwrap();
+    assert_eq!(reminders::scheduled_at(&reminder, &timed("a")), Some(ts(5, 30)));
+
+    assert!(deliver(&db, ts(5, 29)).is_empty());
+    assert_eq!(deliver(&db, ts(5, 30)), ["Due Oct 20, 15:00"]);
+    assert!(deliver(&db, ts(5, 31)).is_empty(), "delivered only once");
+
+    // Moving the due date re-arms the reminder
+    db.update_task(Task { due_date: Some("2026-10-21".parse().unwrap()), ..timed("a") }).unwrap();
+    assert!(deliver(&db, ts(6, 0)).is_empty());
+    assert_eq!(deliver(&db, ts(5, 30) + 86_400), ["Due Oct 21, 15:00"]);
+}
+
+#[test]
+fn missed_reminders_are_delivered_once_on_the_next_check() {
+    let (_dir, db) = common::temp_db();
+    db.add_task(timed("a")).unwrap();
+    db.add_task(Task { title: "Done already".to_string(), completed: true, ..timed("b") }).unwrap();
+    reminders::add(&db, "a", Some(ts(1, 0)), None).unwrap();
+    reminders::add(&db, "a", None, Some(0)).unwrap();
+    reminders::add(&db, "b", Some(ts(1, 0)), None).unwrap();
+
+    // The app was closed all morning
+    let delivered = reminders::deliver_due(&db, ts(9, 0), &mut |_| {}).unwrap();
+    let times: Vec<_> = delivered.iter().map(|d| (d.at, d.missed)).collect();
+    assert_eq!(times, [(ts(1, 0), true), (ts(6, 0), true)], "oldest first, completed tasks skipped");
+    assert!(reminders::message(&delivered[0]).starts_with("Missed reminder · "));
+    assert!(deliver(&db, ts(9, 0)).is_empty());
+}
+
+#[test]
+fn snooze_fires_again_and_dismiss_silences() {
+    let (_dir, db) = common::temp_db();
+    db.add_task(timed("a")).unwrap();
+    let reminder = reminders::add(&db, "a", Some(ts(5, 0)), None).unwrap();
+    assert_eq!(deliver(&db, ts(5, 0)).len(), 1);
+
+    let snoozed = reminders::snooze(&db, &reminder.id, 10, ts(5, 1)).unwrap();
+    assert_eq!(snoozed.snoozed_until, Some(ts(5, 11)));
+    assert!(deliver(&db, ts(5, 10)).is_empty());
+    assert_eq!(deliver(&db, ts(5, 11)).len(), 1);
+
+    reminders::snooze(&db, &reminder.id, 10, ts(5, 12)).unwrap();
+    let dismissed = reminders::dismiss(&db, &reminder.id).unwrap();
+    assert_eq!(dismissed.snoozed_until, None);
+    assert!(deliver(&db, ts(6, 0)).is_empty());
+
+    assert!(reminders::snooze(&db, &reminder.id, 0, ts(6, 0)).is_err());
+    assert!(reminders::snooze(&db, &reminder.id, i64::MAX, ts(6, 0)).is_err());
+    assert!(reminders::dismiss(&db, "missing").is_err());
+}
+
+#[test]
+fn delivered_snooze_is_cleared_and_a_moved_due_date_fires_again() {
+    let (_dir, db) = common::temp_db();
+    db.add_task(timed("a")).unwrap();
+    let reminder = reminders::add(&db, "a", None, Some(30)).unwrap();
+    assert_eq!(deliver(&db, ts(5, 30)).len(), 1);
+    reminders::snooze(&db, &reminder.id, 10, ts(5, 31)).unwrap();
+    assert_eq!(deliver(&db, ts(5, 41)).len(), 1);
+
+    let stored = &db.get_reminders("a").unwrap()[0];
+    assert_eq!((stored.snoozed_until, stored.fired_for), (None, Some(ts(5, 30))));
+    assert!(deliver(&db, ts(5, 50)).is_empty());
+
+    db.update_task(Task { due_date: Some("2026-10-21".parse().unwrap()), ..timed("a") }).unwrap();
+    assert_eq!(deliver(&db, ts(5, 30) + 86_400), ["Due Oct 21, 15:00"]);
+}
+
+#[test]
+fn rejects_reminders_that_cant_be_scheduled() {
+    let (_dir, db) = common::temp_db();
+    db.add_task(task("undated")).unwrap();
+    assert!(reminders::add(&db, "undated", None, Some(10)).is_err());
+    assert!(reminders::add(&db, "undated", None, None).is_err());
+    assert!(reminders::add(&db, "undated", Some(ts(5, 0)), Some(10)).is_err());
+    assert!(reminders::add(&db, "missing", Some(ts(5, 0)), None).is_err());
+    assert!(reminders::add(&db, "undated", Some(ts(5, 0)), None).is_ok());
+    db.add_task(timed("dated")).unwrap();
+    assert!(reminders::add(&db, "dated", None, Some(i64::MAX / 2)).is_err());
+
+    // Deleting the task deletes its reminders
+    db.delete_task("undated").unwrap();
+    assert!(db.get_reminders("undated").unwrap().is_empty());
+}
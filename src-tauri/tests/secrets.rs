@@ -0,0 +1,179 @@
+mod common;
+
+use common::temp_db;
+use tasker_lib::db::{Account, AccountTokens, Database};
+use tasker_lib::google::{self, GoogleCredentials};
+use tasker_lib::secrets::{self, KeyBackend, PassphraseBackend, SecretStore};
+
+fn add_account(db: &Database, access_token: &str, refresh_token: &str) {
+    db.upsert_account(&Account {
+        provider: "google".to_string(),
+        account_id: "acct".to_string(),
+        email: "acct@example.com".to_string(),
+        name: "Account".to_string(),
+        picture: None,
+    }).unwrap();
+    db.set_account_tokens("google", "acct", &AccountTokens {
+        access_token: access_token.to_string(),
+        refresh_token: Some(refresh_token.to_string()),
+        expires_at: Some(42),
+    }).unwrap();
+}
+
+fn raw_tokens(dir: &tempfile::TempDir) -> (String, String) {
+    let conn = rusqlite::Connection::open(dir.path().join("tasks.db")).unwrap();
+    conn.query_row(
+        "SELECT access_token, refresh_token FROM accounts WHERE account_id = 'acct'",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).unwrap()
+}
+
+#[test]
+fn store_round_trips_and_rejects_other_keys() {
+    let store = SecretStore::new([7u8; 32]);
+    let sealed = store.encrypt("ya29.token").unwrap();
+    assert!(secrets::is_encrypted(&sealed));
+    assert!(!sealed.contains("ya29"));
+    assert_eq!(store.decrypt(&sealed).unwrap(), "ya29.token");
+
+    // Fresh nonce every time
+    assert_ne!(sealed, store.encrypt("ya29.token").unwrap());
+    assert!(SecretStore::new([8u8; 32]).decrypt(&sealed).is_err());
+}
+
+#[test]
+fn passphrase_backend_is_deterministic_per_salt() {
+    let a = PassphraseBackend::new("correct horse", vec![1; 16]).load_key().unwrap();
+    let b = PassphraseBackend::new("correct horse", vec![1; 16]).load_key().unwrap();
+    let c = PassphraseBackend::new("correct horse", vec![2; 16]).load_key().unwrap();
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+}
+
+#[test]
+fn existing_plaintext_tokens_are_encrypted_on_open() {
+    let (dir, db) = temp_db();
+    add_account(&db, "plain-access", "plain-refresh");
+    assert_eq!(raw_tokens(&dir).0, "plain-access");
+
+    secrets::open(&db).unwrap();
+
+    let (access, refresh) = raw_tokens(&dir);
+    assert!(secrets::is_encrypted(&access));
+    assert!(secrets::is_encrypted(&refresh));
+    assert!(dir.path().join("secret.key").exists());
+
+    let tokens = db.get_account_tokens("google", "acct").unwrap().unwrap();
+    assert_eq!(tokens.access_token, "plain-access");
+    assert_eq!(tokens.refresh_token.as_deref(), Some("plain-refresh"));
+    assert_eq!(tokens.expires_at, Some(42));
+}
+
+#[test]
+fn legacy_setting_tokens_are_encrypted_on_open_without_network() {
+    let (dir, db) = temp_db();
+    db.set_setting("google_access_token", "plain-access").unwrap();
+    db.set_setting("google_refresh_token", "plain-refresh").unwrap();
+
+    secrets::open(&db).unwrap();
+
+    let conn = rusqlite::Connection::open(dir.path().join("tasks.db")).unwrap();
+    let stored: Vec<String> = conn
+        .prepare("SELECT value FROM settings WHERE key LIKE 'google_%_token' ORDER BY key")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(stored.len(), 2);
+    assert!(stored.iter().all(|value| secrets::is_encrypted(value)), "{:?}", stored);
+    assert_eq!(db.get_secret_setting("google_refresh_token").unwrap().as_deref(), Some("plain-refresh"));
+
+    // Opening again leaves sealed values alone
+    secrets::open(&db).unwrap();
+    assert_eq!(db.get_secret_setting("google_access_token").unwrap().as_deref(), Some("plain-access"));
+}
+
+#[test]
+fn new_tokens_are_written_encrypted() {
+    let (dir, db) = temp_db();
+    secrets::open(&db).unwrap();
+    add_account(&db, "access", "refresh");
+
+    assert!(secrets::is_encrypted(&raw_tokens(&dir).0));
+
+    // A second handle to the same files reads them back with the same key file
+    let reopened = Database::from_path(dir.path().join("tasks.db").to_str().unwrap());
+    secrets::open(&reopened).unwrap();
+    assert_eq!(reopened.get_account_tokens("google", "acct").unwrap().unwrap().access_token, "access");
+}
+
+#[test]
+fn oauth_client_secret_is_sealed() {
+    let (_dir, db) = temp_db();
+    db.set_setting("google_client_secret", "plain-secret").unwrap();
+    secrets::open(&db).unwrap();
+    assert!(secrets::is_encrypted(&db.get_setting("google_client_secret").unwrap().unwrap()), "sealed on open");
+
+    let credentials = GoogleCredentials { client_id: "client".to_string(), client_secret: " new-secret ".to_string() };
+    google::save_credentials(&db, &credentials).unwrap();
+    assert!(secrets::is_encrypted(&db.get_setting("google_client_secret").unwrap().unwrap()));
+    assert_eq!(google::load_credentials(&db).unwrap().client_secret, "new-secret");
+}
+
+#[test]
+fn passphrase_protects_tokens_until_unlocked() {
+    let (dir, db) = temp_db();
+    secrets::open(&db).unwrap();
+    add_account(&db, "access", "refresh");
+
+    secrets::change_backend(&db, Some("hunter2")).unwrap();
+    assert_eq!(secrets::status(&db).unwrap().backend, "passphrase");
+    assert_eq!(db.get_account_tokens("google", "acct").unwrap().unwrap().access_token, "access");
+
+    // Simulate a restart
+    let restarted = Database::from_path(dir.path().join("tasks.db").to_str().unwrap());
+    secrets::open(&restarted).unwrap();
+    assert!(!secrets::status(&restarted).unwrap().unlocked);
+    let err = restarted.get_account_tokens("google", "acct").unwrap_err();
+    assert!(err.to_string().contains("locked"), "{}", err);
+
+    assert!(secrets::unlock(&restarted, "wrong").is_err());
+    assert!(!secrets::status(&restarted).unwrap().unlocked);
+
+    secrets::unlock(&restarted, "hunter2").unwrap();
+    assert_eq!(restarted.get_account_tokens("google", "acct").unwrap().unwrap().refresh_token.as_deref(), Some("refresh"));
+}
+
+#[test]
+fn switching_back_to_key_file_re_encrypts() {
+    let (dir, db) = temp_db();
+    secrets::open(&db).unwrap();
+    add_account(&db, "access", "refresh");
+    secrets::change_backend(&db, Some("hunter2")).unwrap();
+    secrets::change_backend(&db, None).unwrap();
+
+    let restarted = Database::from_path(dir.path().join("tasks.db").to_str().unwrap());
+    secrets::open(&restarted).unwrap();
+    assert!(secrets::status(&restarted).unwrap().unlocked);
+    assert_eq!(restarted.get_account_tokens("google", "acct").unwrap().unwrap().access_token, "access");
+}
+
+#[test]
+fn wrong_key_file_leaves_secrets_locked() {
+    let (dir, db) = temp_db();
+    secrets::open(&db).unwrap();
+    add_account(&db, "access", "refresh");
+    std::fs::remove_file(dir.path().join("secret.key")).unwrap();
+
+    let restarted = Database::from_path(dir.path().join("tasks.db").to_str().unwrap());
+    assert!(secrets::open(&restarted).is_err());
+    assert!(restarted.get_account_tokens("google", "acct").is_err());
+    // Writes must not fall back to plaintext either
+    assert!(restarted.set_account_tokens("google", "acct", &AccountTokens {
+        access_token: "new".to_string(),
+        refresh_token: None,
+        expires_at: None,
+    }).is_err());
+}
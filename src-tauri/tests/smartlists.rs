@@ -0,0 +1,117 @@
+mod common;
+
+use chrono::{DateTime, TimeZone};
+use chrono_tz::Tz;
+use tasker_lib::db::{Database, SmartList, Task};
+use tasker_lib::smartlists::{self, SmartFilter, SmartSort};
+
+fn task(id: &str, priority: &str, category: &str, due: Option<&str>) -> Task {
+    Task {
+        priority: priority.to_string(),
+        category: category.to_string(),
+        due_date: due.map(|d| d.parse().unwrap()),
+        ..common::task(id)
+    }
+}
+
+fn now() -> DateTime<Tz> {
+    Tz::UTC.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap()
+}
+
+fn ids(tasks: &[Task]) -> Vec<&str> {
+    tasks.iter().map(|t| t.id.as_str()).collect()
+}
+
+fn seed() -> (tempfile::TempDir, Database) {
+    let (dir, db) = common::temp_db();
+    db.add_task(task("overdue", "High", "Work", Some("2026-10-15"))).unwrap();
+    db.add_task(task("personal", "High", "Personal", Some("2026-10-20"))).unwrap();
+    db.add_task(task("later", "High", "Work", Some("2026-11-30"))).unwrap();
+    db.add_task(task("low", "Low", "Work", Some("2026-10-21"))).unwrap();
+    db.add_task(task("soon", "High", "Home", Some("2026-10-22"))).unwrap();
+    db.add_task(task("undated", "High", "Work", None)).unwrap();
+    (dir, db)
+}
+
+#[test]
+fn high_priority_due_this_week_not_in_personal() {
+    let (_dir, db) = seed();
+    let filter = SmartFilter {
+        completed: Some(false),
+        priorities: vec!["High".to_string()],
+        exclude_categories: vec!["Personal".to_string()],
+        due_within_days: Some(7),
+        ..Default::default()
+    };
+    let list = smartlists::create(&db, " This week ", filter, SmartSort::DueDate).unwrap();
+    assert_eq!(list.name, "This week");
+
+    let shown = smartlists::tasks(&db, &list.id, now()).unwrap();
+    assert_eq!(ids(&shown), ["overdue", "soon"]);
+
+    // The same list written as a query
+    let query = SmartFilter { query: Some("is:open p:high -list:personal due:<=7d".to_string()), ..Default::default() };
+    let written = smartlists::create(&db, "This week (query)", query, SmartSort::DueDate).unwrap();
+    assert_eq!(ids(&smartlists::tasks(&db, &written.id, now()).unwrap()), ["overdue", "soon"]);
+    db.delete_smart_list(&written.id).unwrap();
+
+    // Counts follow the tasks
+    db.update_task(Task { completed: true, ..task("soon", "High", "Home", Some("2026-10-22")) }).unwrap();
+    let counts = smartlists::counts(&db, now()).unwrap();
+    assert_eq!((counts[0].list.id.as_str(), counts[0].count), (list.id.as_str(), 1));
+}
+
+#[test]
+fn tags_text_and_sort_orders() {
+    let (_dir, db) = seed();
+    db.update_task(Task { tags: vec!["release".to_string()], description: Some("Ship the BETA".to_string()), ..task("low", "Low", "Work", Some("2026-10-21")) }).unwrap();
+    db.update_task(Task { tags: vec!["release".to_string(), "waiting".to_string()], ..task("later", "High", "Work", Some("2026-11-30")) }).unwrap();
+
+    let release = SmartFilter { tags: vec!["release".to_string()], exclude_tags: vec!["waiting".to_string()], ..Default::default() };
+    let list = smartlists::create(&db, "Release", release, SmartSort::Position).unwrap();
+    assert_eq!(ids(&smartlists::tasks(&db, &list.id, now()).unwrap()), ["low"]);
+
+    let text = SmartFilter { text: Some("beta".to_string()), ..Default::default() };
+    let list = smartlists::update(&db, SmartList { filter: text, ..list }).unwrap();
+    assert_eq!(ids(&smartlists::tasks(&db, &list.id, now()).unwrap()), ["low"]);
+
+    let all = smartlists::create(&db, "Everything", SmartFilter::default(), SmartSort::Priority).unwrap();
+    let shown = smartlists::tasks(&db, &all.id, now()).unwrap();
+    assert_eq!(ids(&shown).last(), Some(&"low"), "High before Low");
+    assert_eq!(ids(&shown)[0], "overdue", "ties keep the task list order");
+
+    let by_due = SmartList { sort: SmartSort::DueDate, ..all };
+    smartlists::update(&db, by_due.clone()).unwrap();
+    assert_eq!(ids(&smartlists::tasks(&db, &by_due.id, now()).unwrap()).last(), Some(&"undated"));
+
+    let lists: Vec<_> = db.get_smart_lists().unwrap().into_iter().map(|l| l.name).collect();
+    assert_eq!(lists, ["Release", "Everything"], "in the order they were created");
+    db.delete_smart_list(&by_due.id).unwrap();
+    assert!(smartlists::tasks(&db, &by_due.id, now()).is_err());
+}
+
+#[test]
+fn rejects_invalid_lists() {
+    let (_dir, db) = common::temp_db();
+    assert!(smartlists::create(&db, "  ", SmartFilter::default(), SmartSort::Position).is_err());
+    let bad_priority = SmartFilter { priorities: vec!["Urgent".to_string()], ..Default::default() };
+    assert!(smartlists::create(&db, "Bad", bad_priority, SmartSort::Position).is_err());
+    let bad_days = SmartFilter { due_within_days: Some(-1), ..Default::default() };
+    assert!(smartlists::create(&db, "Bad", bad_days, SmartSort::Position).is_err());
+    let far = SmartFilter { due_within_days: Some(100_000_000), ..Default::default() };
+    assert!(smartlists::create(&db, "Bad", far, SmartSort::Position).is_err());
+    assert!(serde_json::from_str::<SmartFilter>(r#"{"priority": ["High"]}"#).is_err(), "typos are errors");
+    let bad_query = SmartFilter { query: Some("due:soon".to_string()), ..Default::default() };
+    let err = smartlists::create(&db, "Bad", bad_query, SmartSort::Position).unwrap_err();
+    assert!(err.starts_with("Invalid query: Invalid date \"soon\""), "{}", err);
+
+    let list = smartlists::create(&db, "Ok", SmartFilter::default(), SmartSort::Position).unwrap();
+    assert!(smartlists::update(&db, SmartList { id: "missing".to_string(), ..list.clone() }).is_err());
+    assert_eq!(db.get_smart_lists().unwrap().len(), 1);
+
+    // A list saved before the limit is an error, not a panic
+    let stored = SmartList { filter: SmartFilter { due_within_days: Some(i64::MAX / 2), ..Default::default() }, ..list };
+    db.update_smart_list(&stored).unwrap();
+    let err = smartlists::counts(&db, now()).unwrap_err();
+    assert_eq!(err, "Ok: Date out of range");
+}
@@ -0,0 +1,201 @@
+mod common;
+
+use chrono::{NaiveDate, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+use rusqlite::Connection;
+use serde_json::json;
+use tasker_lib::db::{Due, Task, TaskPatch};
+
+fn task(id: &str) -> Task {
+    Task {
+        title: "Task".to_string(),
+        priority: "high".to_string(),
+        category: "Personal".to_string(),
+        ..common::task(id)
+    }
+}
+
+fn date(s: &str) -> NaiveDate {
+    s.parse().unwrap()
+}
+
+fn time(s: &str) -> NaiveTime {
+    s.parse().unwrap()
+}
+
+#[test]
+fn due_fields_round_trip() {
+    let (_dir, db) = common::temp_db();
+    db.add_task(Task {
+        due_date: Some(date("2026-03-10")),
+        due_time: Some(time("23:45")),
+        timezone: Some("Asia/Seoul".to_string()),
+        start_date: Some(date("2026-03-01")),
+        ..task("a")
+    }).unwrap();
+
+    let stored = db.get_task_by_id("a").unwrap().unwrap();
+    assert_eq!(stored.due_date, Some(date("2026-03-10")));
+    assert_eq!(stored.due_time, Some(time("23:45")));
+    assert_eq!(stored.timezone.as_deref(), Some("Asia/Seoul"));
+    assert_eq!(stored.start_date, Some(date("2026-03-01")));
+}
+
+#[test]
+fn due_near_midnight_keeps_its_date() {
+    let timed = Task {
+        due_date: Some(date("2026-01-10")),
+        due_time: Some(time("00:30")),
+        timezone: Some("Asia/Seoul".to_string()),
+        ..task("a")
+    };
+    let due = timed.due().unwrap().unwrap();
+    assert_eq!(due.date(), date("2026-01-10"));
+    assert_eq!(due, Due::At(Tz::Asia__Seoul.with_ymd_and_hms(2026, 1, 10, 0, 30, 0).unwrap()));
+
+    let all_day = Task { due_date: Some(date("2026-01-10")), ..task("b") };
+    assert_eq!(all_day.due().unwrap(), Some(Due::AllDay(date("2026-01-10"))));
+}
+
+#[test]
+fn invalid_due_is_rejected_on_write() {
+    let (_dir, db) = common::temp_db();
+
+    let no_timezone = Task { due_date: Some(date("2026-01-10")), due_time: Some(time("09:00")), ..task("a") };
+    assert!(db.add_task(no_timezone).is_err());
+
+    let no_date = Task { due_time: Some(time("09:00")), timezone: Some("Asia/Seoul".to_string()), ..task("b") };
+    assert!(db.add_task(no_date).is_err());
+
+    let unknown_zone = Task { due_date: Some(date("2026-01-10")), timezone: Some("Mars/Olympus".to_string()), ..task("c") };
+    let err = db.add_task(unknown_zone).unwrap_err();
+    assert!(err.to_string().contains("Unknown timezone"), "{}", err);
+
+    let starts_late = Task { due_date: Some(date("2026-01-10")), start_date: Some(date("2026-01-11")), ..task("d") };
+    assert!(db.add_task(starts_late).is_err());
+
+    // Skipped by the spring-forward DST change
+    let missing_time = Task {
+        due_date: Some(date("2026-03-08")),
+        due_time: Some(time("02:30")),
+        timezone: Some("America/New_York".to_string()),
+        ..task("e")
+    };
+    assert!(db.add_task(missing_time).is_err());
+
+    db.add_task(task("f")).unwrap();
+    let mut updated = task("f");
+    updated.due_time = Some(time("09:00"));
+    assert!(db.update_task(updated).is_err());
+
+    assert_eq!(db.get_tasks().unwrap().len(), 1);
+}
+
+#[test]
+fn cleared_date_inputs_deserialize_as_none() {
+    let task: Task = serde_json::from_value(json!({
+        "id": "a",
+        "title": "Task",
+        "completed": false,
+        "priority": "High",
+        "category": "Work",
+        "due_date": "",
+        "position": 0
+    })).unwrap();
+    assert!(task.due_date.is_none());
+    assert!(task.due_time.is_none());
+
+    let task: Task = serde_json::from_value(json!({
+        "id": "a",
+        "title": "Task",
+        "completed": false,
+        "priority": "High",
+        "category": "Work",
+        "due_date": "2026-01-10",
+        "due_time": "09:30",
+        "timezone": "Asia/Seoul",
+        "position": 0
+    })).unwrap();
+    assert_eq!(serde_json::to_value(&task).unwrap()["due_time"], "09:30");
+}
+
+#[test]
+fn legacy_empty_due_date_reads_as_none() {
+    let (dir, db) = common::temp_db();
+    let conn = Connection::open(dir.path().join("tasks.db")).unwrap();
+    conn.execute(
+        "INSERT INTO tasks (id, title, completed, priority, category, due_date, position) VALUES ('old', 'Old', 0, 'Low', 'Personal', '', 0)",
+        [],
+    ).unwrap();
+
+    assert!(db.get_task_by_id("old").unwrap().unwrap().due_date.is_none());
+}
+
+#[test]
+fn patch_changes_only_given_fields_and_rejects_stale_revisions() {
+    let (_dir, db) = common::temp_db();
+    db.add_task(Task { description: Some("Notes".to_string()), due_date: Some(date("2026-03-10")), ..task("a") }).unwrap();
+    assert_eq!(db.get_task_by_id("a").unwrap().unwrap().revision, 0);
+
+    // Two windows loaded revision 0 and edit different fields
+    let first: TaskPatch = serde_json::from_value(json!({"title": "Renamed"})).unwrap();
+    let patched = db.patch_task("a", &first, Some(0)).unwrap();
+    assert_eq!((patched.title.as_str(), patched.revision), ("Renamed", 1));
+    assert_eq!(patched.description.as_deref(), Some("Notes"));
+
+    let second: TaskPatch = serde_json::from_value(json!({"due_date": null})).unwrap();
+    let err = db.patch_task("a", &second, Some(0)).unwrap_err().to_string();
+    assert!(err.starts_with("Conflict:"), "{}", err);
+    assert_eq!(db.get_task_by_id("a").unwrap().unwrap().due_date, Some(date("2026-03-10")));
+
+    // Without a revision the patch applies to whatever is current
+    let stored = db.patch_task("a", &second, None).unwrap();
+    assert_eq!((stored.title.as_str(), stored.due_date, stored.revision), ("Renamed", None, 2));
+    assert_eq!(db.get_task_by_id("a").unwrap().unwrap().revision, 2);
+
+    // Full updates bump the revision too
+    db.update_task(stored).unwrap();
+    assert_eq!(db.get_task_by_id("a").unwrap().unwrap().revision, 3);
+
+    let invalid: TaskPatch = serde_json::from_value(json!({"start_date": "2026-04-01", "due_date": "2026-03-01"})).unwrap();
+    assert!(db.patch_task("a", &invalid, Some(3)).is_err());
+    assert!(db.patch_task("missing", &first, None).is_err());
+}
+
+#[test]
+fn timestamps_are_kept_by_the_backend() {
+    let (_dir, db) = common::temp_db();
+    let sent = "2001-01-01T00:00:00Z".parse().unwrap();
+    db.add_task(Task { created_at: Some(sent), completed_at: Some(sent), ..task("a") }).unwrap();
+    let added = db.get_task_by_id("a").unwrap().unwrap();
+    let created = added.created_at.expect("created_at is set");
+    assert!(created > sent, "values sent by the frontend are ignored");
+    assert_eq!((added.updated_at, added.completed_at), (Some(created), None));
+
+    let done = db.patch_task("a", &serde_json::from_value(json!({"completed": true})).unwrap(), None).unwrap();
+    let completed = done.completed_at.expect("completed_at is set");
+    assert_eq!(done.created_at, Some(created));
+    assert!(done.updated_at >= Some(created));
+
+    // Staying completed keeps the time it was completed, reopening clears it
+    let renamed = db.patch_task("a", &serde_json::from_value(json!({"title": "Renamed"})).unwrap(), None).unwrap();
+    assert_eq!(renamed.completed_at, Some(completed));
+    db.update_task(Task { completed: false, ..renamed }).unwrap();
+    assert_eq!(db.get_task_by_id("a").unwrap().unwrap().completed_at, None);
+}
+
+#[test]
+fn rows_from_before_timestamps_are_backfilled() {
+    let (dir, db) = common::temp_db();
+    let conn = Connection::open(dir.path().join("tasks.db")).unwrap();
+    conn.execute(
+        "INSERT INTO tasks (id, title, completed, priority, category, position) VALUES ('open', 'Open', 0, 'Low', 'Personal', 0), ('done', 'Done', 1, 'Low', 'Personal', 1)",
+        [],
+    ).unwrap();
+    db.init().unwrap();
+
+    let open = db.get_task_by_id("open").unwrap().unwrap();
+    let done = db.get_task_by_id("done").unwrap().unwrap();
+    assert!(open.created_at.is_some() && open.updated_at.is_some() && open.completed_at.is_none());
+    assert!(done.completed_at.is_some());
+}